@@ -1,11 +1,52 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::hash::Hash;
+use std::sync::{Mutex, PoisonError};
 use std::time::{Duration, Instant};
 
-/// Generic cache with TTL
+/// Generic cache with TTL, optionally bounded to its most recently used entries
 pub struct Cache<K, V> {
     data: HashMap<K, (V, Instant)>,
     ttl: Duration,
+    /// Least-recently-used entries beyond this are evicted (None = unbounded)
+    max_entries: Option<usize>,
+    /// Only maintained when bounded; `get` updates it through a shared reference,
+    /// behind a `Mutex` so the cache stays `Sync`
+    recency: Mutex<Recency<K>>,
+}
+
+/// Use order of a bounded cache's keys
+struct Recency<K> {
+    tick: u64,
+    /// Tick of last use -> key, oldest first
+    order: BTreeMap<u64, K>,
+    ticks: HashMap<K, u64>,
+}
+
+impl<K: Eq + Hash + Clone> Recency<K> {
+    fn touch(&mut self, key: &K) {
+        self.tick += 1;
+        if let Some(previous) = self.ticks.insert(key.clone(), self.tick) {
+            self.order.remove(&previous);
+        }
+        self.order.insert(self.tick, key.clone());
+    }
+    
+    fn remove(&mut self, key: &K) {
+        if let Some(tick) = self.ticks.remove(key) {
+            self.order.remove(&tick);
+        }
+    }
+    
+    fn pop_oldest(&mut self) -> Option<K> {
+        let (_, key) = self.order.pop_first()?;
+        self.ticks.remove(&key);
+        Some(key)
+    }
+    
+    fn clear(&mut self) {
+        self.order.clear();
+        self.ticks.clear();
+    }
 }
 
 impl<K, V> Cache<K, V>
@@ -17,29 +58,102 @@ where
         Self {
             data: HashMap::new(),
             ttl: Duration::from_secs(ttl_seconds),
+            max_entries: None,
+            recency: Mutex::new(Recency {
+                tick: 0,
+                order: BTreeMap::new(),
+                ticks: HashMap::new(),
+            }),
         }
     }
+    
+    /// Keep at most `max_entries`, evicting the least recently used (read or written) first
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
 
     pub fn get(&self, key: &K) -> Option<V> {
+        let now = Instant::now();
         self.data.get(key).and_then(|(value, timestamp)| {
-            if timestamp.elapsed() < self.ttl {
+            if self.is_fresh(*timestamp, now) {
+                if self.max_entries.is_some() {
+                    // Recency is only an eviction hint, so a poisoned lock is still usable
+                    self.recency.lock().unwrap_or_else(PoisonError::into_inner).touch(key);
+                }
                 Some(value.clone())
             } else {
                 None
             }
         })
     }
+    
+    /// Return the cached value if fresh, otherwise compute, store and return it
+    pub fn get_or_insert_with<F>(&mut self, key: K, f: F) -> V
+    where
+        F: FnOnce() -> V,
+    {
+        if let Some(value) = self.get(&key) {
+            return value;
+        }
+        
+        let value = f();
+        self.insert(key, value.clone());
+        value
+    }
+    
+    /// Drop every expired entry; `get` only skips them, it never frees them
+    pub fn prune_expired(&mut self) {
+        self.prune_expired_at(Instant::now());
+    }
+    
+    fn prune_expired_at(&mut self, now: Instant) {
+        let ttl = self.ttl;
+        let recency = self.recency.get_mut().unwrap_or_else(PoisonError::into_inner);
+        self.data.retain(|key, (_, timestamp)| {
+            let fresh = now.saturating_duration_since(*timestamp) < ttl;
+            if !fresh {
+                recency.remove(key);
+            }
+            fresh
+        });
+    }
+    
+    fn is_fresh(&self, timestamp: Instant, now: Instant) -> bool {
+        now.saturating_duration_since(timestamp) < self.ttl
+    }
 
     pub fn insert(&mut self, key: K, value: V) {
-        self.data.insert(key, (value, Instant::now()));
+        let Some(max_entries) = self.max_entries else {
+            self.data.insert(key, (value, Instant::now()));
+            return;
+        };
+        
+        let now = Instant::now();
+        self.recency.get_mut().unwrap_or_else(PoisonError::into_inner).touch(&key);
+        self.data.insert(key, (value, now));
+        
+        // Expired entries go before any live one
+        if self.data.len() > max_entries {
+            self.prune_expired_at(now);
+        }
+        let recency = self.recency.get_mut().unwrap_or_else(PoisonError::into_inner);
+        while self.data.len() > max_entries {
+            match recency.pop_oldest() {
+                Some(oldest) => self.data.remove(&oldest),
+                None => break,
+            };
+        }
     }
 
     pub fn remove(&mut self, key: &K) {
         self.data.remove(key);
+        self.recency.get_mut().unwrap_or_else(PoisonError::into_inner).remove(key);
     }
 
     pub fn clear(&mut self) {
         self.data.clear();
+        self.recency.get_mut().unwrap_or_else(PoisonError::into_inner).clear();
     }
     
     pub fn len(&self) -> usize {
@@ -50,3 +164,110 @@ where
         self.data.is_empty()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_get_or_insert_with() {
+        let mut cache: Cache<&str, u32> = Cache::new(60);
+        let mut calls = 0;
+        
+        assert_eq!(cache.get_or_insert_with("a", || { calls += 1; 1 }), 1);
+        assert_eq!(cache.get_or_insert_with("a", || { calls += 1; 2 }), 1);
+        assert_eq!(calls, 1);
+        
+        // An expired entry is recomputed
+        let mut cache: Cache<&str, u32> = Cache::new(0);
+        cache.insert("a", 1);
+        assert_eq!(cache.get_or_insert_with("a", || 2), 2);
+    }
+    
+    #[test]
+    fn test_prune_expired() {
+        let mut cache: Cache<&str, u32> = Cache::new(0);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        assert_eq!(cache.len(), 2);
+        
+        cache.prune_expired();
+        assert!(cache.is_empty());
+        
+        let mut cache: Cache<&str, u32> = Cache::new(60);
+        cache.insert("a", 1);
+        cache.prune_expired();
+        assert_eq!(cache.len(), 1);
+    }
+    
+    #[test]
+    fn test_max_entries_evicts_least_recently_used() {
+        let mut cache: Cache<&str, u32> = Cache::new(60).with_max_entries(2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        
+        // Reading "a" makes "b" the least recently used
+        assert_eq!(cache.get(&"a"), Some(1));
+        cache.insert("c", 3);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&"b"), None);
+        
+        // Overwriting counts as a use too
+        cache.insert("a", 10);
+        cache.insert("d", 4);
+        assert_eq!(cache.get(&"c"), None);
+        assert_eq!(cache.get(&"a"), Some(10));
+        assert_eq!(cache.get(&"d"), Some(4));
+        
+        // Removed keys don't count toward eviction order
+        cache.remove(&"a");
+        cache.insert("e", 5);
+        assert_eq!((cache.get(&"d"), cache.get(&"e")), (Some(4), Some(5)));
+    }
+    
+    #[test]
+    fn test_unbounded_by_default() {
+        let mut cache: Cache<u32, u32> = Cache::new(60);
+        for i in 0..1_000 {
+            cache.insert(i, i);
+        }
+        assert_eq!(cache.len(), 1_000);
+        assert!(cache.recency.lock().unwrap().order.is_empty());
+    }
+    
+    #[test]
+    fn test_max_entries_evicts_expired_before_live() {
+        let mut cache: Cache<&str, u32> = Cache::new(60).with_max_entries(2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        
+        // "b" is the most recently used but expired, so it goes instead of "a"
+        cache.data.get_mut("b").unwrap().1 = Instant::now().checked_sub(Duration::from_secs(61)).unwrap();
+        cache.insert("c", 3);
+        assert_eq!(cache.len(), 2);
+        assert_eq!((cache.get(&"a"), cache.get(&"c")), (Some(1), Some(3)));
+        assert!(!cache.data.contains_key("b"));
+    }
+    
+    #[test]
+    fn test_cache_is_shareable_across_tasks() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Cache<String, u64>>();
+    }
+    
+    #[test]
+    fn test_prune_expired_at_ttl_edge() {
+        let mut cache: Cache<&str, u32> = Cache::new(10);
+        cache.insert("a", 1);
+        let inserted = cache.data["a"].1;
+        
+        // Just inside the TTL the entry survives
+        cache.prune_expired_at(inserted + Duration::from_secs(10) - Duration::from_nanos(1));
+        assert_eq!(cache.len(), 1);
+        
+        // Exactly at the TTL it is expired, matching `get`
+        assert!(!cache.is_fresh(inserted, inserted + Duration::from_secs(10)));
+        cache.prune_expired_at(inserted + Duration::from_secs(10));
+        assert!(cache.is_empty());
+    }
+}
@@ -1,20 +1,148 @@
 use anyhow::{anyhow, Result};
-use reqwest;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
 use std::env;
-use std::time::Duration;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::models::market::{MarketConfig, TokenCount};
+use crate::models::market::{KTokenReserve, MarketConfig, SlippageConfig, TokenCount};
+use crate::oracle::StalenessConfig;
+use crate::utils::{http_client_builder, HttpClientConfig};
+use crate::wallet::rebalance::BaseToken;
 
 /// Configuration for the liquidator bot
 #[derive(Debug, Clone)]
 pub struct Config {
     pub app: String,
     pub rpc_endpoint: String,
-    pub secret_path: String,
-    pub markets_filter: Option<String>,
+    /// Wallet keypair files; markets are assigned to them round-robin
+    pub secret_paths: Vec<String>,
+    /// Only fetch these market addresses from the Solend API (empty = all markets)
+    pub markets_filter: Vec<String>,
+    /// File the Solend markets response is cached in between runs
+    pub markets_cache_path: String,
+    /// Reuse cached markets younger than this many seconds (0 disables the cache)
+    pub markets_cache_ttl_secs: u64,
+    /// Local JSON array of markets used when the Solend API can't be reached
+    pub markets_fallback_path: Option<String>,
     pub targets: Vec<TokenCount>,
     pub throttle_ms: u64,
+    /// Milliseconds between liquidation transactions within an epoch (0 disables)
+    pub inter_liquidation_throttle_ms: u64,
     pub rebalance_padding: f64,
+    /// Token rebalancing buys with and sells into
+    pub rebalance_base_token: String,
+    /// Mint of `rebalance_base_token`, for tokens the markets don't list
+    pub rebalance_base_mint: Option<Pubkey>,
+    /// Maximum Pyth confidence / price ratio before a price is rejected
+    pub max_conf_ratio: f64,
+    /// Maximum Pyth/Switchboard divergence (percent) before a token is untrusted
+    pub oracle_tolerance_percent: f64,
+    /// Oracle staleness bound in slots per token symbol (ORACLE_MAX_SLOT_AGE_PER_TOKEN),
+    /// falling back to ORACLE_MAX_SLOT_AGE
+    pub oracle_staleness: StalenessConfig,
+    /// Decide obligation health on Pyth EMA prices instead of spot
+    pub use_ema_for_health: bool,
+    pub ktokens: Vec<KTokenReserve>,
+    pub compute_unit_limit: u32,
+    /// Size each liquidation's compute unit limit from a simulation, capped by `compute_unit_limit`
+    pub estimate_compute_units: bool,
+    /// Headroom added to simulated compute units, in percent
+    pub compute_unit_margin_percent: u32,
+    pub priority_fee_micro_lamports: u64,
+    pub simulate_before_send: bool,
+    /// Leave out refresh instructions for reserves already refreshed in the current slot
+    pub skip_fresh_reserve_refresh: bool,
+    /// Give up waiting for a sent liquidation after this many seconds
+    pub confirm_timeout_secs: u64,
+    /// Re-broadcast an unconfirmed liquidation this often (milliseconds)
+    pub resend_interval_ms: u64,
+    /// Reuse the shared blockhash while it has more than this many blocks of validity left
+    pub blockhash_refresh_margin_blocks: u64,
+    /// Warn (and notify) when a wallet's native SOL for fees drops below this
+    pub min_sol_balance: f64,
+    /// Minimum estimated net profit (USD) required to liquidate
+    pub min_profit_usd: f64,
+    /// Skip unhealthy obligations borrowing less than this (USD) as dust
+    pub min_borrowed_value_usd: f64,
+    /// Max fraction of a borrow repayable in a single liquidation
+    pub close_factor: f64,
+    /// Only liquidate once borrows exceed the unhealthy threshold by this fraction
+    pub liquidation_margin: f64,
+    /// Flag obligations whose borrows are within this fraction below the threshold
+    pub health_warn_margin: f64,
+    /// Obligations last refreshed more than this many slots ago have interest accrued locally (0 disables)
+    pub max_obligation_slot_age: u64,
+    /// Only liquidate obligations owned by these wallets (None = any owner)
+    pub obligation_owner_allowlist: Option<Vec<Pubkey>>,
+    /// Never liquidate obligations owned by these wallets
+    pub obligation_owner_denylist: Vec<Pubkey>,
+    /// Only repay debt in these token symbols (empty = any token)
+    pub repay_token_allowlist: Vec<String>,
+    /// Only seize collateral in these token symbols (empty = any token)
+    pub withdraw_token_allowlist: Vec<String>,
+    /// Swap seized collateral back to the repay token after each liquidation
+    pub auto_swap_collateral: bool,
+    /// Swap leftover token balances into USDC once per epoch
+    pub sweep_dust: bool,
+    /// Only sweep balances worth at least this much (USD)
+    pub min_sweep_usd: f64,
+    /// Address lookup table used to compile v0 liquidation transactions
+    pub lookup_table: Option<String>,
+    /// Wallet receiving redeemed collateral instead of the signer (None = the signer)
+    pub proceeds_wallet: Option<Pubkey>,
+    /// Push obligation updates over WebSocket instead of polling every epoch
+    pub use_websocket: bool,
+    pub ws_endpoint: String,
+    pub rpc_max_attempts: u32,
+    pub rpc_retry_base_ms: u64,
+    /// Outbound RPC request budget shared by all market tasks (0 = unlimited)
+    pub rpc_requests_per_second: u32,
+    /// Give up on a single RPC request after this long
+    pub rpc_timeout_secs: u64,
+    /// Timeouts and pooling for the Solend markets API and Jupiter
+    pub http: HttpClientConfig,
+    /// Commitment for obligation, reserve and account reads
+    pub read_commitment: CommitmentConfig,
+    /// Commitment liquidation transactions are confirmed at
+    pub confirm_commitment: CommitmentConfig,
+    /// Send liquidations as Jito bundles instead of through the RPC
+    pub use_jito: bool,
+    pub jito_block_engine_url: String,
+    /// Tip paid to the block engine with each bundled liquidation
+    pub jito_tip_lamports: u64,
+    /// Port for the Prometheus exporter; disabled when unset
+    pub metrics_port: Option<u16>,
+    /// Port for the /health endpoint; disabled when unset
+    pub health_port: Option<u16>,
+    /// Report unhealthy when no epoch has succeeded for this long
+    pub health_stale_secs: u64,
+    /// How long fetched reserves are reused before re-fetching, per market and per reserve
+    pub reserve_cache_ttl_secs: u64,
+    /// Reuse healthy obligations' account data, re-downloading everything this often (0 fetches all every epoch)
+    pub obligation_resync_secs: u64,
+    /// Jupiter slippage per token symbol (SLIPPAGE_BPS), falling back to DEFAULT_SLIPPAGE_BPS
+    pub slippage: SlippageConfig,
+    /// Priority fee for Jupiter swaps; Jupiter's default when unset
+    pub swap_priority_fee_lamports: Option<u64>,
+    /// Jupiter API base URL; the public endpoint unless self-hosted or paid
+    pub jupiter_api_url: String,
+    /// Key sent with every Jupiter request, required by the paid API
+    pub jupiter_api_key: Option<String>,
+    /// Jupiter swap attempts, re-quoting after an expired route
+    pub swap_max_attempts: u32,
+    /// Refuse Jupiter swaps quoted above this price impact, in percent
+    pub max_price_impact_pct: f64,
+    /// Telegram bot credentials; notifications are off unless both are set
+    pub telegram_bot_token: Option<String>,
+    pub telegram_chat_id: Option<String>,
+    /// Discord webhook for liquidation and oracle notifications
+    pub discord_webhook_url: Option<String>,
+    /// Append per-epoch metrics as JSON lines to this file
+    pub metrics_file: Option<String>,
 }
 
 impl Config {
@@ -35,10 +163,23 @@ impl Config {
         let rpc_endpoint = env::var("RPC_ENDPOINT")
             .map_err(|_| anyhow!("RPC_ENDPOINT must be set in environment"))?;
         
-        let secret_path = env::var("SECRET_PATH")
-            .map_err(|_| anyhow!("SECRET_PATH must be set in environment"))?;
+        // Left empty here so read-only modes run without a wallet; main requires one otherwise
+        let secret_paths = match env::var("SECRET_PATHS") {
+            Ok(paths) => Self::parse_secret_paths(&paths),
+            Err(_) => env::var("SECRET_PATH").map(|path| vec![path]).unwrap_or_default(),
+        };
         
-        let markets_filter = env::var("MARKETS").ok();
+        let markets_filter = Self::parse_market_ids(&env::var("MARKETS").unwrap_or_default())?;
+        
+        let markets_cache_path = env::var("MARKETS_CACHE_PATH")
+            .unwrap_or_else(|_| "markets-cache.json".to_string());
+        
+        let markets_cache_ttl_secs = env::var("MARKETS_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(3_600);
+        
+        let markets_fallback_path = env::var("MARKETS_FALLBACK_PATH").ok();
         
         let targets = Self::parse_targets(&env::var("TARGETS").unwrap_or_default());
         
@@ -47,47 +188,588 @@ impl Config {
             .and_then(|s| s.parse().ok())
             .unwrap_or(0);
         
+        let inter_liquidation_throttle_ms = env::var("INTER_LIQUIDATION_THROTTLE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0);
+        
         let rebalance_padding = env::var("REBALANCE_PADDING")
             .ok()
             .and_then(|s| s.parse().ok())
             .unwrap_or(0.2);
         
+        let rebalance_base_token = env::var("REBALANCE_BASE_TOKEN")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| crate::wallet::rebalance::DEFAULT_BASE_TOKEN.to_string());
+        
+        let rebalance_base_mint = env::var("REBALANCE_BASE_MINT")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .map(|s| Pubkey::from_str(&s).map_err(|_| anyhow!("Invalid REBALANCE_BASE_MINT: {}", s)))
+            .transpose()?;
+        
+        let max_conf_ratio = env::var("MAX_CONF_RATIO")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(crate::oracle::DEFAULT_MAX_CONF_RATIO);
+        
+        let ktokens = Self::parse_ktokens(&env::var("KTOKENS").unwrap_or_default());
+        
+        let compute_unit_limit = env::var("COMPUTE_UNIT_LIMIT")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(400_000);
+        
+        let estimate_compute_units = env::var("ESTIMATE_COMPUTE_UNITS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(true);
+        
+        let compute_unit_margin_percent = env::var("COMPUTE_UNIT_MARGIN_PERCENT")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(20);
+        
+        let priority_fee_micro_lamports = env::var("PRIORITY_FEE_MICRO_LAMPORTS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(10_000);
+        
+        let simulate_before_send = env::var("SIMULATE_BEFORE_SEND")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(true);
+        
+        let skip_fresh_reserve_refresh = env::var("SKIP_FRESH_RESERVE_REFRESH")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(false);
+        
+        let confirm_timeout_secs = env::var("CONFIRM_TIMEOUT_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(60);
+        
+        let resend_interval_ms = env::var("RESEND_INTERVAL_MS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(2_000);
+        
+        let blockhash_refresh_margin_blocks = env::var("BLOCKHASH_REFRESH_MARGIN_BLOCKS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(crate::rpc::blockhash::DEFAULT_REFRESH_MARGIN_BLOCKS);
+        
+        let min_sol_balance = env::var("MIN_SOL_BALANCE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0.05);
+        
+        let min_profit_usd = env::var("MIN_PROFIT_USD")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(1.0);
+        
+        let min_borrowed_value_usd = env::var("MIN_BORROWED_VALUE_USD")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0.0);
+        
+        let close_factor = env::var("CLOSE_FACTOR")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0.2);
+        
+        let liquidation_margin = env::var("LIQUIDATION_MARGIN")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0.0);
+        
+        let health_warn_margin = env::var("HEALTH_WARN_MARGIN")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0.05);
+        
+        let max_obligation_slot_age = env::var("MAX_OBLIGATION_SLOT_AGE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0);
+        
+        let obligation_owner_allowlist =
+            Some(Self::parse_owners(&env::var("OBLIGATION_OWNER_ALLOWLIST").unwrap_or_default())?)
+                .filter(|owners| !owners.is_empty());
+        
+        let obligation_owner_denylist =
+            Self::parse_owners(&env::var("OBLIGATION_OWNER_DENYLIST").unwrap_or_default())?;
+        
+        let repay_token_allowlist = Self::parse_symbols(&env::var("REPAY_TOKEN_ALLOWLIST").unwrap_or_default());
+        let withdraw_token_allowlist = Self::parse_symbols(&env::var("WITHDRAW_TOKEN_ALLOWLIST").unwrap_or_default());
+        
+        let auto_swap_collateral = env::var("AUTO_SWAP_COLLATERAL")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(false);
+        
+        let sweep_dust = env::var("SWEEP_DUST")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(false);
+        
+        let min_sweep_usd = env::var("MIN_SWEEP_USD")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(1.0);
+        
+        let lookup_table = env::var("LOOKUP_TABLE").ok();
+        
+        let proceeds_wallet = env::var("PROCEEDS_WALLET")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .map(|s| Pubkey::from_str(&s).map_err(|_| anyhow!("Invalid PROCEEDS_WALLET: {}", s)))
+            .transpose()?;
+        
+        let use_websocket = env::var("USE_WEBSOCKET")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(false);
+        
+        let ws_endpoint = env::var("WS_ENDPOINT")
+            .unwrap_or_else(|_| Self::derive_ws_endpoint(&rpc_endpoint));
+        
+        let rpc_max_attempts = env::var("RPC_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(5);
+        
+        let rpc_retry_base_ms = env::var("RPC_RETRY_BASE_MS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(200);
+        
+        let rpc_requests_per_second = env::var("RPC_REQUESTS_PER_SECOND")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0);
+        
+        let rpc_timeout_secs = env::var("RPC_TIMEOUT_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(20);
+        
+        let default_http = HttpClientConfig::default();
+        let http = HttpClientConfig {
+            connect_timeout: env::var("HTTP_CONNECT_TIMEOUT_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(default_http.connect_timeout),
+            timeout: env::var("HTTP_TIMEOUT_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(default_http.timeout),
+            pool_max_idle_per_host: env::var("HTTP_POOL_MAX_IDLE_PER_HOST")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default_http.pool_max_idle_per_host),
+        };
+        
+        let read_commitment = Self::parse_commitment("READ_COMMITMENT")?;
+        let confirm_commitment = Self::parse_commitment("CONFIRM_COMMITMENT")?;
+        
+        let use_jito = env::var("USE_JITO")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(false);
+        
+        let jito_block_engine_url = env::var("JITO_BLOCK_ENGINE_URL")
+            .unwrap_or_else(|_| crate::rpc::jito::DEFAULT_BLOCK_ENGINE_URL.to_string());
+        
+        let jito_tip_lamports = env::var("JITO_TIP_LAMPORTS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(10_000);
+        
+        let metrics_port = env::var("METRICS_PORT")
+            .ok()
+            .and_then(|s| s.parse().ok());
+        
+        let health_port = env::var("HEALTH_PORT")
+            .ok()
+            .and_then(|s| s.parse().ok());
+        
+        let health_stale_secs = env::var("HEALTH_STALE_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(300);
+        
+        let reserve_cache_ttl_secs = env::var("RESERVE_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(300);
+        
+        let obligation_resync_secs = env::var("OBLIGATION_RESYNC_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0);
+        
+        let slippage = SlippageConfig {
+            default_bps: env::var("DEFAULT_SLIPPAGE_BPS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(100),
+            per_token: Self::parse_per_token(&env::var("SLIPPAGE_BPS").unwrap_or_default()),
+        };
+        
+        let swap_priority_fee_lamports = env::var("SWAP_PRIORITY_FEE_LAMPORTS")
+            .ok()
+            .and_then(|s| s.parse().ok());
+        
+        let jupiter_api_url = env::var("JUPITER_API_URL")
+            .unwrap_or_else(|_| crate::wallet::swap::JUPITER_API_URL.to_string());
+        
+        let jupiter_api_key = env::var("JUPITER_API_KEY").ok();
+        
+        let swap_max_attempts = env::var("SWAP_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(crate::wallet::swap::DEFAULT_MAX_SWAP_ATTEMPTS);
+        
+        let max_price_impact_pct = env::var("MAX_PRICE_IMPACT_PCT")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(crate::wallet::swap::DEFAULT_MAX_PRICE_IMPACT_PCT);
+        
+        let telegram_bot_token = env::var("TELEGRAM_BOT_TOKEN").ok();
+        let telegram_chat_id = env::var("TELEGRAM_CHAT_ID").ok();
+        let discord_webhook_url = env::var("DISCORD_WEBHOOK_URL").ok();
+        let metrics_file = env::var("METRICS_FILE").ok();
+        
+        let oracle_tolerance_percent = env::var("ORACLE_TOLERANCE_PERCENT")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(crate::oracle::DEFAULT_ORACLE_TOLERANCE_PERCENT);
+        
+        let oracle_staleness = StalenessConfig {
+            default_slots: env::var("ORACLE_MAX_SLOT_AGE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(crate::oracle::DEFAULT_MAX_SLOT_AGE),
+            per_token: Self::parse_per_token(&env::var("ORACLE_MAX_SLOT_AGE_PER_TOKEN").unwrap_or_default()),
+        };
+        
+        let use_ema_for_health = env::var("USE_EMA_FOR_HEALTH")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(false);
+        
         Ok(Config {
             app,
             rpc_endpoint,
-            secret_path,
+            secret_paths,
             markets_filter,
+            markets_cache_path,
+            markets_cache_ttl_secs,
+            markets_fallback_path,
             targets,
             throttle_ms,
+            inter_liquidation_throttle_ms,
             rebalance_padding,
+            rebalance_base_token,
+            rebalance_base_mint,
+            max_conf_ratio,
+            oracle_tolerance_percent,
+            oracle_staleness,
+            use_ema_for_health,
+            ktokens,
+            compute_unit_limit,
+            estimate_compute_units,
+            compute_unit_margin_percent,
+            priority_fee_micro_lamports,
+            simulate_before_send,
+            skip_fresh_reserve_refresh,
+            confirm_timeout_secs,
+            resend_interval_ms,
+            blockhash_refresh_margin_blocks,
+            min_sol_balance,
+            min_profit_usd,
+            min_borrowed_value_usd,
+            close_factor,
+            liquidation_margin,
+            health_warn_margin,
+            max_obligation_slot_age,
+            obligation_owner_allowlist,
+            obligation_owner_denylist,
+            repay_token_allowlist,
+            withdraw_token_allowlist,
+            auto_swap_collateral,
+            sweep_dust,
+            min_sweep_usd,
+            lookup_table,
+            proceeds_wallet,
+            use_websocket,
+            ws_endpoint,
+            rpc_max_attempts,
+            rpc_retry_base_ms,
+            rpc_requests_per_second,
+            rpc_timeout_secs,
+            http,
+            read_commitment,
+            confirm_commitment,
+            use_jito,
+            jito_block_engine_url,
+            jito_tip_lamports,
+            metrics_port,
+            health_port,
+            health_stale_secs,
+            reserve_cache_ttl_secs,
+            obligation_resync_secs,
+            slippage,
+            swap_priority_fee_lamports,
+            jupiter_api_url,
+            jupiter_api_key,
+            swap_max_attempts,
+            max_price_impact_pct,
+            telegram_bot_token,
+            telegram_chat_id,
+            discord_webhook_url,
+            metrics_file,
         })
     }
     
     /// Parse target distribution from TARGETS env var
-    /// Format: "USDC:100 USDT:5 SOL:0.5"
+    /// Format: "USDC:100 USDT:5 SOL:0.5", optionally with absolute bounds as
+    /// "SOL:1:0.8:1.5" (target:min:max), where either bound may be left empty
     fn parse_targets(targets_str: &str) -> Vec<TokenCount> {
+        let bound = |s: &str| if s.is_empty() { Ok(None) } else { s.parse::<f64>().map(Some) };
+        
         targets_str
             .split_whitespace()
             .filter_map(|dist| {
                 let parts: Vec<&str> = dist.split(':').collect();
-                if parts.len() == 2 {
-                    let symbol = parts[0].to_string();
-                    let target = parts[1].parse::<f64>().ok()?;
-                    Some(TokenCount { symbol, target })
-                } else {
-                    None
+                let (symbol, target, min, max) = match parts.as_slice() {
+                    [symbol, target] => (symbol, target, "", ""),
+                    [symbol, target, min, max] => (symbol, target, *min, *max),
+                    _ => return None,
+                };
+                let target = TokenCount {
+                    symbol: symbol.to_string(),
+                    target: target.parse::<f64>().ok()?,
+                    min: bound(min).ok()?,
+                    max: bound(max).ok()?,
+                };
+                
+                // Rebalancing buys or sells to the target, which must lie within the bounds
+                let (lower, upper) = target.bounds(0.0);
+                if lower > target.target || upper < target.target {
+                    log::warn!("Ignoring TARGETS entry {}: target outside its bounds", dist);
+                    return None;
                 }
+                Some(target)
+            })
+            .collect()
+    }
+    
+    /// Derive WebSocket endpoint from the HTTP RPC endpoint
+    fn derive_ws_endpoint(rpc_endpoint: &str) -> String {
+        if let Some(rest) = rpc_endpoint.strip_prefix("https://") {
+            format!("wss://{}", rest)
+        } else if let Some(rest) = rpc_endpoint.strip_prefix("http://") {
+            format!("ws://{}", rest)
+        } else {
+            rpc_endpoint.to_string()
+        }
+    }
+    
+    /// Parse known kToken reserves from KTOKENS env var
+    /// Format: "<kToken mint>:<Kamino reserve> <kToken mint>:<Kamino reserve>"
+    fn parse_ktokens(ktokens_str: &str) -> Vec<KTokenReserve> {
+        ktokens_str
+            .split_whitespace()
+            .filter_map(|entry| {
+                let (mint, reserve) = entry.split_once(':')?;
+                Some(KTokenReserve {
+                    mint: mint.to_string(),
+                    reserve: reserve.to_string(),
+                })
+            })
+            .collect()
+    }
+    
+    /// Parse per-token values such as SLIPPAGE_BPS, skipping malformed entries
+    /// Format: "USDC:10 USDT:10 mSOL:300"
+    fn parse_per_token<T: FromStr>(values_str: &str) -> HashMap<String, T> {
+        values_str
+            .split_whitespace()
+            .filter_map(|entry| {
+                let (symbol, value) = entry.split_once(':')?;
+                Some((symbol.to_string(), value.parse().ok()?))
+            })
+            .collect()
+    }
+    
+    /// Read a commitment level (processed, confirmed or finalized) from `var`
+    /// Defaults to confirmed when unset
+    fn parse_commitment(var: &str) -> Result<CommitmentConfig> {
+        match env::var(var) {
+            Ok(level) => CommitmentConfig::from_str(&level)
+                .map_err(|_| anyhow!("Invalid {}: {}. Must be processed, confirmed, or finalized", var, level)),
+            Err(_) => Ok(CommitmentConfig::confirmed()),
+        }
+    }
+    
+    /// Parse wallet keypair files from SECRET_PATHS env var
+    /// Format: "/path/a.json,/path/b.json"
+    fn parse_secret_paths(paths_str: &str) -> Vec<String> {
+        paths_str
+            .split(',')
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+            .map(String::from)
+            .collect()
+    }
+    
+    /// Parse obligation owner wallets from a comma-separated list of addresses
+    fn parse_owners(owners_str: &str) -> Result<Vec<Pubkey>> {
+        owners_str
+            .split(',')
+            .map(str::trim)
+            .filter(|owner| !owner.is_empty())
+            .map(|owner| Pubkey::from_str(owner).map_err(|_| anyhow!("Invalid obligation owner: {}", owner)))
+            .collect()
+    }
+    
+    /// Parse market addresses from MARKETS, failing fast on typos
+    fn parse_market_ids(ids_str: &str) -> Result<Vec<String>> {
+        ids_str
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| match Pubkey::from_str(id) {
+                Ok(_) => Ok(id.to_string()),
+                Err(_) => Err(anyhow!("Invalid market id in MARKETS: {} (expected a market address)", id)),
             })
             .collect()
     }
     
-    /// Fetch markets from Solend API
-    pub async fn fetch_markets(&self) -> Result<Vec<MarketConfig>> {
+    /// Parse token symbols from a comma-separated list, e.g. "USDC,USDT"
+    fn parse_symbols(symbols_str: &str) -> Vec<String> {
+        symbols_str
+            .split(',')
+            .map(str::trim)
+            .filter(|symbol| !symbol.is_empty())
+            .map(String::from)
+            .collect()
+    }
+    
+    /// Whether seized collateral is swapped back after a liquidation
+    /// It can't be when it goes to a separate proceeds wallet the signer doesn't control
+    pub fn swaps_seized_collateral(&self) -> bool {
+        self.auto_swap_collateral && self.proceeds_wallet.is_none()
+    }
+    
+    /// Token rebalancing and dust sweeping swap through, REBALANCE_BASE_TOKEN
+    /// Its mint is REBALANCE_BASE_MINT, else the markets' mint for that symbol,
+    /// else this deployment's USDC when that's the base token
+    pub fn rebalance_base(&self, token_mints: &HashMap<String, (Pubkey, u8)>) -> Result<BaseToken> {
+        let mint = match (self.rebalance_base_mint.as_ref(), token_mints.get(&self.rebalance_base_token)) {
+            (Some(mint), _) | (None, Some((mint, _))) => *mint,
+            (None, None) if self.rebalance_base_token == crate::wallet::rebalance::DEFAULT_BASE_TOKEN => {
+                crate::wallet::swap::get_usdc_mint(&self.app)?
+            }
+            (None, None) => {
+                return Err(anyhow!(
+                    "No mint for rebalance base token {}; set REBALANCE_BASE_MINT",
+                    self.rebalance_base_token
+                ))
+            }
+        };
+        Ok(BaseToken { symbol: self.rebalance_base_token.clone(), mint })
+    }
+    
+    /// Whether the token allowlists permit repaying `repay_symbol` to seize `withdraw_symbol`
+    pub fn is_pair_allowed(&self, repay_symbol: &str, withdraw_symbol: &str) -> bool {
+        is_symbol_allowed(&self.repay_token_allowlist, repay_symbol)
+            && is_symbol_allowed(&self.withdraw_token_allowlist, withdraw_symbol)
+    }
+    
+    /// Whether the owner allowlist and denylist permit liquidating `owner`'s obligations
+    pub fn is_owner_allowed(&self, owner: &Pubkey) -> bool {
+        let allowed = self
+            .obligation_owner_allowlist
+            .as_ref()
+            .is_none_or(|allowlist| allowlist.contains(owner));
+        allowed && !self.obligation_owner_denylist.contains(owner)
+    }
+    
+    /// Fetch markets from the Solend API, or from the local cache while it's fresh
+    /// A cache hit is refreshed in the background for the next start; `refresh` skips the cache
+    pub async fn fetch_markets(&self, refresh: bool) -> Result<Vec<MarketConfig>> {
         let url = self.get_markets_url();
+        let path = self.markets_cache_path.clone();
+        
+        if self.markets_cache_ttl_secs == 0 {
+            return match Self::fetch_markets_from_api(&url, &self.http).await {
+                Ok(markets) => Ok(markets),
+                Err(e) => self.fallback_markets(e),
+            };
+        }
+        
+        if !refresh {
+            if let Some(markets) = MarketsCache::load(&path, &url, self.markets_cache_ttl_secs, unix_now()) {
+                log::info!("Loaded {} markets from cache {}", markets.len(), path);
+                
+                let http = self.http;
+                tokio::spawn(async move {
+                    match Self::fetch_markets_from_api(&url, &http).await {
+                        Ok(markets) => {
+                            if let Err(e) = MarketsCache::save(&path, &url, &markets) {
+                                log::warn!("Failed to update markets cache {}: {}", path, e);
+                            }
+                        }
+                        Err(e) => log::warn!("Background markets refresh failed: {}", e),
+                    }
+                });
+                
+                return Ok(markets);
+            }
+        }
+        
+        let markets = match Self::fetch_markets_from_api(&url, &self.http).await {
+            Ok(markets) => markets,
+            Err(e) => return self.fallback_markets(e),
+        };
+        if let Err(e) = MarketsCache::save(&path, &url, &markets) {
+            log::warn!("Failed to write markets cache {}: {}", path, e);
+        }
+        
+        Ok(markets)
+    }
+    
+    /// Markets from MARKETS_FALLBACK_PATH once the API fetch failed with `api_error`
+    fn fallback_markets(&self, api_error: anyhow::Error) -> Result<Vec<MarketConfig>> {
+        let Some(path) = &self.markets_fallback_path else {
+            return Err(api_error);
+        };
         
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(30))
-            .build()?;
+        let mut markets = load_markets_file(path)
+            .map_err(|e| anyhow!("{}; fallback markets unusable: {}", api_error, e))?;
+        if !self.markets_filter.is_empty() {
+            markets.retain(|m| self.markets_filter.contains(&m.address));
+        }
+        
+        log::warn!(
+            "⚠️  Solend API unavailable ({}). Running from local markets file {}, which may be stale ⚠️",
+            api_error,
+            path
+        );
+        Ok(markets)
+    }
+    
+    /// Fetch markets from the Solend API, retrying with exponential backoff
+    async fn fetch_markets_from_api(url: &str, http: &HttpClientConfig) -> Result<Vec<MarketConfig>> {
+        let client = http_client_builder(http).build()?;
         
         let mut attempts = 0;
         let max_attempts = 10;
@@ -96,7 +778,7 @@ impl Config {
         loop {
             attempts += 1;
             
-            match client.get(&url).send().await {
+            match client.get(url).send().await {
                 Ok(response) => {
                     if response.status().is_success() {
                         let markets: Vec<MarketConfig> = response.json().await?;
@@ -121,18 +803,89 @@ impl Config {
     }
     
     /// Get markets API URL based on configuration
+    /// Several markets are requested as `ids=<a>,<b>` with each address encoded
     fn get_markets_url(&self) -> String {
-        if let Some(ref market_ids) = self.markets_filter {
-            format!("https://api.solend.fi/v1/markets/configs?ids={}", market_ids)
-        } else {
+        if self.markets_filter.is_empty() {
             format!(
                 "https://api.solend.fi/v1/markets/configs?scope=solend&deployment={}",
                 self.app
             )
+        } else {
+            let ids: Vec<String> = self
+                .markets_filter
+                .iter()
+                .map(|id| utf8_percent_encode(id, NON_ALPHANUMERIC).to_string())
+                .collect();
+            format!("https://api.solend.fi/v1/markets/configs?ids={}", ids.join(","))
         }
     }
 }
 
+/// Solend markets response persisted between runs
+#[derive(Debug, Serialize, Deserialize)]
+struct MarketsCache {
+    /// API URL the markets came from; changing MARKETS or APP invalidates the cache
+    url: String,
+    /// Unix timestamp of the fetch
+    fetched_at: u64,
+    markets: Vec<MarketConfig>,
+}
+
+impl MarketsCache {
+    /// Cached markets for `url` if younger than `ttl_secs`
+    /// Missing, unreadable or outdated-schema files are treated as a miss
+    fn load(path: &str, url: &str, ttl_secs: u64, now: u64) -> Option<Vec<MarketConfig>> {
+        let contents = std::fs::read_to_string(path).ok()?;
+        let cache: MarketsCache = match serde_json::from_str(&contents) {
+            Ok(cache) => cache,
+            Err(e) => {
+                log::warn!("Ignoring markets cache {}: {}", path, e);
+                return None;
+            }
+        };
+        
+        let fresh = now.saturating_sub(cache.fetched_at) < ttl_secs;
+        (cache.url == url && fresh && !cache.markets.is_empty()).then_some(cache.markets)
+    }
+    
+    /// Write through a temporary file so a crash never leaves a truncated cache
+    fn save(path: &str, url: &str, markets: &[MarketConfig]) -> Result<()> {
+        let cache = MarketsCache {
+            url: url.to_string(),
+            fetched_at: unix_now(),
+            markets: markets.to_vec(),
+        };
+        let tmp_path = format!("{}.tmp", path);
+        std::fs::write(&tmp_path, serde_json::to_vec(&cache)?)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+/// Parse a JSON array of markets in the Solend API's format
+pub(crate) fn load_markets_file(path: &str) -> Result<Vec<MarketConfig>> {
+    let contents = std::fs::read_to_string(path).map_err(|e| anyhow!("Failed to read {}: {}", path, e))?;
+    let markets: Vec<MarketConfig> =
+        serde_json::from_str(&contents).map_err(|e| anyhow!("Invalid markets in {}: {}", path, e))?;
+    
+    if markets.is_empty() {
+        return Err(anyhow!("No markets in {}", path));
+    }
+    Ok(markets)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// An empty allowlist permits every symbol
+fn is_symbol_allowed(allowlist: &[String], symbol: &str) -> bool {
+    allowlist.is_empty() || allowlist.iter().any(|allowed| allowed == symbol)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -145,5 +898,147 @@ mod tests {
         assert_eq!(targets[0].target, 100.0);
         assert_eq!(targets[2].symbol, "SOL");
         assert_eq!(targets[2].target, 0.5);
+        assert_eq!((targets[2].min, targets[2].max), (None, None));
+        
+        let targets = Config::parse_targets("SOL:1:0.8:1.5 ETH:1::2 USDT:100:150:200 BONK:1:2");
+        assert_eq!(targets.len(), 2);
+        assert_eq!((targets[0].min, targets[0].max), (Some(0.8), Some(1.5)));
+        assert_eq!(targets[1].symbol, "ETH");
+        assert_eq!((targets[1].min, targets[1].max), (None, Some(2.0)));
+    }
+
+    #[test]
+    fn test_markets_cache_round_trip() {
+        let path = env::temp_dir().join(format!("markets-cache-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let market: MarketConfig = serde_json::from_value(serde_json::json!({
+            "name": "main", "isPrimary": true, "description": "", "creator": "", "address": "Market",
+            "authorityAddress": "", "owner": "", "reserves": [],
+        }))
+        .unwrap();
+        
+        MarketsCache::save(path, "url", &[market]).unwrap();
+        let now = unix_now();
+        let cached = MarketsCache::load(path, "url", 60, now).unwrap();
+        assert_eq!(cached[0].address, "Market");
+        // Expired, or fetched for different markets
+        assert!(MarketsCache::load(path, "url", 60, now + 60).is_none());
+        assert!(MarketsCache::load(path, "other-url", 60, now).is_none());
+        
+        // An old schema is refetched rather than trusted
+        std::fs::write(path, r#"{"url":"url","fetched_at":0,"markets":[{"name":"main"}]}"#).unwrap();
+        assert!(MarketsCache::load(path, "url", u64::MAX, now).is_none());
+        
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_load_markets_file() {
+        let path = env::temp_dir().join(format!("markets-fallback-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        
+        std::fs::write(path, r#"[{"name": "main", "isPrimary": true, "description": "", "creator": "",
+            "address": "Market", "authorityAddress": "", "owner": "", "reserves": []}]"#).unwrap();
+        assert_eq!(load_markets_file(path).unwrap()[0].name, "main");
+        
+        std::fs::write(path, "[]").unwrap();
+        assert!(load_markets_file(path).is_err());
+        std::fs::write(path, r#"[{"name": "main"}]"#).unwrap();
+        assert!(load_markets_file(path).is_err());
+        
+        std::fs::remove_file(path).unwrap();
+        assert!(load_markets_file(path).is_err());
+    }
+
+    #[test]
+    fn test_parse_ktokens() {
+        let ktokens = Config::parse_ktokens("MintA:ReserveA bogus MintB:ReserveB");
+        assert_eq!(ktokens.len(), 2);
+        assert_eq!(ktokens[0].mint, "MintA");
+        assert_eq!(ktokens[0].reserve, "ReserveA");
+        assert_eq!(ktokens[1].mint, "MintB");
+    }
+
+    #[test]
+    fn test_parse_slippage() {
+        let slippage = SlippageConfig {
+            default_bps: 100,
+            per_token: Config::parse_per_token("USDC:10 bogus mSOL:300 SOL:abc"),
+        };
+        assert_eq!(slippage.per_token.len(), 2);
+        assert_eq!(slippage.bps_for("USDC"), 10);
+        assert_eq!(slippage.bps_for("mSOL"), 300);
+        assert_eq!(slippage.bps_for("SOL"), 100);
+    }
+
+    #[test]
+    fn test_parse_secret_paths() {
+        let paths = Config::parse_secret_paths("/keys/a.json, /keys/b.json,,");
+        assert_eq!(paths, vec!["/keys/a.json", "/keys/b.json"]);
+        assert!(Config::parse_secret_paths(" ").is_empty());
+    }
+
+    #[test]
+    fn test_parse_owners() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let owners = Config::parse_owners(&format!("{}, {},", a, b)).unwrap();
+        assert_eq!(owners, vec![a, b]);
+        assert!(Config::parse_owners("").unwrap().is_empty());
+        assert!(Config::parse_owners("not-a-pubkey").is_err());
+    }
+
+    #[test]
+    fn test_parse_market_ids() {
+        let (a, b) = (Pubkey::new_unique().to_string(), Pubkey::new_unique().to_string());
+        assert_eq!(Config::parse_market_ids(&format!(" {},{} ,", a, b)).unwrap(), vec![a, b]);
+        assert!(Config::parse_market_ids("").unwrap().is_empty());
+        
+        let err = Config::parse_market_ids("main,TURBO").unwrap_err();
+        assert!(err.to_string().contains("Invalid market id in MARKETS: main"));
+    }
+
+    #[test]
+    fn test_symbol_allowlist() {
+        let allowlist = Config::parse_symbols(" USDC,USDT ,");
+        assert_eq!(allowlist, vec!["USDC", "USDT"]);
+        assert!(is_symbol_allowed(&allowlist, "USDT"));
+        assert!(!is_symbol_allowed(&allowlist, "SOL"));
+        assert!(is_symbol_allowed(&[], "SOL"));
+    }
+
+    #[test]
+    fn test_derive_ws_endpoint() {
+        assert_eq!(Config::derive_ws_endpoint("https://rpc.example.com/key"), "wss://rpc.example.com/key");
+        assert_eq!(Config::derive_ws_endpoint("http://localhost:8899"), "ws://localhost:8899");
+    }
+
+    #[cfg(feature = "compression")]
+    #[tokio::test]
+    async fn test_fetch_markets_decodes_gzip_responses() {
+        use std::io::Write;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        let markets = r#"[{"name": "main", "isPrimary": true, "description": "", "creator": "",
+            "address": "Market", "authorityAddress": "", "owner": "", "reserves": []}]"#;
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(markets.as_bytes()).unwrap();
+        
+        let server = MockServer::start().await;
+        // Only clients advertising gzip get the compressed body
+        Mock::given(|request: &wiremock::Request| {
+            request.headers.get("accept-encoding").is_some_and(|v| v.to_str().unwrap_or("").contains("gzip"))
+        })
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-encoding", "gzip")
+                .insert_header("content-type", "application/json")
+                .set_body_bytes(encoder.finish().unwrap()),
+        )
+        .mount(&server)
+        .await;
+        
+        let fetched = Config::fetch_markets_from_api(&server.uri(), &HttpClientConfig::default()).await.unwrap();
+        assert_eq!(fetched.len(), 1);
+        assert_eq!(fetched[0].address, "Market");
     }
 }
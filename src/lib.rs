@@ -5,5 +5,12 @@ pub mod liquidation;
 pub mod wallet;
 pub mod rpc;
 pub mod utils;
+pub mod metrics;
+pub mod cache;
+pub mod notify;
+pub mod report;
+pub mod market_task;
+pub mod replay;
 
 pub use config::Config;
+pub use liquidation::RefreshedObligation;
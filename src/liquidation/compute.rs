@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Solana's per-transaction compute unit cap
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// What a liquidation's compute cost mostly depends on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransactionShape {
+    /// Deposits plus borrows of the obligation, each refreshed before liquidating
+    pub positions: usize,
+    /// Every instruction, so ATA creations and tips count too
+    pub instructions: usize,
+}
+
+/// Compute unit limits learned by simulating liquidations, one per transaction shape
+/// Each shape is simulated once and later liquidations reuse its limit
+pub struct ComputeUnitEstimator {
+    margin_percent: u32,
+    limits: Mutex<HashMap<TransactionShape, u32>>,
+}
+
+impl ComputeUnitEstimator {
+    /// Limits are the simulated consumption plus `margin_percent`
+    pub fn new(margin_percent: u32) -> Self {
+        Self {
+            margin_percent,
+            limits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Limit learned for `shape`, if it was simulated before
+    pub fn cached(&self, shape: &TransactionShape) -> Option<u32> {
+        self.limits.lock().ok()?.get(shape).copied()
+    }
+
+    /// Store the limit for a simulation of `shape` that consumed `units_consumed`, and return it
+    /// Nothing is stored when the RPC didn't report consumption (or reported none);
+    /// callers then keep their configured limit
+    pub fn record(&self, shape: TransactionShape, units_consumed: Option<u64>) -> Option<u32> {
+        let limit = with_margin(units_consumed.filter(|&units| units > 0)?, self.margin_percent);
+        if let Ok(mut limits) = self.limits.lock() {
+            limits.insert(shape, limit);
+        }
+        Some(limit)
+    }
+
+    /// Drop the limit for `shape` so the next liquidation re-simulates it
+    pub fn forget(&self, shape: &TransactionShape) {
+        if let Ok(mut limits) = self.limits.lock() {
+            limits.remove(shape);
+        }
+    }
+}
+
+fn with_margin(units_consumed: u64, margin_percent: u32) -> u32 {
+    let limit = units_consumed.saturating_mul(100 + margin_percent as u64) / 100;
+    limit.min(MAX_COMPUTE_UNIT_LIMIT as u64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimates_are_cached_per_shape() {
+        let estimator = ComputeUnitEstimator::new(20);
+        let shape = TransactionShape { positions: 3, instructions: 7 };
+        let larger = TransactionShape { positions: 4, instructions: 8 };
+
+        assert_eq!(estimator.cached(&shape), None);
+        assert_eq!(estimator.record(shape, Some(150_000)), Some(180_000));
+        assert_eq!(estimator.cached(&shape), Some(180_000));
+        assert_eq!(estimator.cached(&larger), None);
+
+        estimator.forget(&shape);
+        assert_eq!(estimator.cached(&shape), None);
+    }
+
+    #[test]
+    fn test_margin_is_capped_at_the_transaction_limit() {
+        assert_eq!(with_margin(1_300_000, 20), MAX_COMPUTE_UNIT_LIMIT);
+    }
+
+    #[test]
+    fn test_unreported_consumption_is_not_cached() {
+        let estimator = ComputeUnitEstimator::new(20);
+        let shape = TransactionShape { positions: 2, instructions: 6 };
+
+        // A zero limit would fail every later liquidation of this shape
+        assert_eq!(estimator.record(shape, None), None);
+        assert_eq!(estimator.record(shape, Some(0)), None);
+        assert_eq!(estimator.cached(&shape), None);
+    }
+}
@@ -1,24 +1,341 @@
 use anyhow::{anyhow, Result};
-use solana_client::rpc_client::RpcClient;
+use solana_client::{rpc_client::RpcClient, rpc_config::RpcSendTransactionConfig};
 use solana_sdk::{
+    address_lookup_table::{state::AddressLookupTable, AddressLookupTableAccount},
+    commitment_config::CommitmentConfig,
+    compute_budget::ComputeBudgetInstruction,
+    instruction::{Instruction, InstructionError},
+    message::{v0, VersionedMessage},
     pubkey::Pubkey,
-    signature::{Keypair, Signer},
-    transaction::Transaction,
+    signature::{Keypair, Signature, Signer},
+    transaction::{Transaction, TransactionError, VersionedTransaction},
 };
 use std::str::FromStr;
 use std::collections::HashSet;
+use std::time::{Duration, Instant};
 
-use crate::models::{MarketConfig, Obligation};
+use crate::models::{MarketConfig, MarketConfigReserve, Obligation, Reserve};
+use crate::rpc::blockhash::{BlockhashExpired, BlockhashManager};
+use crate::rpc::jito::{BundleOutcome, JitoClient};
+use crate::wallet::{find_associated_token_address, get_wallet_token_balance, mint_token_programs, JupiterClient};
+use crate::wallet::balance::TOKEN_PROGRAMS;
+use crate::liquidation::compute::{ComputeUnitEstimator, TransactionShape};
 use crate::liquidation::instructions::{
+    oracle_pubkey,
     refresh_reserve_instruction,
     refresh_obligation_instruction,
     liquidate_and_redeem_instruction,
 };
 
-/// Execute liquidation and redeem transaction
-/// Equivalent to libs/actions/liquidateAndRedeem.ts
+/// Solend `LendingError::ObligationHealthy`
+const OBLIGATION_HEALTHY: u32 = 29;
+/// Solend `LendingError::ObligationStale`
+const OBLIGATION_STALE: u32 = 30;
+
+/// How often the signature status is polled while waiting for a transaction to land
+const CONFIRM_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long to wait for a sent transaction and how often to re-broadcast it meanwhile
+#[derive(Debug, Clone, Copy)]
+pub struct ConfirmOptions {
+    pub timeout: Duration,
+    pub resend_interval: Duration,
+}
+
+/// Errors surfaced by the liquidation transaction path
+#[derive(Debug, thiserror::Error)]
+pub enum LiquidationError {
+    /// The obligation was repaid, refreshed or liquidated by someone else
+    #[error("obligation is no longer liquidatable: {0}")]
+    StaleObligation(TransactionError),
+    #[error("insufficient {symbol} balance: need {required}, have {available}")]
+    InsufficientBalance {
+        symbol: String,
+        required: u64,
+        available: u64,
+    },
+    #[error("reserve {0} not found in market config")]
+    ReserveNotFound(String),
+    #[error("liquidation simulation failed: {err}")]
+    SimulationFailed {
+        err: TransactionError,
+        logs: Vec<String>,
+    },
+    /// The signature is known even when confirmation fails, so the tx may still land
+    #[error("liquidation transaction failed: {message}")]
+    TransactionFailed {
+        signature: Option<Signature>,
+        message: String,
+    },
+    /// Sent but not seen at the client's commitment; unless the blockhash expired it may still land
+    #[error("liquidation transaction {signature} not confirmed after {elapsed_secs}s (blockhash expired: {blockhash_expired})")]
+    ConfirmationTimeout {
+        signature: Signature,
+        elapsed_secs: u64,
+        blockhash_expired: bool,
+    },
+    /// Rejected for its blockhash before it could land; safe to rebuild and retry
+    #[error(transparent)]
+    BlockhashExpired(#[from] BlockhashExpired),
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl From<anyhow::Error> for LiquidationError {
+    /// Recover a `LiquidationError` that was propagated through `anyhow`
+    fn from(e: anyhow::Error) -> Self {
+        e.downcast::<LiquidationError>().unwrap_or_else(LiquidationError::Other)
+    }
+}
+
+/// Whether a transaction error means Solend refused to liquidate a healthy or stale obligation
+fn is_stale_obligation_error(err: &TransactionError) -> bool {
+    matches!(
+        err,
+        TransactionError::InstructionError(_, InstructionError::Custom(OBLIGATION_HEALTHY | OBLIGATION_STALE))
+    )
+}
+
+/// Simulate a signed transaction, returning compute units consumed if the RPC reports them
+/// Simulation logs are surfaced at debug level
+fn simulate(client: &RpcClient, transaction: &VersionedTransaction) -> Result<Option<u64>, LiquidationError> {
+    let simulation = client
+        .simulate_transaction(transaction)
+        .map_err(|e| LiquidationError::Other(e.into()))?
+        .value;
+    let logs = simulation.logs.unwrap_or_default();
+    
+    for line in &logs {
+        log::debug!("  sim: {}", line);
+    }
+    
+    match simulation.err {
+        Some(err) if is_stale_obligation_error(&err) => Err(LiquidationError::StaleObligation(err)),
+        Some(TransactionError::BlockhashNotFound) => {
+            Err(BlockhashExpired(*transaction.message.recent_blockhash()).into())
+        }
+        Some(err) => Err(LiquidationError::SimulationFailed { err, logs }),
+        None => Ok(simulation.units_consumed),
+    }
+}
+
+/// Send a signed transaction and wait for it at the client's commitment,
+/// re-broadcasting it every `resend_interval` until it lands, the timeout passes or its blockhash expires
+async fn send_and_confirm(
+    client: &RpcClient,
+    transaction: &VersionedTransaction,
+    options: ConfirmOptions,
+) -> Result<Signature, LiquidationError> {
+    let signature = transaction.signatures[0];
+    let blockhash = *transaction.message.recent_blockhash();
+    let failed = |message: String| LiquidationError::TransactionFailed {
+        signature: Some(signature),
+        message,
+    };
+    
+    // Preflight only on the first send; re-sends of a landed transaction would fail it
+    client
+        .send_transaction_with_config(transaction, RpcSendTransactionConfig::default())
+        .map_err(|e| match e.get_transaction_error() {
+            Some(err) if is_stale_obligation_error(&err) => LiquidationError::StaleObligation(err),
+            Some(TransactionError::BlockhashNotFound) => BlockhashExpired(blockhash).into(),
+            _ => failed(e.to_string()),
+        })?;
+    let resend_config = RpcSendTransactionConfig {
+        skip_preflight: true,
+        max_retries: Some(0),
+        ..RpcSendTransactionConfig::default()
+    };
+    
+    let start = Instant::now();
+    let mut last_send = start;
+    
+    loop {
+        let status = client
+            .get_signature_statuses(&[signature])
+            .map_err(|e| LiquidationError::Other(e.into()))?
+            .value
+            .remove(0);
+        
+        if let Some(status) = status.filter(|s| s.satisfies_commitment(client.commitment())) {
+            return match status.err {
+                None => Ok(signature),
+                Some(err) if is_stale_obligation_error(&err) => Err(LiquidationError::StaleObligation(err)),
+                Some(err) => Err(failed(err.to_string())),
+            };
+        }
+        
+        let timed_out = start.elapsed() >= options.timeout;
+        if timed_out || last_send.elapsed() >= options.resend_interval {
+            let blockhash_expired = !client
+                .is_blockhash_valid(&blockhash, CommitmentConfig::processed())
+                .map_err(|e| LiquidationError::Other(e.into()))?;
+            
+            if timed_out || blockhash_expired {
+                return Err(LiquidationError::ConfirmationTimeout {
+                    signature,
+                    elapsed_secs: start.elapsed().as_secs(),
+                    blockhash_expired,
+                });
+            }
+            
+            if let Err(e) = client.send_transaction_with_config(transaction, resend_config) {
+                log::debug!("Re-sending {} failed: {}", signature, e);
+            }
+            last_send = Instant::now();
+        }
+        
+        tokio::time::sleep(CONFIRM_POLL_INTERVAL).await;
+    }
+}
+
+/// Fetch an address lookup table account
+fn fetch_lookup_table(client: &RpcClient, lookup_table: &Pubkey) -> Result<AddressLookupTableAccount> {
+    let account = client
+        .get_account(lookup_table)
+        .map_err(|e| anyhow!("Failed to get lookup table {}: {}", lookup_table, e))?;
+    
+    let table = AddressLookupTable::deserialize(&account.data)
+        .map_err(|e| anyhow!("Failed to deserialize lookup table {}: {}", lookup_table, e))?;
+    
+    Ok(AddressLookupTableAccount {
+        key: *lookup_table,
+        addresses: table.addresses.to_vec(),
+    })
+}
+
+/// Build a signed transaction: v0 with the lookup table if given, legacy otherwise
+fn build_transaction(
+    client: &RpcClient,
+    blockhashes: &BlockhashManager,
+    payer: &Keypair,
+    instructions: &[Instruction],
+    lookup_table: Option<&Pubkey>,
+) -> Result<VersionedTransaction> {
+    let recent_blockhash = blockhashes.get_fresh_blockhash(client)?;
+    
+    match lookup_table {
+        Some(lookup_table) => {
+            let lookup_table_account = fetch_lookup_table(client, lookup_table)?;
+            let message = v0::Message::try_compile(
+                &payer.pubkey(),
+                instructions,
+                &[lookup_table_account],
+                recent_blockhash,
+            )?;
+            Ok(VersionedTransaction::try_new(VersionedMessage::V0(message), &[payer])?)
+        }
+        None => {
+            let mut transaction = Transaction::new_with_payer(instructions, Some(&payer.pubkey()));
+            transaction.sign(&[payer], recent_blockhash);
+            Ok(transaction.into())
+        }
+    }
+}
+
+/// Reserves refreshed in the current slot and not marked stale since, whose refresh can be skipped
+/// Any RPC failure yields an empty set, so every reserve is refreshed
+fn fresh_reserves(client: &RpcClient, reserves: &[Pubkey]) -> HashSet<Pubkey> {
+    let fetched = client.get_slot().map_err(anyhow::Error::from).and_then(|slot| {
+        Ok((slot, client.get_multiple_accounts(reserves)?))
+    });
+    let (current_slot, accounts) = match fetched {
+        Ok(fetched) => fetched,
+        Err(e) => {
+            log::warn!("Refreshing every reserve, couldn't read reserve freshness: {}", e);
+            return HashSet::new();
+        }
+    };
+    
+    reserves
+        .iter()
+        .zip(accounts)
+        .filter_map(|(pubkey, account)| {
+            let reserve = Reserve::parse(&account?.data).ok()?;
+            reserve.last_update.is_fresh_at(current_slot).then_some(*pubkey)
+        })
+        .collect()
+}
+
+/// Payer's collateral ATA and the proceeds owner's liquidity ATA for redeeming from
+/// `withdraw_reserve`, with instructions creating those never held before (funded by the payer)
+/// The token programs are those owning the reserve's collateral and liquidity mints
+fn destination_token_accounts(
+    client: &RpcClient,
+    payer: &Pubkey,
+    proceeds_owner: &Pubkey,
+    withdraw_reserve: &MarketConfigReserve,
+    collateral_token_program: &Pubkey,
+    liquidity_token_program: &Pubkey,
+) -> Result<(Pubkey, Pubkey, Vec<Instruction>)> {
+    // Seized collateral is redeemed straight out of this account with the payer's
+    // authority, so it stays with the payer even when proceeds go elsewhere
+    let withdraw_collateral_mint = Pubkey::from_str(&withdraw_reserve.collateral_mint_address)?;
+    let withdraw_mint = Pubkey::from_str(&withdraw_reserve.liquidity_token.mint)?;
+    
+    let withdraw_collateral_account = find_associated_token_address(
+        payer,
+        &withdraw_collateral_mint,
+        collateral_token_program,
+    );
+    let withdraw_liquidity_account = find_associated_token_address(
+        proceeds_owner,
+        &withdraw_mint,
+        liquidity_token_program,
+    );
+    
+    let mut instructions = Vec::new();
+    for (account, owner, mint, token_program) in [
+        (&withdraw_collateral_account, payer, &withdraw_collateral_mint, collateral_token_program),
+        (&withdraw_liquidity_account, proceeds_owner, &withdraw_mint, liquidity_token_program),
+    ] {
+        if client.get_account(account).is_err() {
+            log::info!("Creating associated token account {} for mint {}", account, mint);
+            instructions.push(
+                spl_associated_token_account::instruction::create_associated_token_account(
+                    payer,
+                    owner,
+                    mint,
+                    token_program,
+                ),
+            );
+        }
+    }
+    
+    Ok((withdraw_collateral_account, withdraw_liquidity_account, instructions))
+}
+
+/// How many of `destination_token_accounts`' ATAs don't exist yet, read in one request
+/// Mints aren't looked up, so an ATA under either token program counts as existing
+pub fn missing_destination_accounts(
+    client: &RpcClient,
+    payer: &Pubkey,
+    proceeds_owner: &Pubkey,
+    withdraw_reserve: &MarketConfigReserve,
+) -> Result<usize> {
+    let withdraw_collateral_mint = Pubkey::from_str(&withdraw_reserve.collateral_mint_address)?;
+    let withdraw_mint = Pubkey::from_str(&withdraw_reserve.liquidity_token.mint)?;
+    
+    let candidates: Vec<Pubkey> = [(payer, &withdraw_collateral_mint), (proceeds_owner, &withdraw_mint)]
+        .into_iter()
+        .flat_map(|(owner, mint)| {
+            TOKEN_PROGRAMS
+                .iter()
+                .map(move |token_program| find_associated_token_address(owner, mint, token_program))
+        })
+        .collect();
+    let accounts = client.get_multiple_accounts(&candidates)?;
+    
+    Ok(accounts
+        .chunks(TOKEN_PROGRAMS.len())
+        .filter(|atas| atas.iter().all(Option::is_none))
+        .count())
+}
+
+/// Build the refresh + liquidate-and-redeem instructions for one liquidation
+/// Fails with `ReserveNotFound` or `InsufficientBalance` before anything is signed
 #[allow(clippy::too_many_arguments)]
-pub async fn liquidate_and_redeem(
+fn build_liquidation_instructions(
     client: &RpcClient,
     env: &str,
     payer: &Keypair,
@@ -26,10 +343,18 @@ pub async fn liquidate_and_redeem(
     repay_token_symbol: &str,
     withdraw_token_symbol: &str,
     market: &MarketConfig,
+    obligation_pubkey: &Pubkey,
     obligation: &Obligation,
-    dry_run: bool,
-) -> Result<()> {
-    let mut instructions = vec![];
+    compute_unit_limit: u32,
+    priority_fee_micro_lamports: u64,
+    skip_fresh_reserve_refresh: bool,
+    proceeds_wallet: Option<&Pubkey>,
+) -> Result<Vec<Instruction>> {
+    // Compute budget goes first so the large liquidate+redeem tx can land under congestion
+    let mut instructions = vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(compute_unit_limit),
+        ComputeBudgetInstruction::set_compute_unit_price(priority_fee_micro_lamports),
+    ];
     
     // Collect unique reserve addresses from deposits and borrows
     let mut unique_reserves = HashSet::new();
@@ -42,18 +367,27 @@ pub async fn liquidate_and_redeem(
         unique_reserves.insert(borrow.borrow_reserve);
     }
     
+    let fresh = if skip_fresh_reserve_refresh {
+        fresh_reserves(client, &unique_reserves.iter().copied().collect::<Vec<_>>())
+    } else {
+        HashSet::new()
+    };
+    if !fresh.is_empty() {
+        log::debug!("Skipping refresh of {} reserves already refreshed this slot", fresh.len());
+    }
+    
     // Create refresh reserve instructions for all unique reserves
-    for reserve_pubkey in &unique_reserves {
+    for reserve_pubkey in unique_reserves.difference(&fresh) {
         let reserve_addr = reserve_pubkey.to_string();
         
         // Find reserve config
         let reserve_config = market.reserves
             .iter()
             .find(|r| r.address == reserve_addr)
-            .ok_or_else(|| anyhow!("Reserve {} not found in market config", reserve_addr))?;
+            .ok_or_else(|| LiquidationError::ReserveNotFound(reserve_addr.clone()))?;
         
-        let pyth_oracle = Pubkey::from_str(&reserve_config.pyth_oracle)?;
-        let switchboard_oracle = Pubkey::from_str(&reserve_config.switchboard_oracle)?;
+        let pyth_oracle = oracle_pubkey(&reserve_config.pyth_oracle)?;
+        let switchboard_oracle = oracle_pubkey(&reserve_config.switchboard_oracle)?;
         
         let refresh_ix = refresh_reserve_instruction(
             env,
@@ -76,11 +410,9 @@ pub async fn liquidate_and_redeem(
         .map(|b| b.borrow_reserve)
         .collect();
     
-    let obligation_pubkey = obligation.lending_market; // This should be the obligation pubkey
-    
     let refresh_obligation_ix = refresh_obligation_instruction(
         env,
-        &obligation_pubkey,
+        obligation_pubkey,
         &deposit_reserves,
         &borrow_reserves,
     )?;
@@ -89,33 +421,42 @@ pub async fn liquidate_and_redeem(
     
     // Get reserve configs for repay and withdraw tokens
     let repay_reserve = market.find_reserve(repay_token_symbol)
-        .ok_or_else(|| anyhow!("Repay token {} not found", repay_token_symbol))?;
+        .ok_or_else(|| LiquidationError::ReserveNotFound(repay_token_symbol.to_string()))?;
     
     let withdraw_reserve = market.find_reserve(withdraw_token_symbol)
-        .ok_or_else(|| anyhow!("Withdraw token {} not found", withdraw_token_symbol))?;
+        .ok_or_else(|| LiquidationError::ReserveNotFound(withdraw_token_symbol.to_string()))?;
     
     // Get associated token accounts
     let repay_mint = Pubkey::from_str(&repay_reserve.liquidity_token.mint)?;
+    let withdraw_collateral_mint = Pubkey::from_str(&withdraw_reserve.collateral_mint_address)?;
     let withdraw_mint = Pubkey::from_str(&withdraw_reserve.liquidity_token.mint)?;
     
-    let repay_account = spl_associated_token_account::get_associated_token_address(
-        &payer.pubkey(),
-        &repay_mint,
-    );
+    // Token-2022 mints have their ATAs under their own program
+    let token_programs = mint_token_programs(client, &[repay_mint, withdraw_collateral_mint, withdraw_mint])?;
     
-    let withdraw_collateral_mint = Pubkey::from_str(&withdraw_reserve.collateral_mint_address)?;
-    let withdraw_collateral_account = spl_associated_token_account::get_associated_token_address(
-        &payer.pubkey(),
-        &withdraw_collateral_mint,
-    );
+    let repay_account = find_associated_token_address(&payer.pubkey(), &repay_mint, &token_programs[0]);
     
-    let withdraw_liquidity_account = spl_associated_token_account::get_associated_token_address(
-        &payer.pubkey(),
-        &withdraw_mint,
-    );
+    // Decimals only affect the human amount, which isn't used here
+    let (available, _) = get_wallet_token_balance(client, &repay_mint, &payer.pubkey(), 0)?;
+    if available < liquidity_amount {
+        return Err(LiquidationError::InsufficientBalance {
+            symbol: repay_token_symbol.to_string(),
+            required: liquidity_amount,
+            available,
+        }
+        .into());
+    }
     
-    // Check if accounts exist, create if needed
-    // (Simplified - in production, check account existence first)
+    let proceeds_owner = proceeds_wallet.copied().unwrap_or_else(|| payer.pubkey());
+    let (withdraw_collateral_account, withdraw_liquidity_account, create_atas) = destination_token_accounts(
+        client,
+        &payer.pubkey(),
+        &proceeds_owner,
+        withdraw_reserve,
+        &token_programs[1],
+        &token_programs[2],
+    )?;
+    instructions.extend(create_atas);
     
     // Create liquidate and redeem instruction
     let liquidate_ix = liquidate_and_redeem_instruction(
@@ -131,7 +472,7 @@ pub async fn liquidate_and_redeem(
         &Pubkey::from_str(&withdraw_reserve.collateral_supply_address)?,
         &Pubkey::from_str(&withdraw_reserve.liquidity_address)?,
         &Pubkey::from_str(&withdraw_reserve.liquidity_fee_receiver_address)?,
-        &obligation_pubkey,
+        obligation_pubkey,
         &Pubkey::from_str(&market.address)?,
         &Pubkey::from_str(&market.authority_address)?,
         &payer.pubkey(),
@@ -139,23 +480,225 @@ pub async fn liquidate_and_redeem(
     
     instructions.push(liquidate_ix);
     
-    // Build and send transaction
-    let recent_blockhash = client.get_latest_blockhash()?;
+    Ok(instructions)
+}
+
+/// Execute liquidation and redeem transaction
+/// Equivalent to libs/actions/liquidateAndRedeem.ts
+/// Returns the signature, or `None` in dry-run mode
+/// With `jito`, a tip is added and the transaction is sent as a bundle
+/// With `proceeds_wallet`, redeemed collateral lands in that wallet's ATA while the
+/// payer still signs and repays; a missing ATA is created at the payer's expense
+/// With `compute_units`, the compute unit limit is sized from a simulation instead of
+/// `compute_unit_limit`, which then only caps that simulation
+/// Fails with `BlockhashExpired`, and drops the cached blockhash, when the blockhash it was
+/// signed with had expired; calling again rebuilds it with a fresh one
+#[allow(clippy::too_many_arguments)]
+pub async fn liquidate_and_redeem(
+    client: &RpcClient,
+    blockhashes: &BlockhashManager,
+    env: &str,
+    payer: &Keypair,
+    liquidity_amount: u64,
+    repay_token_symbol: &str,
+    withdraw_token_symbol: &str,
+    market: &MarketConfig,
+    obligation_pubkey: &Pubkey,
+    obligation: &Obligation,
+    dry_run: bool,
+    compute_unit_limit: u32,
+    priority_fee_micro_lamports: u64,
+    simulate_before_send: bool,
+    lookup_table: Option<&Pubkey>,
+    jito: Option<&JitoClient>,
+    confirm: ConfirmOptions,
+    skip_fresh_reserve_refresh: bool,
+    proceeds_wallet: Option<&Pubkey>,
+    compute_units: Option<&ComputeUnitEstimator>,
+) -> Result<Option<Signature>, LiquidationError> {
+    let mut instructions = build_liquidation_instructions(
+        client,
+        env,
+        payer,
+        liquidity_amount,
+        repay_token_symbol,
+        withdraw_token_symbol,
+        market,
+        obligation_pubkey,
+        obligation,
+        compute_unit_limit,
+        priority_fee_micro_lamports,
+        skip_fresh_reserve_refresh,
+        proceeds_wallet,
+    )?;
+    
+    if let Some(jito) = jito {
+        instructions.push(jito.tip_instruction(&payer.pubkey()));
+    }
+    
+    let shape = TransactionShape {
+        positions: obligation.deposits.len() + obligation.borrows.len(),
+        instructions: instructions.len(),
+    };
+    let max_compute_unit_limit = compute_unit_limit;
+    let mut compute_unit_limit = compute_unit_limit;
+    let mut simulated = false;
+    let mut estimate_cached = false;
+    if let Some(estimator) = compute_units {
+        let estimate = match estimator.cached(&shape) {
+            Some(limit) => {
+                estimate_cached = true;
+                Some(limit)
+            }
+            None => {
+                let probe = build_transaction(client, blockhashes, payer, &instructions, lookup_table)?;
+                simulated = true;
+                let units_consumed = simulate(client, &probe).inspect_err(|e| {
+                    if matches!(e, LiquidationError::BlockhashExpired(_)) {
+                        blockhashes.invalidate();
+                    }
+                })?;
+                estimator.record(shape, units_consumed)
+            }
+        };
+        match estimate {
+            Some(estimate) => {
+                compute_unit_limit = estimate.min(max_compute_unit_limit);
+                log::debug!("Compute unit limit {} for {:?}", compute_unit_limit, shape);
+                // The compute budget instructions lead the transaction
+                instructions[0] = ComputeBudgetInstruction::set_compute_unit_limit(compute_unit_limit);
+            }
+            None => log::debug!(
+                "Simulation reported no compute units for {:?}; keeping the limit of {}",
+                shape,
+                compute_unit_limit
+            ),
+        }
+    }
     
-    let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
-    transaction.sign(&[payer], recent_blockhash);
+    let result = submit_liquidation(
+        client,
+        blockhashes,
+        payer,
+        &instructions,
+        repay_token_symbol,
+        withdraw_token_symbol,
+        obligation_pubkey,
+        dry_run,
+        compute_unit_limit,
+        priority_fee_micro_lamports,
+        simulate_before_send && !simulated,
+        lookup_table,
+        jito,
+        confirm,
+    ).await;
+    
+    match &result {
+        // A retry then signs with a fresh blockhash
+        Err(LiquidationError::BlockhashExpired(_)) => blockhashes.invalidate(),
+        // A reused estimate may be too low for this obligation; measure again next time
+        Err(_) if estimate_cached => {
+            if let Some(estimator) = compute_units {
+                estimator.forget(&shape);
+            }
+        }
+        _ => {}
+    }
+    result
+}
+
+/// Sign a built liquidation and send it, or simulate it in dry-run mode
+#[allow(clippy::too_many_arguments)]
+async fn submit_liquidation(
+    client: &RpcClient,
+    blockhashes: &BlockhashManager,
+    payer: &Keypair,
+    instructions: &[Instruction],
+    repay_token_symbol: &str,
+    withdraw_token_symbol: &str,
+    obligation_pubkey: &Pubkey,
+    dry_run: bool,
+    compute_unit_limit: u32,
+    priority_fee_micro_lamports: u64,
+    simulate_before_send: bool,
+    lookup_table: Option<&Pubkey>,
+    jito: Option<&JitoClient>,
+    confirm: ConfirmOptions,
+) -> Result<Option<Signature>, LiquidationError> {
+    // Build and send transaction
+    let transaction = build_transaction(client, blockhashes, payer, instructions, lookup_table)?;
     
     if dry_run {
+        // Priority fee in lamports = CU limit * micro-lamports per CU / 1e6
+        let priority_fee_lamports =
+            compute_unit_limit as u64 * priority_fee_micro_lamports / 1_000_000;
+        
         log::info!(
             "🔍 DRY-RUN: Would liquidate {} (repay: {}, withdraw: {}) with {} instructions",
-            obligation.lending_market,
+            obligation_pubkey,
             repay_token_symbol,
             withdraw_token_symbol,
             instructions.len()
         );
+        log::info!(
+            "🔍 DRY-RUN: Priority fee up to {} lamports ({} CU @ {} micro-lamports)",
+            priority_fee_lamports,
+            compute_unit_limit,
+            priority_fee_micro_lamports
+        );
+        
+        // Simulate instead of sending so dry-run never touches the chain
+        match simulate(client, &transaction)? {
+            Some(units_consumed) => log::info!(
+                "🔍 DRY-RUN: Simulation succeeded, {} compute units consumed",
+                units_consumed
+            ),
+            None => log::info!("🔍 DRY-RUN: Simulation succeeded"),
+        }
         log::info!("🔍 DRY-RUN: Transaction not submitted (dry-run mode)");
+        
+        Ok(None)
     } else {
-        let signature = client.send_and_confirm_transaction(&transaction)?;
+        // Pre-flight so a doomed liquidation doesn't cost a real fee
+        if simulate_before_send {
+            simulate(client, &transaction)?;
+        }
+        
+        let signature = transaction.signatures[0];
+        let failed = |message: String| LiquidationError::TransactionFailed {
+            signature: Some(signature),
+            message,
+        };
+        
+        // Confirms at the client's commitment, i.e. CONFIRM_COMMITMENT
+        match jito {
+            Some(jito) => {
+                // Nothing was submitted if the block engine refused the bundle
+                let bundle_id = jito
+                    .send_bundle(&transaction)
+                    .await
+                    .map_err(|e| LiquidationError::TransactionFailed { signature: None, message: e.to_string() })?;
+                log::info!("Submitted Jito bundle {} for {}", bundle_id, signature);
+                
+                let outcome = jito
+                    .confirm_bundle(client, &transaction, confirm.timeout, confirm.resend_interval)
+                    .await
+                    .map_err(LiquidationError::Other)?;
+                match outcome {
+                    BundleOutcome::Landed(Ok(())) => {}
+                    BundleOutcome::Landed(Err(err)) if is_stale_obligation_error(&err) => {
+                        return Err(LiquidationError::StaleObligation(err));
+                    }
+                    BundleOutcome::Landed(Err(err)) => return Err(failed(err.to_string())),
+                    BundleOutcome::TimedOut { elapsed_secs, blockhash_expired } => {
+                        return Err(LiquidationError::ConfirmationTimeout { signature, elapsed_secs, blockhash_expired });
+                    }
+                }
+            }
+            None => {
+                send_and_confirm(client, &transaction, confirm).await?;
+            }
+        }
         
         log::info!(
             "Liquidation successful! Signature: {} for repay: {} withdraw: {}",
@@ -163,7 +706,310 @@ pub async fn liquidate_and_redeem(
             repay_token_symbol,
             withdraw_token_symbol
         );
+        
+        Ok(Some(signature))
     }
+}
+
+/// Swap collateral received from a liquidation back into the repay token
+/// Only the balance gained since `collateral_before` is swapped
+#[allow(clippy::too_many_arguments)]
+pub async fn swap_collateral_to_repay(
+    client: &RpcClient,
+    payer: &Keypair,
+    jupiter: &JupiterClient,
+    collateral_mint: &Pubkey,
+    repay_mint: &Pubkey,
+    collateral_before: u64,
+    slippage_bps: u16,
+    priority_fee_lamports: Option<u64>,
+    max_price_impact_pct: f64,
+) -> Result<Option<Signature>> {
+    // Decimals only affect the human amount, which isn't used here
+    let (collateral_after, _) = get_wallet_token_balance(client, collateral_mint, &payer.pubkey(), 0)?;
+    let received = collateral_after.saturating_sub(collateral_before);
     
-    Ok(())
+    if received == 0 {
+        return Ok(None);
+    }
+    
+    log::info!("Swapping {} seized collateral ({}) back to {}", received, collateral_mint, repay_mint);
+    
+    let signature = jupiter
+        .swap(
+            client,
+            payer,
+            collateral_mint,
+            repay_mint,
+            received,
+            slippage_bps,
+            priority_fee_lamports,
+            max_price_impact_pct,
+        )
+        .await?;
+    
+    Ok(Some(signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_obligation_errors() {
+        let healthy = TransactionError::InstructionError(5, InstructionError::Custom(OBLIGATION_HEALTHY));
+        assert!(is_stale_obligation_error(&healthy));
+        
+        let stale = TransactionError::InstructionError(5, InstructionError::Custom(OBLIGATION_STALE));
+        assert!(is_stale_obligation_error(&stale));
+        
+        let other = TransactionError::InstructionError(5, InstructionError::Custom(1));
+        assert!(!is_stale_obligation_error(&other));
+        assert!(!is_stale_obligation_error(&TransactionError::BlockhashNotFound));
+    }
+
+    #[test]
+    fn test_liquidation_error_survives_anyhow() {
+        let e: anyhow::Error = LiquidationError::ReserveNotFound("SOL".to_string()).into();
+        assert!(matches!(LiquidationError::from(e), LiquidationError::ReserveNotFound(s) if s == "SOL"));
+        
+        let e = LiquidationError::from(anyhow!("rpc down"));
+        assert!(matches!(e, LiquidationError::Other(_)));
+        assert_eq!(e.to_string(), "rpc down");
+    }
+
+    fn signed_transfer() -> VersionedTransaction {
+        let payer = Keypair::new();
+        let ix = solana_sdk::system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+        Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], Default::default()).into()
+    }
+
+    const OPTIONS: ConfirmOptions = ConfirmOptions {
+        timeout: Duration::from_secs(5),
+        resend_interval: Duration::from_millis(0),
+    };
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_send_and_confirm_separates_timeouts_from_failures() {
+        let transaction = signed_transfer();
+        
+        let client = RpcClient::new_mock("succeeds".to_string());
+        assert_eq!(send_and_confirm(&client, &transaction, OPTIONS).await.unwrap(), transaction.signatures[0]);
+        
+        let client = RpcClient::new_mock("instruction_error".to_string());
+        let err = send_and_confirm(&client, &transaction, OPTIONS).await.unwrap_err();
+        assert!(matches!(err, LiquidationError::TransactionFailed { signature: Some(_), .. }));
+        
+        // Never seen on-chain and the blockhash has expired
+        let mut mocks = std::collections::HashMap::new();
+        mocks.insert(
+            solana_client::rpc_request::RpcRequest::IsBlockhashValid,
+            serde_json::json!({ "context": { "slot": 1 }, "value": false }),
+        );
+        let client = RpcClient::new_mock_with_mocks("sig_not_found".to_string(), mocks);
+        let err = send_and_confirm(&client, &transaction, OPTIONS).await.unwrap_err();
+        assert!(matches!(
+            err,
+            LiquidationError::ConfirmationTimeout { blockhash_expired: true, signature, .. } if signature == transaction.signatures[0]
+        ));
+    }
+    
+    #[test]
+    fn test_simulation_reports_expired_blockhash() {
+        let transaction = signed_transfer();
+        let mut mocks = std::collections::HashMap::new();
+        mocks.insert(
+            solana_client::rpc_request::RpcRequest::SimulateTransaction,
+            serde_json::json!({
+                "context": { "slot": 1 },
+                "value": { "err": "BlockhashNotFound", "logs": [], "accounts": null, "unitsConsumed": 0 },
+            }),
+        );
+        let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+        
+        let err = simulate(&client, &transaction).unwrap_err();
+        assert!(matches!(
+            err,
+            LiquidationError::BlockhashExpired(BlockhashExpired(hash)) if hash == *transaction.message.recent_blockhash()
+        ));
+    }
+    
+    #[test]
+    fn test_fresh_reserves_only_includes_reserves_refreshed_this_slot() {
+        use base64::Engine;
+        use solana_client::rpc_request::RpcRequest;
+        
+        let reserve_account = |slot: u64, stale: bool| {
+            let mut data = vec![0u8; crate::models::reserve::RESERVE_SIZE];
+            data[1..9].copy_from_slice(&slot.to_le_bytes());
+            data[9] = stale as u8;
+            serde_json::json!({
+                "lamports": 1_000_000,
+                "data": [base64::engine::general_purpose::STANDARD.encode(&data), "base64"],
+                "owner": Pubkey::new_unique().to_string(),
+                "executable": false,
+                "rentEpoch": 0,
+                "space": data.len(),
+            })
+        };
+        let reserves: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        
+        let mut mocks = std::collections::HashMap::new();
+        mocks.insert(RpcRequest::GetSlot, serde_json::json!(100));
+        mocks.insert(
+            RpcRequest::GetMultipleAccounts,
+            serde_json::json!({
+                "context": { "slot": 100 },
+                "value": [reserve_account(100, false), reserve_account(99, false), reserve_account(100, true), null],
+            }),
+        );
+        let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+        assert_eq!(fresh_reserves(&client, &reserves), HashSet::from([reserves[0]]));
+        
+        // Unreadable state falls back to refreshing everything
+        let client = RpcClient::new_mock("fails".to_string());
+        assert!(fresh_reserves(&client, &reserves).is_empty());
+    }
+    
+    /// Market with USDC and SOL reserves, each with fresh random addresses
+    fn test_market() -> MarketConfig {
+        let key = || Pubkey::new_unique().to_string();
+        let reserve = |symbol: &str| serde_json::json!({
+            "liquidityToken": {
+                "coingeckoId": "", "decimals": 6, "logo": "", "mint": key(), "name": symbol,
+                "symbol": symbol, "volume24h": "",
+            },
+            "pythOracle": key(), "switchboardOracle": crate::oracle::NULL_ORACLE, "address": key(),
+            "collateralMintAddress": key(), "collateralSupplyAddress": key(), "liquidityAddress": key(),
+            "liquidityFeeReceiverAddress": key(), "userSupplyCap": 0,
+        });
+        serde_json::from_value(serde_json::json!({
+            "name": "main", "isPrimary": true, "description": "", "creator": "", "address": key(),
+            "authorityAddress": key(), "owner": "", "reserves": [reserve("USDC"), reserve("SOL")],
+        }))
+        .unwrap()
+    }
+    
+    fn empty_obligation() -> Obligation {
+        Obligation {
+            version: 1,
+            last_update: crate::models::LastUpdate { slot: 1, stale: false },
+            lending_market: Pubkey::default(),
+            owner: Pubkey::default(),
+            deposited_value: 0,
+            borrowed_value: 0,
+            allowed_borrow_value: 0,
+            unhealthy_borrow_value: 0,
+            deposits: vec![],
+            borrows: vec![],
+        }
+    }
+    
+    /// `getMultipleAccounts` entry for an account owned by `owner`
+    fn account_owned_by(owner: Pubkey) -> serde_json::Value {
+        serde_json::json!({
+            "lamports": 1_000_000, "data": ["", "base64"], "owner": owner.to_string(),
+            "executable": false, "rentEpoch": 0, "space": 0,
+        })
+    }
+    
+    #[test]
+    fn test_missing_destination_accounts_counts_atas_under_either_program() {
+        let market = test_market();
+        let sol = market.find_reserve("SOL").unwrap();
+        
+        // The collateral ATA exists under Token-2022; no liquidity ATA exists
+        let mut mocks = std::collections::HashMap::new();
+        mocks.insert(
+            solana_client::rpc_request::RpcRequest::GetMultipleAccounts,
+            serde_json::json!({
+                "context": { "slot": 1 },
+                "value": [null, account_owned_by(spl_token_2022::id()), null, null],
+            }),
+        );
+        let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+        
+        let payer = Pubkey::new_unique();
+        assert_eq!(missing_destination_accounts(&client, &payer, &payer, sol).unwrap(), 1);
+    }
+    
+    #[test]
+    fn test_proceeds_wallet_receives_redeemed_liquidity() {
+        let market = test_market();
+        let obligation = empty_obligation();
+        let (payer, proceeds) = (Keypair::new(), Pubkey::new_unique());
+        let sol = market.find_reserve("SOL").unwrap();
+        
+        // The collateral mint is SPL Token, the liquidity mint Token-2022; no ATA exists
+        // on the mock, so both destination ATAs get created
+        let mut mocks = std::collections::HashMap::new();
+        mocks.insert(
+            solana_client::rpc_request::RpcRequest::GetMultipleAccounts,
+            serde_json::json!({
+                "context": { "slot": 1 },
+                "value": [
+                    account_owned_by(spl_token::id()),
+                    account_owned_by(spl_token::id()),
+                    account_owned_by(spl_token_2022::id()),
+                ],
+            }),
+        );
+        let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+        
+        let instructions = build_liquidation_instructions(
+            &client, "production", &payer, 0, "USDC", "SOL", &market, &Pubkey::new_unique(),
+            &obligation, 400_000, 0, false, Some(&proceeds),
+        )
+        .unwrap();
+        
+        let ata = |owner: &Pubkey, mint: &str, token_program: &Pubkey| {
+            find_associated_token_address(owner, &Pubkey::from_str(mint).unwrap(), token_program)
+        };
+        let liquidate = instructions.last().unwrap();
+        assert_eq!(liquidate.accounts[4].pubkey, ata(&payer.pubkey(), &sol.collateral_mint_address, &spl_token::id()));
+        assert_eq!(liquidate.accounts[5].pubkey, ata(&proceeds, &sol.liquidity_token.mint, &spl_token_2022::id()));
+        
+        // The proceeds ATA is owned by the proceeds wallet but paid for by the payer,
+        // under the liquidity mint's token program
+        let create_collateral_ata = &instructions[instructions.len() - 3];
+        assert_eq!(create_collateral_ata.accounts[5].pubkey, spl_token::id());
+        let create_liquidity_ata = &instructions[instructions.len() - 2];
+        assert_eq!(create_liquidity_ata.accounts[0].pubkey, payer.pubkey());
+        assert_eq!(create_liquidity_ata.accounts[2].pubkey, proceeds);
+        assert_eq!(create_liquidity_ata.accounts[5].pubkey, spl_token_2022::id());
+    }
+    
+    #[test]
+    fn test_token_2022_repay_mint_repays_from_its_ata() {
+        let market = test_market();
+        let payer = Keypair::new();
+        let usdc = market.find_reserve("USDC").unwrap();
+        
+        let mut mocks = std::collections::HashMap::new();
+        mocks.insert(
+            solana_client::rpc_request::RpcRequest::GetMultipleAccounts,
+            serde_json::json!({
+                "context": { "slot": 1 },
+                "value": [
+                    account_owned_by(spl_token_2022::id()),
+                    account_owned_by(spl_token::id()),
+                    account_owned_by(spl_token::id()),
+                ],
+            }),
+        );
+        let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+        
+        let instructions = build_liquidation_instructions(
+            &client, "production", &payer, 0, "USDC", "SOL", &market, &Pubkey::new_unique(),
+            &empty_obligation(), 400_000, 0, false, None,
+        )
+        .unwrap();
+        
+        let repay_mint = Pubkey::from_str(&usdc.liquidity_token.mint).unwrap();
+        let liquidate = instructions.last().unwrap();
+        assert_eq!(
+            liquidate.accounts[3].pubkey,
+            find_associated_token_address(&payer.pubkey(), &repay_mint, &spl_token_2022::id())
+        );
+    }
 }
@@ -1,13 +1,24 @@
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
 };
+use std::str::FromStr;
 
+use crate::oracle::NULL_ORACLE;
 use crate::utils::get_program_id;
 
+/// Oracle account for one of a reserve's refresh slots
+/// A reserve without that oracle is given NULL_ORACLE, which is what the program stores for it
+pub fn oracle_pubkey(oracle: &str) -> Result<Pubkey> {
+    let oracle = if oracle.is_empty() { NULL_ORACLE } else { oracle };
+    Pubkey::from_str(oracle).with_context(|| format!("Invalid oracle address: {}", oracle))
+}
+
 /// Create refresh reserve instruction
 /// Equivalent to models/instructions/refreshReserve.ts
+/// The program checks both oracle slots against the reserve by position, so a single-oracle
+/// reserve keeps NULL_ORACLE in its unused slot rather than dropping the account
 pub fn refresh_reserve_instruction(
     env: &str,
     reserve: &Pubkey,
@@ -16,8 +27,13 @@ pub fn refresh_reserve_instruction(
 ) -> Result<Instruction> {
     let program_id = get_program_id(env)?;
     
+    let null_oracle = Pubkey::from_str(NULL_ORACLE)?;
+    if *pyth_oracle == null_oracle && *switchboard_oracle == null_oracle {
+        return Err(anyhow!("Reserve {} has no oracle to refresh from", reserve));
+    }
+    
     // Instruction discriminator for RefreshReserve (instruction index 3)
-    let mut data = vec![3];
+    let data = vec![3];
     
     Ok(Instruction {
         program_id,
@@ -41,7 +57,7 @@ pub fn refresh_obligation_instruction(
     let program_id = get_program_id(env)?;
     
     // Instruction discriminator for RefreshObligation (instruction index 7)
-    let mut data = vec![7];
+    let data = vec![7];
     
     let mut accounts = vec![
         AccountMeta::new(*obligation, false),
@@ -117,3 +133,25 @@ pub fn liquidate_and_redeem_instruction(
         data,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_refresh_reserve_keeps_null_oracle_slot() {
+        let reserve = Pubkey::new_unique();
+        let pyth = Pubkey::new_unique();
+        let null_oracle = oracle_pubkey("").unwrap();
+        assert_eq!(null_oracle.to_string(), NULL_ORACLE);
+        
+        // Pyth-only reserve: switchboard slot still present, holding the sentinel
+        let ix = refresh_reserve_instruction("production", &reserve, &pyth, &null_oracle).unwrap();
+        let accounts: Vec<Pubkey> = ix.accounts.iter().map(|a| a.pubkey).collect();
+        assert_eq!(accounts, vec![reserve, pyth, null_oracle]);
+        assert!(ix.accounts[1..].iter().all(|a| !a.is_writable && !a.is_signer));
+        
+        assert!(refresh_reserve_instruction("production", &reserve, &null_oracle, &null_oracle).is_err());
+        assert!(oracle_pubkey("not-a-key").is_err());
+    }
+}
@@ -1,6 +1,13 @@
 pub mod refresh;
 pub mod instructions;
 pub mod execute;
+pub mod profit;
+pub mod compute;
 
-pub use refresh::calculate_refreshed_obligation;
-pub use execute::liquidate_and_redeem;
+pub use refresh::{calculate_refreshed_obligation, RefreshedObligation};
+pub use execute::{liquidate_and_redeem, swap_collateral_to_repay, ConfirmOptions, LiquidationError};
+pub use compute::ComputeUnitEstimator;
+pub use profit::{
+    estimate_liquidation_profit, max_repay_for_available, protocol_liquidation_fee_amount, seized_collateral_amount,
+    LiquidationCosts,
+};
@@ -0,0 +1,258 @@
+use anyhow::{anyhow, Result};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use std::str::FromStr;
+
+use crate::liquidation::refresh::{RefreshedBorrow, RefreshedDeposit};
+use crate::utils::WAD;
+use crate::wallet::swap::QuoteResponse;
+
+/// Base fee per transaction signature, in lamports
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+/// Rent-exempt minimum of an SPL token account, in lamports
+pub const TOKEN_ACCOUNT_RENT_LAMPORTS: u64 = 2_039_280;
+
+/// What sending one liquidation costs the payer
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LiquidationCosts {
+    pub compute_unit_limit: u32,
+    pub priority_fee_micro_lamports: u64,
+    /// Zero unless sent through Jito
+    pub jito_tip_lamports: u64,
+    /// Destination token accounts the transaction creates
+    pub atas_created: usize,
+}
+
+impl LiquidationCosts {
+    /// Base fee, priority fee at the full compute unit limit, tip and new account rent
+    pub fn lamports(&self) -> u64 {
+        LAMPORTS_PER_SIGNATURE
+            + self.compute_unit_limit as u64 * self.priority_fee_micro_lamports / 1_000_000
+            + self.jito_tip_lamports
+            + self.atas_created as u64 * TOKEN_ACCOUNT_RENT_LAMPORTS
+    }
+}
+
+/// Borrowed amount in base units
+fn borrowed_amount(borrow: &RefreshedBorrow) -> Decimal {
+    Decimal::from(borrow.borrowed_amount_wads) / Decimal::from(WAD)
+}
+
+/// USD value of repaying `repay_amount` base units of the borrow
+pub fn repay_value_usd(borrow: &RefreshedBorrow, repay_amount: u64) -> Decimal {
+    let borrowed = borrowed_amount(borrow);
+    if borrowed.is_zero() {
+        return Decimal::ZERO;
+    }
+    borrow.market_value * Decimal::from(repay_amount) / borrowed
+}
+
+/// Collateral (deposit liquidity base units) seized for repaying `repay_amount`
+/// Seized value is the repay value plus the liquidation bonus, capped at the deposit
+pub fn seized_collateral_amount(
+    borrow: &RefreshedBorrow,
+    deposit: &RefreshedDeposit,
+    repay_amount: u64,
+    liquidation_bonus: u8,
+) -> u64 {
+    if deposit.market_value.is_zero() {
+        return 0;
+    }
+    
+    let bonus = Decimal::from(liquidation_bonus) / Decimal::from(100);
+    let seized_value = (repay_value_usd(borrow, repay_amount) * (Decimal::ONE + bonus))
+        .min(deposit.market_value);
+    
+    (seized_value * deposit.liquidity_amount / deposit.market_value)
+        .floor()
+        .to_u64()
+        .unwrap_or(0)
+}
+
+/// Largest repay whose seized collateral fits in `available` deposit liquidity base units,
+/// the inverse of `seized_collateral_amount`
+pub fn max_repay_for_available(
+    borrow: &RefreshedBorrow,
+    deposit: &RefreshedDeposit,
+    available: u64,
+    liquidation_bonus: u8,
+) -> u64 {
+    if deposit.liquidity_amount.is_zero() || borrow.market_value.is_zero() {
+        return 0;
+    }
+    
+    let bonus = Decimal::from(liquidation_bonus) / Decimal::from(100);
+    let available_value = Decimal::from(available) * deposit.market_value / deposit.liquidity_amount;
+    let repay_value = available_value / (Decimal::ONE + bonus);
+    
+    (repay_value * borrowed_amount(borrow) / borrow.market_value)
+        .floor()
+        .to_u64()
+        .unwrap_or(u64::MAX)
+}
+
+/// Collateral Solend keeps out of `seized` as its protocol liquidation fee
+/// Mirrors Solend's `calculate_protocol_liquidation_fee`: deca-bps of the amount before
+/// the bonus, rounded up and at least one base unit
+pub fn protocol_liquidation_fee_amount(seized: u64, liquidation_bonus: u8, protocol_liquidation_fee: u8) -> u64 {
+    if seized == 0 {
+        return 0;
+    }
+    let bonus_rate = Decimal::ONE + Decimal::from(liquidation_bonus) / Decimal::from(100);
+    let fee = Decimal::from(seized) / bonus_rate * Decimal::from(protocol_liquidation_fee) / Decimal::from(1_000);
+    fee.ceil().to_u64().unwrap_or(0).clamp(1, seized)
+}
+
+/// Estimate net profit in USD of a liquidation
+/// `quote` converts seized collateral back into the repay token; its minimum out
+/// (after slippage) is applied to the seized amount left after Solend's protocol fee.
+/// `costs_usd` (fees, tips, account rent) is subtracted from the result
+#[allow(clippy::too_many_arguments)]
+pub fn estimate_liquidation_profit(
+    borrow: &RefreshedBorrow,
+    deposit: &RefreshedDeposit,
+    repay_amount: u64,
+    liquidation_bonus: u8,
+    protocol_liquidation_fee: u8,
+    quote: &QuoteResponse,
+    costs_usd: Decimal,
+) -> Result<Decimal> {
+    let seized = seized_collateral_amount(borrow, deposit, repay_amount, liquidation_bonus);
+    let received = seized - protocol_liquidation_fee_amount(seized, liquidation_bonus, protocol_liquidation_fee);
+    
+    let quote_in = Decimal::from_str(&quote.in_amount)
+        .map_err(|e| anyhow!("Invalid quote inAmount {}: {}", quote.in_amount, e))?;
+    let min_out = Decimal::from_str(&quote.other_amount_threshold)
+        .map_err(|e| anyhow!("Invalid quote otherAmountThreshold {}: {}", quote.other_amount_threshold, e))?;
+    
+    if quote_in.is_zero() {
+        return Err(anyhow!("Jupiter quote has zero input amount"));
+    }
+    
+    // Repay tokens received for the seized collateral, priced like the borrow
+    let proceeds = min_out * Decimal::from(received) / quote_in;
+    let borrowed = borrowed_amount(borrow);
+    let proceeds_usd = if borrowed.is_zero() {
+        Decimal::ZERO
+    } else {
+        proceeds * borrow.market_value / borrowed
+    };
+    
+    Ok(proceeds_usd - repay_value_usd(borrow, repay_amount) - costs_usd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    
+    fn borrow() -> RefreshedBorrow {
+        // 1000 USDC (6 decimals) worth $1000
+        RefreshedBorrow {
+            borrow_reserve: String::new(),
+            borrowed_amount_wads: 1_000_000_000 * WAD,
+            market_value: dec!(1000),
+            symbol: "USDC".to_string(),
+            mint_address: String::new(),
+        }
+    }
+    
+    fn deposit() -> RefreshedDeposit {
+        // 10 SOL (9 decimals) worth $1000
+        RefreshedDeposit {
+            deposit_reserve: String::new(),
+            deposited_amount: 10_000_000_000,
+            liquidity_amount: dec!(10_000_000_000),
+            market_value: dec!(1000),
+            symbol: "SOL".to_string(),
+            mint_address: String::new(),
+        }
+    }
+    
+    fn quote(in_amount: u64, out_amount: u64, min_out: u64) -> QuoteResponse {
+        QuoteResponse {
+            input_mint: String::new(),
+            output_mint: String::new(),
+            in_amount: in_amount.to_string(),
+            out_amount: out_amount.to_string(),
+            other_amount_threshold: min_out.to_string(),
+            swap_mode: "ExactIn".to_string(),
+            price_impact_pct: "0".to_string(),
+        }
+    }
+    
+    #[test]
+    fn test_seized_collateral_with_bonus() {
+        // Repay $200, 5% bonus -> $210 of SOL = 2.1 SOL
+        let seized = seized_collateral_amount(&borrow(), &deposit(), 200_000_000, 5);
+        assert_eq!(seized, 2_100_000_000);
+    }
+    
+    #[test]
+    fn test_seized_collateral_capped_at_deposit() {
+        let seized = seized_collateral_amount(&borrow(), &deposit(), 1_000_000_000, 5);
+        assert_eq!(seized, 10_000_000_000);
+    }
+    
+    #[test]
+    fn test_max_repay_for_available() {
+        // 2.1 SOL pays out a $200 repay at a 5% bonus, and no more
+        let repay = max_repay_for_available(&borrow(), &deposit(), 2_100_000_000, 5);
+        assert_eq!(repay, 200_000_000);
+        assert!(seized_collateral_amount(&borrow(), &deposit(), repay, 5) <= 2_100_000_000);
+        assert_eq!(max_repay_for_available(&borrow(), &deposit(), 0, 5), 0);
+    }
+    
+    #[test]
+    fn test_estimate_liquidation_profit() {
+        // 2.1 SOL with at least 209 USDC out -> $9 profit on a $200 repay
+        let profit = estimate_liquidation_profit(
+            &borrow(),
+            &deposit(),
+            200_000_000,
+            5,
+            0,
+            &quote(2_100_000_000, 210_000_000, 209_000_000),
+            Decimal::ZERO,
+        ).unwrap();
+        // Less the one base unit Solend always keeps
+        assert_eq!(profit.round_dp(4), dec!(9));
+    }
+    
+    #[test]
+    fn test_estimate_liquidation_profit_is_net_of_fees() {
+        // Solend keeps 3% of the 2 SOL before the bonus (0.06 SOL), leaving 2.04 SOL;
+        // at the 209 USDC per 2.1 SOL minimum that's ~203.03 USDC, less $0.50 of costs
+        let profit = estimate_liquidation_profit(
+            &borrow(),
+            &deposit(),
+            200_000_000,
+            5,
+            30,
+            &quote(2_100_000_000, 210_000_000, 209_000_000),
+            dec!(0.5),
+        ).unwrap();
+        assert_eq!(profit.round_dp(4), dec!(2.5286));
+    }
+    
+    #[test]
+    fn test_protocol_liquidation_fee_amount() {
+        assert_eq!(protocol_liquidation_fee_amount(2_100_000_000, 5, 30), 60_000_000);
+        // Rounded up to at least one base unit, like Solend
+        assert_eq!(protocol_liquidation_fee_amount(2_100_000_000, 5, 0), 1);
+        assert_eq!(protocol_liquidation_fee_amount(0, 5, 30), 0);
+    }
+    
+    #[test]
+    fn test_liquidation_costs() {
+        let costs = LiquidationCosts {
+            compute_unit_limit: 400_000,
+            priority_fee_micro_lamports: 10_000,
+            jito_tip_lamports: 100_000,
+            atas_created: 1,
+        };
+        assert_eq!(costs.lamports(), 5_000 + 4_000 + 100_000 + TOKEN_ACCOUNT_RENT_LAMPORTS);
+        assert_eq!(LiquidationCosts::default().lamports(), LAMPORTS_PER_SIGNATURE);
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use rust_decimal::Decimal;
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
 use std::collections::HashMap;
 use solana_sdk::pubkey::Pubkey;
 
@@ -10,6 +11,7 @@ use crate::oracle::TokenOracleData;
 #[derive(Debug, Clone)]
 pub struct RefreshedObligation {
     pub borrowed_value: Decimal,
+    pub allowed_borrow_value: Decimal,
     pub unhealthy_borrow_value: Decimal,
     pub deposits: Vec<RefreshedDeposit>,
     pub borrows: Vec<RefreshedBorrow>,
@@ -19,6 +21,8 @@ pub struct RefreshedObligation {
 pub struct RefreshedDeposit {
     pub deposit_reserve: String,
     pub deposited_amount: u64,
+    /// Underlying liquidity in base units (deposited_amount / exchange rate)
+    pub liquidity_amount: Decimal,
     pub market_value: Decimal,
     pub symbol: String,
     pub mint_address: String,
@@ -36,10 +40,13 @@ pub struct RefreshedBorrow {
 /// Calculate refreshed obligation health
 /// Equivalent to libs/refreshObligation.ts:calculateRefreshedObligation
 /// Optimized to use Pubkey-keyed HashMap for faster lookups
+/// With `use_ema_for_health`, health totals use EMA prices while per-position
+/// market values stay at spot for repay sizing
 pub fn calculate_refreshed_obligation(
     obligation: &Obligation,
     reserves: &HashMap<Pubkey, Reserve>,
     oracle_data: &HashMap<String, TokenOracleData>,
+    use_ema_for_health: bool,
 ) -> Result<RefreshedObligation> {
     
     let mut total_borrowed_value = Decimal::ZERO;
@@ -68,18 +75,21 @@ pub fn calculate_refreshed_obligation(
                 
                 // Calculate market value
                 let market_value = liquidity_amount * oracle.price / Decimal::from(oracle.decimals);
+                let health_value = liquidity_amount * oracle.health_price(use_ema_for_health)
+                    / Decimal::from(oracle.decimals);
                 
                 // Add to allowed borrow value
                 let ltv = reserve.get_loan_to_value_rate();
-                total_allowed_borrow_value += market_value * ltv;
+                total_allowed_borrow_value += health_value * ltv;
                 
                 // Add to unhealthy borrow value
                 let liquidation_threshold = reserve.get_liquidation_threshold_rate();
-                total_unhealthy_borrow_value += market_value * liquidation_threshold;
+                total_unhealthy_borrow_value += health_value * liquidation_threshold;
                 
                 refreshed_deposits.push(RefreshedDeposit {
                     deposit_reserve: reserve_pubkey.to_string(),
                     deposited_amount,
+                    liquidity_amount,
                     market_value,
                     symbol: oracle.symbol.clone(),
                     mint_address: mint_addr,
@@ -96,7 +106,8 @@ pub fn calculate_refreshed_obligation(
             let mint_addr = reserve.liquidity.mint_pubkey.to_string();
             
             if let Some(oracle) = oracle_data.values().find(|o| o.mint_address == mint_addr) {
-                let borrowed_amount_wads = borrow.borrowed_amount_wads;
+                // Interest accrued since the obligation was last refreshed on-chain
+                let borrowed_amount_wads = borrow.accrued_amount_wads(reserve.liquidity.cumulative_borrow_rate_wads);
                 
                 // Calculate actual borrowed amount from wads
                 let wad = Decimal::from(crate::utils::WAD);
@@ -105,7 +116,8 @@ pub fn calculate_refreshed_obligation(
                 // Calculate market value
                 let market_value = borrowed_amount * oracle.price / Decimal::from(oracle.decimals);
                 
-                total_borrowed_value += market_value;
+                total_borrowed_value += borrowed_amount * oracle.health_price(use_ema_for_health)
+                    / Decimal::from(oracle.decimals);
                 
                 refreshed_borrows.push(RefreshedBorrow {
                     borrow_reserve: reserve_pubkey.to_string(),
@@ -120,18 +132,76 @@ pub fn calculate_refreshed_obligation(
     
     Ok(RefreshedObligation {
         borrowed_value: total_borrowed_value,
+        allowed_borrow_value: total_allowed_borrow_value,
         unhealthy_borrow_value: total_unhealthy_borrow_value,
         deposits: refreshed_deposits,
         borrows: refreshed_borrows,
     })
 }
 
+fn fraction(value: f64) -> Decimal {
+    Decimal::from_f64(value).unwrap_or(Decimal::ZERO)
+}
+
+/// `numerator / denominator`, treating any debt against no collateral as maximally unhealthy
+fn ratio(numerator: Decimal, denominator: Decimal) -> Decimal {
+    if numerator.is_zero() {
+        Decimal::ZERO
+    } else if denominator.is_zero() {
+        Decimal::MAX
+    } else {
+        numerator / denominator
+    }
+}
+
+impl RefreshedDeposit {
+    /// Liquidity the deposit's reserve can pay out on redeem, `None` if the reserve is unknown
+    pub fn available_liquidity(&self, reserves: &HashMap<Pubkey, Reserve>) -> Option<u64> {
+        let reserve_pubkey = self.deposit_reserve.parse::<Pubkey>().ok()?;
+        reserves.get(&reserve_pubkey).map(|r| r.liquidity.available_amount)
+    }
+}
+
+impl RefreshedBorrow {
+    /// Largest repay allowed in one liquidation:
+    /// min(wallet_balance, borrowed_amount * close_factor)
+    pub fn capped_repay_amount(&self, wallet_balance: u64, close_factor: f64) -> u64 {
+        let borrowed = Decimal::from(self.borrowed_amount_wads) / Decimal::from(crate::utils::WAD);
+        let close_factor = Decimal::from_f64(close_factor).unwrap_or(Decimal::ZERO);
+        let max_repay = (borrowed * close_factor).floor().to_u64().unwrap_or(u64::MAX);
+        
+        wallet_balance.min(max_repay)
+    }
+}
+
 impl RefreshedObligation {
     /// Check if obligation is underwater (unhealthy)
     pub fn is_unhealthy(&self) -> bool {
         self.borrowed_value > self.unhealthy_borrow_value
     }
     
+    /// Unhealthy by more than `margin` (a fraction of the threshold)
+    /// Positions right at the boundary are usually lost races that waste fees
+    pub fn is_liquidatable(&self, margin: f64) -> bool {
+        self.borrowed_value > self.unhealthy_borrow_value * (Decimal::ONE + fraction(margin))
+    }
+    
+    /// Healthy, but within `warn_margin` (a fraction of the threshold) of liquidation
+    pub fn is_near_liquidation(&self, warn_margin: f64) -> bool {
+        !self.is_unhealthy()
+            && self.borrowed_value > self.unhealthy_borrow_value * (Decimal::ONE - fraction(warn_margin))
+    }
+    
+    /// Borrowed value over the liquidation threshold; liquidatable above 1
+    pub fn health_factor(&self) -> Decimal {
+        ratio(self.borrowed_value, self.unhealthy_borrow_value)
+    }
+    
+    /// Borrowed value over the LTV limit; no new borrows above 1
+    pub fn utilization(&self) -> Decimal {
+        ratio(self.borrowed_value, self.allowed_borrow_value)
+    }
+    
     /// Get the best borrow to repay (highest market value)
     pub fn select_repay_borrow(&self) -> Option<&RefreshedBorrow> {
         self.borrows
@@ -139,12 +209,100 @@ impl RefreshedObligation {
             .max_by(|a, b| a.market_value.cmp(&b.market_value))
     }
     
+    /// Highest-value borrow the wallet holds any of, given balances keyed by mint address
+    /// Any positive balance funds at least a partial repay
+    pub fn select_repay_borrow_fundable(&self, balances: &HashMap<String, Decimal>) -> Option<&RefreshedBorrow> {
+        self.borrows
+            .iter()
+            .filter(|b| balances.get(&b.mint_address).is_some_and(|balance| *balance > Decimal::ZERO))
+            .max_by(|a, b| a.market_value.cmp(&b.market_value))
+    }
+    
+    /// First token in this obligation whose oracle price is untrusted
+    pub fn untrusted_symbol(&self, oracle_data: &HashMap<String, TokenOracleData>) -> Option<&str> {
+        self.deposits
+            .iter()
+            .map(|d| d.symbol.as_str())
+            .chain(self.borrows.iter().map(|b| b.symbol.as_str()))
+            .find(|symbol| oracle_data.get(*symbol).is_some_and(|o| !o.trusted))
+    }
+    
     /// Get the best collateral to withdraw (highest market value)
-    pub fn select_withdraw_deposit(&self) -> Option<&RefreshedDeposit> {
+    /// Deposits whose reserve has no liquidity to redeem into are skipped
+    pub fn select_withdraw_deposit(&self, reserves: &HashMap<Pubkey, Reserve>) -> Option<&RefreshedDeposit> {
         self.deposits
             .iter()
+            .filter(|d| d.available_liquidity(reserves).is_some_and(|available| available > 0))
             .max_by(|a, b| a.market_value.cmp(&b.market_value))
     }
+    
+    /// Get the borrow/deposit pair whose liquidation earns the largest bonus
+    /// Each pair is scored by the value that can be seized (the smaller of the borrow,
+    /// the deposit and the withdraw reserve's available liquidity) times that
+    /// reserve's liquidation bonus; deposits without a known reserve are skipped
+    pub fn select_best_liquidation_pair(
+        &self,
+        reserves: &HashMap<Pubkey, Reserve>,
+    ) -> Option<(&RefreshedBorrow, &RefreshedDeposit)> {
+        self.best_scored_pair(reserves, &self.borrows).map(|(_, borrow, deposit)| (borrow, deposit))
+    }
+    
+    /// Best deposit to seize when repaying `borrow`, scored as in `select_best_liquidation_pair`
+    pub fn select_best_liquidation_pair_for<'a>(
+        &'a self,
+        borrow: &'a RefreshedBorrow,
+        reserves: &HashMap<Pubkey, Reserve>,
+    ) -> Option<(&'a RefreshedBorrow, &'a RefreshedDeposit)> {
+        self.best_scored_pair(reserves, std::slice::from_ref(borrow))
+            .map(|(_, borrow, deposit)| (borrow, deposit))
+    }
+    
+    /// Sort key for working through unhealthy obligations, most lucrative first
+    /// Currently the bonus value of the best liquidation pair
+    pub fn liquidation_priority(&self, reserves: &HashMap<Pubkey, Reserve>) -> Decimal {
+        self.best_scored_pair(reserves, &self.borrows)
+            .map(|(score, _, _)| score)
+            .unwrap_or(Decimal::ZERO)
+    }
+    
+    fn best_scored_pair<'a>(
+        &'a self,
+        reserves: &HashMap<Pubkey, Reserve>,
+        borrows: &'a [RefreshedBorrow],
+    ) -> Option<(Decimal, &'a RefreshedBorrow, &'a RefreshedDeposit)> {
+        let scored_deposits: Vec<(&RefreshedDeposit, Decimal, Decimal)> = self
+            .deposits
+            .iter()
+            .filter_map(|deposit| {
+                let reserve_pubkey = deposit.deposit_reserve.parse::<Pubkey>().ok()?;
+                let reserve = reserves.get(&reserve_pubkey)?;
+                
+                // Redeeming needs liquidity in the reserve, valued at the deposit's price
+                let available = reserve.liquidity.available_amount;
+                if available == 0 {
+                    return None;
+                }
+                let available_value = if deposit.liquidity_amount.is_zero() {
+                    Decimal::ZERO
+                } else {
+                    Decimal::from(available) * deposit.market_value / deposit.liquidity_amount
+                };
+                let bonus = Decimal::from(reserve.config.liquidation_bonus) / Decimal::from(100);
+                
+                Some((deposit, deposit.market_value.min(available_value), bonus))
+            })
+            .collect();
+        
+        borrows
+            .iter()
+            .flat_map(|borrow| {
+                scored_deposits.iter().map(move |(deposit, seizable_value, bonus)| {
+                    let score = borrow.market_value.min(*seizable_value) * bonus;
+                    (score, borrow, *deposit)
+                })
+            })
+            .max_by(|a, b| a.0.cmp(&b.0))
+    }
 }
 
 #[cfg(test)]
@@ -155,23 +313,340 @@ mod tests {
     fn test_refreshed_obligation_healthy() {
         let refreshed = RefreshedObligation {
             borrowed_value: Decimal::from(100),
+            allowed_borrow_value: Decimal::from(110),
             unhealthy_borrow_value: Decimal::from(120),
             deposits: vec![],
             borrows: vec![],
         };
         
         assert!(!refreshed.is_unhealthy());
+        assert!(refreshed.health_factor() < Decimal::ONE);
     }
 
     #[test]
     fn test_refreshed_obligation_unhealthy() {
         let refreshed = RefreshedObligation {
             borrowed_value: Decimal::from(150),
+            allowed_borrow_value: Decimal::from(110),
             unhealthy_borrow_value: Decimal::from(120),
             deposits: vec![],
             borrows: vec![],
         };
         
         assert!(refreshed.is_unhealthy());
+        assert_eq!(refreshed.health_factor(), Decimal::new(125, 2));
+    }
+
+    #[test]
+    fn test_liquidation_margin_and_warn_zone() {
+        let mut refreshed = RefreshedObligation {
+            borrowed_value: Decimal::from(121),
+            allowed_borrow_value: Decimal::from(110),
+            unhealthy_borrow_value: Decimal::from(120),
+            deposits: vec![],
+            borrows: vec![],
+        };
+        
+        // Barely over the threshold: liquidatable without a margin, not with 2%
+        assert!(refreshed.is_liquidatable(0.0));
+        assert!(!refreshed.is_liquidatable(0.02));
+        assert!(!refreshed.is_near_liquidation(0.05));
+        
+        refreshed.borrowed_value = Decimal::from(123);
+        assert!(refreshed.is_liquidatable(0.02));
+        
+        // $115 is within 5% of $120 but not within 2%
+        refreshed.borrowed_value = Decimal::from(115);
+        assert!(!refreshed.is_liquidatable(0.0));
+        assert!(refreshed.is_near_liquidation(0.05));
+        assert!(!refreshed.is_near_liquidation(0.02));
+    }
+
+    #[test]
+    fn test_health_factor_and_utilization() {
+        let mut refreshed = RefreshedObligation {
+            borrowed_value: Decimal::from(60),
+            allowed_borrow_value: Decimal::from(75),
+            unhealthy_borrow_value: Decimal::from(80),
+            deposits: vec![],
+            borrows: vec![],
+        };
+        
+        assert_eq!(refreshed.health_factor(), Decimal::new(75, 2));
+        assert_eq!(refreshed.utilization(), Decimal::new(8, 1));
+        
+        // Debt with no collateral left is as unhealthy as it gets
+        refreshed.allowed_borrow_value = Decimal::ZERO;
+        refreshed.unhealthy_borrow_value = Decimal::ZERO;
+        assert_eq!(refreshed.health_factor(), Decimal::MAX);
+        assert_eq!(refreshed.utilization(), Decimal::MAX);
+        
+        refreshed.borrowed_value = Decimal::ZERO;
+        assert_eq!(refreshed.health_factor(), Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_capped_repay_amount() {
+        let borrow = RefreshedBorrow {
+            borrow_reserve: String::new(),
+            borrowed_amount_wads: 1_000 * crate::utils::WAD,
+            market_value: Decimal::from(1000),
+            symbol: "USDC".to_string(),
+            mint_address: String::new(),
+        };
+        
+        // Close factor caps a large wallet balance
+        assert_eq!(borrow.capped_repay_amount(5_000, 0.2), 200);
+        // Wallet balance caps when smaller
+        assert_eq!(borrow.capped_repay_amount(50, 0.2), 50);
+    }
+
+    fn test_reserve(mint: Pubkey) -> Reserve {
+        let mut reserve = Reserve::test_reserve(1_000, 0);
+        reserve.liquidity.mint_pubkey = mint;
+        // 1:1 collateral exchange rate
+        reserve.collateral.mint_total_supply = 1_000;
+        reserve
+    }
+    
+    fn test_oracle(symbol: &str, mint: Pubkey, price: i64) -> TokenOracleData {
+        TokenOracleData {
+            symbol: symbol.to_string(),
+            reserve_address: String::new(),
+            mint_address: mint.to_string(),
+            decimals: 1_000_000,
+            price: Decimal::from(price),
+            confidence: Decimal::ZERO,
+            ema_price: Decimal::from(price),
+            trusted: true,
+        }
+    }
+    
+    #[test]
+    fn test_calculate_refreshed_obligation_matches_reserves() {
+        use crate::models::{LastUpdate, ObligationCollateral, ObligationLiquidity};
+        
+        let (sol_reserve, sol_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (usdc_reserve, usdc_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let unknown_reserve = Pubkey::new_unique();
+        
+        let reserves: HashMap<Pubkey, Reserve> = [
+            (sol_reserve, test_reserve(sol_mint)),
+            (usdc_reserve, test_reserve(usdc_mint)),
+        ].into_iter().collect();
+        
+        let oracle_data: HashMap<String, TokenOracleData> = [
+            ("SOL".to_string(), test_oracle("SOL", sol_mint, 100)),
+            ("USDC".to_string(), test_oracle("USDC", usdc_mint, 1)),
+        ].into_iter().collect();
+        
+        let obligation = Obligation {
+            version: 1,
+            last_update: LastUpdate { slot: 1, stale: false },
+            lending_market: Pubkey::default(),
+            owner: Pubkey::default(),
+            deposited_value: 0,
+            borrowed_value: 0,
+            allowed_borrow_value: 0,
+            unhealthy_borrow_value: 0,
+            deposits: vec![
+                ObligationCollateral {
+                    deposit_reserve: sol_reserve,
+                    deposited_amount: 2_000_000,
+                    market_value: 0,
+                },
+                // Reserve missing from the map is skipped
+                ObligationCollateral {
+                    deposit_reserve: unknown_reserve,
+                    deposited_amount: 1_000_000,
+                    market_value: 0,
+                },
+            ],
+            borrows: vec![ObligationLiquidity {
+                borrow_reserve: usdc_reserve,
+                cumulative_borrow_rate_wads: crate::utils::WAD,
+                borrowed_amount_wads: 150_000_000 * crate::utils::WAD,
+                market_value: 0,
+            }],
+        };
+        
+        let refreshed = calculate_refreshed_obligation(&obligation, &reserves, &oracle_data, false).unwrap();
+        
+        assert_eq!(refreshed.deposits.len(), 1);
+        assert_eq!(refreshed.deposits[0].deposit_reserve, sol_reserve.to_string());
+        assert_eq!(refreshed.deposits[0].symbol, "SOL");
+        assert_eq!(refreshed.deposits[0].market_value, Decimal::from(200));
+        
+        assert_eq!(refreshed.borrows.len(), 1);
+        assert_eq!(refreshed.borrows[0].borrow_reserve, usdc_reserve.to_string());
+        assert_eq!(refreshed.borrows[0].symbol, "USDC");
+        assert_eq!(refreshed.borrows[0].market_value, Decimal::from(150));
+        
+        // $200 * 80% threshold = $160 > $150 borrowed
+        assert!(!refreshed.is_unhealthy());
+        assert_eq!(refreshed.untrusted_symbol(&oracle_data), None);
+        
+        let mut oracle_data = oracle_data;
+        oracle_data.get_mut("USDC").unwrap().trusted = false;
+        assert_eq!(refreshed.untrusted_symbol(&oracle_data), Some("USDC"));
+        
+        // A wick in spot SOL to $90 makes it unhealthy ($144 < $150) but not on EMA
+        let sol = oracle_data.get_mut("SOL").unwrap();
+        sol.price = Decimal::from(90);
+        let spot = calculate_refreshed_obligation(&obligation, &reserves, &oracle_data, false).unwrap();
+        let ema = calculate_refreshed_obligation(&obligation, &reserves, &oracle_data, true).unwrap();
+        assert!(spot.is_unhealthy());
+        assert!(!ema.is_unhealthy());
+        // Repay sizing still sees spot values
+        assert_eq!(ema.deposits[0].market_value, Decimal::from(180));
+        
+        // 10% interest accrued on the USDC reserve since the obligation's last refresh:
+        // $165 of debt against a $160 threshold at the original SOL price
+        let sol = oracle_data.get_mut("SOL").unwrap();
+        sol.price = Decimal::from(100);
+        let mut reserves = reserves;
+        reserves.get_mut(&usdc_reserve).unwrap().liquidity.cumulative_borrow_rate_wads =
+            crate::utils::WAD + crate::utils::WAD / 10;
+        let accrued = calculate_refreshed_obligation(&obligation, &reserves, &oracle_data, false).unwrap();
+        assert_eq!(accrued.borrows[0].borrowed_amount_wads, 165_000_000 * crate::utils::WAD);
+        assert_eq!(accrued.borrows[0].market_value, Decimal::from(165));
+        assert_eq!(accrued.borrowed_value, Decimal::from(165));
+        assert!(accrued.is_unhealthy());
+    }
+
+    #[test]
+    fn test_select_best_liquidation_pair() {
+        let (sol_reserve, msol_reserve) = (Pubkey::new_unique(), Pubkey::new_unique());
+        
+        let mut sol = test_reserve(Pubkey::new_unique());
+        sol.liquidity.available_amount = 1_000_000_000;
+        sol.config.liquidation_bonus = 5;
+        let mut msol = test_reserve(Pubkey::new_unique());
+        msol.liquidity.available_amount = 1_000_000_000;
+        msol.config.liquidation_bonus = 10;
+        let reserves: HashMap<Pubkey, Reserve> =
+            [(sol_reserve, sol), (msol_reserve, msol)].into_iter().collect();
+        
+        let deposit = |reserve: Pubkey, symbol: &str, value: i64| RefreshedDeposit {
+            deposit_reserve: reserve.to_string(),
+            deposited_amount: 0,
+            liquidity_amount: Decimal::from(value * 1_000_000),
+            market_value: Decimal::from(value),
+            symbol: symbol.to_string(),
+            mint_address: String::new(),
+        };
+        let borrow = |symbol: &str, value: i64| RefreshedBorrow {
+            borrow_reserve: String::new(),
+            borrowed_amount_wads: 0,
+            market_value: Decimal::from(value),
+            symbol: symbol.to_string(),
+            mint_address: String::new(),
+        };
+        
+        let mut refreshed = RefreshedObligation {
+            borrowed_value: Decimal::from(150),
+            allowed_borrow_value: Decimal::from(110),
+            unhealthy_borrow_value: Decimal::from(120),
+            deposits: vec![deposit(sol_reserve, "SOL", 200), deposit(msol_reserve, "mSOL", 100)],
+            borrows: vec![borrow("USDC", 100), borrow("USDT", 50)],
+        };
+        
+        // The smaller mSOL deposit still covers the USDC borrow at a higher bonus
+        assert_eq!(refreshed.select_withdraw_deposit(&reserves).unwrap().symbol, "SOL");
+        let (b, d) = refreshed.select_best_liquidation_pair(&reserves).unwrap();
+        assert_eq!((b.symbol.as_str(), d.symbol.as_str()), ("USDC", "mSOL"));
+        
+        // Once mSOL can only seize $40, SOL's $100 at 5% wins
+        refreshed.deposits[1] = deposit(msol_reserve, "mSOL", 40);
+        let (_, d) = refreshed.select_best_liquidation_pair(&reserves).unwrap();
+        assert_eq!(d.symbol, "SOL");
+        
+        assert_eq!(refreshed.liquidation_priority(&reserves), Decimal::from(5));
+        
+        // A fully borrowed-out SOL reserve can't redeem the seized collateral
+        let mut drained = reserves.clone();
+        drained.get_mut(&sol_reserve).unwrap().liquidity.available_amount = 0;
+        assert_eq!(refreshed.select_withdraw_deposit(&drained).unwrap().symbol, "mSOL");
+        let (_, d) = refreshed.select_best_liquidation_pair(&drained).unwrap();
+        assert_eq!(d.symbol, "mSOL");
+        assert_eq!(refreshed.deposits[0].available_liquidity(&drained), Some(0));
+        
+        // Deposits without a known reserve are never selected
+        assert!(refreshed.select_best_liquidation_pair(&HashMap::new()).is_none());
+        assert_eq!(refreshed.liquidation_priority(&HashMap::new()), Decimal::ZERO);
+    }
+    
+    #[test]
+    fn test_illiquid_best_valued_deposit_is_passed_over() {
+        let (sol_reserve, msol_reserve) = (Pubkey::new_unique(), Pubkey::new_unique());
+        
+        // The SOL reserve can only pay out $5 of the $200 deposit
+        let mut sol = test_reserve(Pubkey::new_unique());
+        sol.liquidity.available_amount = 5_000_000;
+        let mut msol = test_reserve(Pubkey::new_unique());
+        msol.liquidity.available_amount = 1_000_000_000;
+        let reserves: HashMap<Pubkey, Reserve> =
+            [(sol_reserve, sol), (msol_reserve, msol)].into_iter().collect();
+        
+        let deposit = |reserve: Pubkey, symbol: &str, value: i64| RefreshedDeposit {
+            deposit_reserve: reserve.to_string(),
+            deposited_amount: 0,
+            liquidity_amount: Decimal::from(value * 1_000_000),
+            market_value: Decimal::from(value),
+            symbol: symbol.to_string(),
+            mint_address: String::new(),
+        };
+        let refreshed = RefreshedObligation {
+            borrowed_value: Decimal::from(150),
+            allowed_borrow_value: Decimal::from(110),
+            unhealthy_borrow_value: Decimal::from(120),
+            deposits: vec![deposit(sol_reserve, "SOL", 200), deposit(msol_reserve, "mSOL", 100)],
+            borrows: vec![RefreshedBorrow {
+                borrow_reserve: String::new(),
+                borrowed_amount_wads: 150_000_000 * crate::utils::WAD,
+                market_value: Decimal::from(150),
+                symbol: "USDC".to_string(),
+                mint_address: String::new(),
+            }],
+        };
+        
+        let (borrow, deposit) = refreshed.select_best_liquidation_pair(&reserves).unwrap();
+        assert_eq!(deposit.symbol, "mSOL");
+        
+        // Were SOL seized anyway, the repay shrinks until the redeem fits the reserve
+        let sol_deposit = &refreshed.deposits[0];
+        let available = sol_deposit.available_liquidity(&reserves).unwrap();
+        let repay = crate::liquidation::max_repay_for_available(borrow, sol_deposit, available, 5);
+        assert!(repay > 0 && repay < borrow.capped_repay_amount(u64::MAX, 0.2));
+        assert!(crate::liquidation::seized_collateral_amount(borrow, sol_deposit, repay, 5) <= available);
+    }
+    
+    #[test]
+    fn test_select_repay_borrow_fundable() {
+        let borrow = |symbol: &str, value: i64| RefreshedBorrow {
+            borrow_reserve: String::new(),
+            borrowed_amount_wads: 0,
+            market_value: Decimal::from(value),
+            symbol: symbol.to_string(),
+            mint_address: format!("{}-mint", symbol),
+        };
+        let refreshed = RefreshedObligation {
+            borrowed_value: Decimal::from(150),
+            allowed_borrow_value: Decimal::from(110),
+            unhealthy_borrow_value: Decimal::from(120),
+            deposits: vec![],
+            borrows: vec![borrow("USDC", 100), borrow("USDT", 50), borrow("SOL", 20)],
+        };
+        
+        // Only USDT and SOL held: the larger of those wins over the unfunded USDC
+        let balances: HashMap<String, Decimal> = [
+            ("USDC-mint".to_string(), Decimal::ZERO),
+            ("USDT-mint".to_string(), Decimal::new(5, 1)),
+            ("SOL-mint".to_string(), Decimal::from(3)),
+        ].into_iter().collect();
+        assert_eq!(refreshed.select_repay_borrow_fundable(&balances).unwrap().symbol, "USDT");
+        assert_eq!(refreshed.select_repay_borrow().unwrap().symbol, "USDC");
+        
+        assert!(refreshed.select_repay_borrow_fundable(&HashMap::new()).is_none());
     }
 }
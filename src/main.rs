@@ -1,27 +1,32 @@
 use anyhow::{anyhow, Result};
 use clap::Parser;
-use log::{error, info, warn};
+use log::{debug, error, info, warn};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
 use solana_sdk::signature::{read_keypair_file, Signer};
 use std::str::FromStr;
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::Semaphore;
+use tokio::sync::{Mutex, Semaphore};
 use futures::future::join_all;
 
-mod config;
-mod liquidation;
-mod models;
-mod oracle;
-mod rpc;
-mod utils;
-mod wallet;
-mod metrics;
-mod cache;
+use solend_liquidator::{market_task, metrics, models, notify, oracle, replay, report, utils, wallet};
+use solend_liquidator::config::Config;
+use solend_liquidator::liquidation::execute::missing_destination_accounts;
+use solend_liquidator::liquidation::profit::repay_value_usd;
+use solend_liquidator::liquidation::refresh::RefreshedBorrow;
+use solend_liquidator::liquidation::{
+    calculate_refreshed_obligation, estimate_liquidation_profit, liquidate_and_redeem, max_repay_for_available,
+    seized_collateral_amount, swap_collateral_to_repay, ComputeUnitEstimator, ConfirmOptions,
+    LiquidationCosts, LiquidationError, RefreshedObligation,
+};
+use solend_liquidator::wallet::JupiterClient;
+use solend_liquidator::rpc::jito::JitoClient;
+use solend_liquidator::rpc::{BlockhashManager, KnownObligations, ObligationWatcher, ReserveCache, SolendRpcClient};
+use solend_liquidator::wallet::get_wallet_token_balance;
 
-use config::Config;
-use liquidation::{calculate_refreshed_obligation, liquidate_and_redeem};
-use rpc::SolendRpcClient;
-use wallet::get_wallet_token_balance;
+/// Times a liquidation is rebuilt after its blockhash expired before it landed
+const MAX_BLOCKHASH_RETRIES: u32 = 1;
 
 /// Solend Liquidator Bot - Rust Edition
 #[derive(Parser, Debug)]
@@ -30,6 +35,50 @@ struct Args {
     /// Run in dry-run mode (no transactions will be submitted)
     #[arg(long)]
     dry_run: bool,
+    
+    /// Only process these markets (comma-separated addresses or names)
+    #[arg(long, value_delimiter = ',')]
+    markets: Option<Vec<String>>,
+    
+    /// Print unhealthy obligations across markets and exit (no wallet needed)
+    #[arg(long)]
+    scan: bool,
+    
+    /// Fetch markets from the Solend API even if the local cache is fresh
+    #[arg(long)]
+    refresh_markets: bool,
+    
+    /// Run a single epoch and exit, failing if any market failed (for cron/systemd timers)
+    #[arg(long)]
+    once: bool,
+    
+    /// Stop after this many epochs, failing if any market failed in any of them
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_epochs: Option<u64>,
+    
+    /// Estimate every liquidation's profit without building or sending transactions,
+    /// reporting captured and missed opportunity totals each epoch
+    #[arg(long, conflicts_with = "dry_run")]
+    shadow: bool,
+    
+    /// Print the liquidations the bot would make on recorded account dumps in DIR and exit (offline)
+    #[arg(long, value_name = "DIR")]
+    replay: Option<std::path::PathBuf>,
+    
+    /// Check one obligation and attempt a single liquidation of it, then exit
+    #[arg(long, value_name = "OBLIGATION", conflicts_with_all = ["scan", "replay"])]
+    liquidate: Option<solana_sdk::pubkey::Pubkey>,
+}
+
+impl Args {
+    /// Epochs to run before exiting; `None` runs forever
+    fn epoch_limit(&self) -> Option<u64> {
+        if self.once {
+            Some(1)
+        } else {
+            self.max_epochs
+        }
+    }
 }
 
 #[tokio::main]
@@ -40,37 +89,98 @@ async fn main() -> Result<()> {
     // Initialize logger
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
         .init();
+    market_task::install_panic_logger();
     
     info!("Starting Solend Liquidator Bot (Rust)");
     
     if args.dry_run {
         warn!("⚠️  DRY-RUN MODE ENABLED - No transactions will be submitted ⚠️");
     }
+    if args.shadow {
+        warn!("⚠️  SHADOW MODE ENABLED - No transactions will be built or submitted ⚠️");
+    }
     
     // Load configuration
     let config = Config::from_env()?;
     
+    if let Some(dir) = &args.replay {
+        return replay_snapshot(&config, &args, dir).await;
+    }
+    
     // Validate RPC endpoint
     if config.rpc_endpoint.is_empty() {
         return Err(anyhow!("Please provide a private RPC endpoint in .env"));
     }
     
+    if let Some(proceeds_wallet) = &config.proceeds_wallet {
+        info!("Sending liquidation proceeds to {}", proceeds_wallet);
+        if config.auto_swap_collateral {
+            warn!("AUTO_SWAP_COLLATERAL is ignored: seized collateral goes to PROCEEDS_WALLET");
+        }
+    }
+    
     // Fetch markets
-    let markets = config.fetch_markets().await?;
+    let mut markets = config.fetch_markets(args.refresh_markets).await?;
+    
+    if let Some(selected) = &args.markets {
+        markets.retain(|m| selected.iter().any(|s| s == &m.address || s == &m.name));
+        
+        if markets.is_empty() {
+            return Err(anyhow!("No markets match --markets {}", selected.join(",")));
+        }
+        
+        for market in &markets {
+            info!("Selected market: {} ({})", market.name, market.address);
+        }
+    }
     
     // Initialize RPC client
-    let rpc_client = Arc::new(SolendRpcClient::new(&config.rpc_endpoint, &config.app)?);
+    let rpc_client = Arc::new(
+        SolendRpcClient::new_with_commitment(
+            &config.rpc_endpoint,
+            &config.app,
+            config.read_commitment,
+            config.confirm_commitment,
+        )?
+            .with_retry_policy(config.rpc_max_attempts, config.rpc_retry_base_ms)
+            .with_timeout(std::time::Duration::from_secs(config.rpc_timeout_secs))
+            .with_rate_limit(config.rpc_requests_per_second)
+            .with_reserve_cache_ttl(config.reserve_cache_ttl_secs),
+    );
+    
+    if args.scan {
+        return scan_markets(&rpc_client, &config, &markets).await;
+    }
+    
+    if config.secret_paths.is_empty() {
+        return Err(anyhow!("SECRET_PATH or SECRET_PATHS must be set in environment"));
+    }
+    
+    // Load wallet keypairs
+    let wallets = config.secret_paths
+        .iter()
+        .map(|path| {
+            read_keypair_file(path)
+                .map(Arc::new)
+                .map_err(|e| anyhow!("Failed to read keypair from {}: {}", path, e))
+        })
+        .collect::<Result<Vec<_>>>()?;
     
-    // Load wallet keypair
-    let payer = Arc::new(read_keypair_file(&config.secret_path)
-        .map_err(|e| anyhow!("Failed to read keypair from {}: {}", config.secret_path, e))?);
+    // One blockhash shared by every liquidation and swap, refetched near expiry
+    let blockhashes = Arc::new(BlockhashManager::new(config.blockhash_refresh_margin_blocks));
+    
+    if let Some(obligation) = args.liquidate {
+        return liquidate_single(&rpc_client, &blockhashes, &config, &args, &markets, &wallets, obligation).await;
+    }
     
     let config_arc = Arc::new(config.clone()); // Clone config for sharing (it's cheap if fields are strings)
     
     info!("\nConfiguration:");
     info!("  app: {}", config.app);
     info!("  rpc: {}", config.rpc_endpoint);
-    info!("  wallet: {}", payer.pubkey());
+    for wallet in &wallets {
+        info!("  wallet: {}", wallet.pubkey());
+    }
     info!("  auto-rebalancing: {}", if config.targets.is_empty() { "OFF" } else { "ON" });
     if !config.targets.is_empty() {
         info!("  rebalancing targets: {} tokens", config.targets.len());
@@ -78,47 +188,144 @@ async fn main() -> Result<()> {
     info!("  Running against {} markets", markets.len());
     info!("");
     
-    // Pre-build token mints cache for all markets (optimization)
-    info!("Building token mints cache...");
-    let mut token_mints_cache: HashMap<String, HashMap<String, (solana_sdk::pubkey::Pubkey, u8)>> = HashMap::new();
-    for market in &markets {
-        let mut mints = HashMap::new();
-        for reserve in &market.reserves {
-            if let Ok(mint) = solana_sdk::pubkey::Pubkey::from_str(&reserve.liquidity_token.mint) {
-        mints.insert(
-                    reserve.liquidity_token.symbol.clone(),
-                    (mint, reserve.decimals()),
-                );
-            }
+    // Decimals and symbols of every market's tokens, known up front so they're never fetched
+    let mint_decimals = Arc::new(wallet::MintDecimalsCache::new());
+    let mut mint_symbols: HashMap<solana_sdk::pubkey::Pubkey, String> = HashMap::new();
+    // Every market's tokens by symbol, for wallet rebalancing; earlier markets win clashes
+    let mut token_mints: HashMap<String, (solana_sdk::pubkey::Pubkey, u8)> = HashMap::new();
+    for reserve in markets.iter().flat_map(|market| &market.reserves) {
+        if let Ok(mint) = solana_sdk::pubkey::Pubkey::from_str(&reserve.liquidity_token.mint) {
+            mint_decimals.insert(mint, reserve.decimals());
+            mint_symbols.insert(mint, reserve.liquidity_token.symbol.clone());
+            token_mints
+                .entry(reserve.liquidity_token.symbol.clone())
+                .or_insert((mint, reserve.decimals()));
         }
-        token_mints_cache.insert(market.address.clone(), mints);
     }
-    let token_mints_cache = Arc::new(token_mints_cache);
-    info!("Token mints cache built for {} markets", token_mints_cache.len());
+    
+    let notifiers = Arc::new(notify::Notifiers::from_config(&config));
+    if !notifiers.is_empty() {
+        info!("{} notification sink(s) enabled", notifiers.len());
+    }
+    
+    // Reserves change slowly; oracle prices are still fetched every epoch
+    let reserve_cache = Arc::new(std::sync::Mutex::new(ReserveCache::new(config.reserve_cache_ttl_secs)));
+    
+    // Subscribe before the first full fetch so no update falls in between
+    let mut watchers: HashMap<String, Arc<Mutex<ObligationWatcher>>> = HashMap::new();
+    if config.use_websocket {
+        info!("Subscribing to obligation updates via {}", config.ws_endpoint);
+        for market in &markets {
+            let watcher = rpc_client.subscribe_obligations(&config.ws_endpoint, &market.address)?;
+            watchers.insert(market.address.clone(), Arc::new(Mutex::new(watcher)));
+        }
+    }
+    
+    // Healthy obligations' data is reused between full resyncs
+    let mut known_obligations: HashMap<String, Arc<Mutex<KnownObligations>>> = HashMap::new();
+    if config.obligation_resync_secs > 0 && !config.use_websocket {
+        for market in &markets {
+            known_obligations.insert(
+                market.address.clone(),
+                Arc::new(Mutex::new(KnownObligations::new(config.obligation_resync_secs))),
+            );
+        }
+    }
+    
+    // Compute unit limits learned from simulations, shared by every market
+    let compute_units = config.estimate_compute_units
+        .then(|| Arc::new(ComputeUnitEstimator::new(config.compute_unit_margin_percent)));
+    
+    // Unhealthy obligations from the previous epoch, so long-standing ones aren't re-logged
+    let unhealthy_sets: HashMap<String, Arc<Mutex<report::UnhealthySet>>> = markets
+        .iter()
+        .map(|market| (market.address.clone(), Arc::new(Mutex::new(report::UnhealthySet::default()))))
+        .collect();
     
     // Semaphore to limit concurrent market processing
     // Use a reasonable limit (e.g., 10) to avoid open file limits or overwhelming RPC
     let semaphore = Arc::new(Semaphore::new(10));
     let args_arc = Arc::new(args);
     
+    // Health endpoint for liveness probes
+    let health = Arc::new(metrics::HealthState::new());
+    if let Some(port) = config.health_port {
+        let state = health.clone();
+        let stale_after = std::time::Duration::from_secs(config.health_stale_secs);
+        tokio::spawn(async move {
+            if let Err(e) = metrics::serve_health(port, state, stale_after).await {
+                error!("Health endpoint stopped: {}", e);
+            }
+        });
+    }
+    
+    // Prometheus exporter
+    let prometheus = match config.metrics_port {
+        Some(port) => {
+            let prometheus = Arc::new(metrics::PrometheusMetrics::new()?);
+            let exporter = prometheus.clone();
+            tokio::spawn(async move {
+                if let Err(e) = metrics::serve_metrics(port, exporter).await {
+                    error!("Metrics exporter stopped: {}", e);
+                }
+            });
+            Some(prometheus)
+        }
+        None => None,
+    };
+    
+    // Dust sweeping leaves rebalancing targets alone
+    let target_mints: std::collections::HashSet<solana_sdk::pubkey::Pubkey> = mint_symbols
+        .iter()
+        .filter(|(_, symbol)| config_arc.targets.iter().any(|target| &target.symbol == *symbol))
+        .map(|(mint, _)| *mint)
+        .collect();
+    
+    // Dust sweeping and rebalancing both swap into this token; one without a known mint is a config error
+    let rebalance_base = if config_arc.sweep_dust || !config_arc.targets.is_empty() {
+        Some(config_arc.rebalance_base(&token_mints)?)
+    } else {
+        None
+    };
+    
+    // Shared by the per-epoch wallet sweeping and rebalancing
+    let jupiter = wallet::JupiterClient::with_config(&config_arc.jupiter_api_url, config_arc.jupiter_api_key.clone())
+        .with_http_config(&config_arc.http)
+        .with_max_swap_attempts(config_arc.swap_max_attempts)
+        .with_blockhashes(blockhashes.clone());
+    
     // Main liquidation loop
     let mut epoch = 0u64;
+    let mut failed_epochs = 0u64;
+    let mut shadow_totals = report::ShadowTotals::default();
     
     loop {
         epoch += 1;
         let mut overall_metrics = metrics::PerformanceMetrics::start_epoch();
         
+        if let Ok(mut cache) = reserve_cache.lock() {
+            cache.prune_expired();
+        }
+        
         let mut tasks = Vec::new();
         
-        for market in markets.clone() { // Clone market config for each task
+        for (i, market) in markets.clone().into_iter().enumerate() { // Clone market config for each task
             let rpc_client = rpc_client.clone();
-            let payer = payer.clone();
+            // Each market always signs with the same wallet, so balances stay consistent
+            let payer = wallets[i % wallets.len()].clone();
             let config = config_arc.clone();
             let args = args_arc.clone();
-            let token_mints_cache = token_mints_cache.clone();
+            let reserve_cache = reserve_cache.clone();
+            let mint_decimals = mint_decimals.clone();
+            let notifiers = notifiers.clone();
             let semaphore = semaphore.clone();
+            let watcher = watchers.get(&market.address).cloned();
+            let known = known_obligations.get(&market.address).cloned();
+            let unhealthy_set = unhealthy_sets[&market.address].clone();
+            let compute_units = compute_units.clone();
+            let blockhashes = blockhashes.clone();
             
-            tasks.push(tokio::spawn(async move {
+            tasks.push(tokio::spawn(market_task::run_guarded(async move {
                 let _permit = semaphore.acquire().await.unwrap();
                 process_market(
                     rpc_client,
@@ -126,68 +333,140 @@ async fn main() -> Result<()> {
                     payer,
                     args,
                     market,
-                    token_mints_cache,
+                    reserve_cache,
+                    mint_decimals,
+                    notifiers,
+                    watcher,
+                    known,
+                    unhealthy_set,
+                    compute_units,
+                    blockhashes,
                 ).await
-            }));
+            })));
         }
         
         // Wait for all markets to be processed
         let results = join_all(tasks).await;
         
-        // Aggregate metrics
-        for result in results {
+        // Aggregate metrics; tasks were spawned in market order
+        let mut rpc_ok = true;
+        for (market, result) in markets.iter().zip(results) {
             match result {
-                Ok(Ok(metrics)) => {
-                    // Manually sum up metrics
-                    overall_metrics.oracle_fetch_ms += metrics.oracle_fetch_ms;
-                    overall_metrics.obligations_fetch_ms += metrics.obligations_fetch_ms;
-                    overall_metrics.reserves_fetch_ms += metrics.reserves_fetch_ms;
-                    overall_metrics.processing_ms += metrics.processing_ms;
-                    overall_metrics.total_obligations += metrics.total_obligations;
-                    overall_metrics.unhealthy_obligations += metrics.unhealthy_obligations;
-                    overall_metrics.liquidations_attempted += metrics.liquidations_attempted;
-                }
+                Ok(Ok(metrics)) => overall_metrics.merge_market(&market.name, metrics),
                 Ok(Err(e)) => {
-                    error!("Market processing failed: {}", e);
+                    error!("[{}] {}", market.name, e);
+                    rpc_ok = false;
                 }
                 Err(e) => {
                     error!("Task join error: {}", e);
+                    rpc_ok = false;
                 }
             }
         }
         
+        // Every transaction pays fees in SOL, so running dry fails them all
+        for payer in &wallets {
+            match rpc_client.client().get_balance(&payer.pubkey()) {
+                Ok(lamports) => {
+                    let balance_sol = solana_sdk::native_token::lamports_to_sol(lamports);
+                    if balance_sol < config_arc.min_sol_balance {
+                        warn!(
+                            "⛽ LOW SOL: wallet {} has {:.4} SOL (minimum {}); transactions will start failing",
+                            payer.pubkey(),
+                            balance_sol,
+                            config_arc.min_sol_balance
+                        );
+                        notifiers.send(notify::Event::LowSolBalance {
+                            wallet: payer.pubkey().to_string(),
+                            balance_sol,
+                            min_sol: config_arc.min_sol_balance,
+                        }).await;
+                    }
+                    overall_metrics.sol_balances.push((payer.pubkey().to_string(), lamports));
+                }
+                Err(e) => warn!("Failed to get SOL balance for {}: {}", payer.pubkey(), e),
+            }
+        }
+        
         // Post-processing: Unwrap and Rebalance ONCE per epoch (safer and more efficient than per market)
+        // Shadow mode never sends transactions, so it skips these
         
-        // Unwrap wrapped tokens
-        if let Err(e) = wallet::unwrap_all_wrapped_tokens(rpc_client.client(), &payer).await {
-            warn!("Failed to unwrap tokens: {}", e);
-        }
+        if !args_arc.shadow {
+            for payer in &wallets {
+                // Unwrap wrapped tokens
+                if let Err(e) = wallet::unwrap_all_wrapped_tokens(rpc_client.client(), payer, &config_arc.ktokens).await {
+                    warn!("Failed to unwrap tokens for {}: {}", payer.pubkey(), e);
+                }
+            
+                if let Some(base) = rebalance_base.as_ref().filter(|_| config_arc.sweep_dust) {
+                    match wallet::sweep_dust(
+                        &rpc_client,
+                        payer,
+                        base,
+                        &mint_decimals,
+                        &jupiter,
+                        &mint_symbols,
+                        &target_mints,
+                        config_arc.min_sweep_usd,
+                        &config_arc.slippage,
+                        config_arc.swap_priority_fee_lamports,
+                        config_arc.max_price_impact_pct,
+                    ).await {
+                        Ok(0) => {}
+                        Ok(swept) => info!("Swept {} tokens to {} for {}", swept, base.symbol, payer.pubkey()),
+                        Err(e) => warn!("Failed to sweep dust for {}: {}", payer.pubkey(), e),
+                    }
+                }
         
-        // Rebalance wallet if targets configured
-        // We use the first market's token mints for reference or merge them?
-        // Actually rebalance_wallet needs a map of all token mints to check balances properly?
-        // The implementation uses `token_mints` mainly for decimals lookup of target tokens.
-        // We can pass a merged map or just pick one if targets are commonly available.
-        // Better: Pass the full cache or create a combined map if needed.
-        // For now, let's use the first available market map assuming targets are liquid tokens present in markets.
-        if !config_arc.targets.is_empty() {
-             // Find a market that has the target tokens? 
-             // Simplification: Use the first market map found, or merge.
-             if let Some(first_market_mints) = token_mints_cache.values().next() {
-                 if let Err(e) = wallet::rebalance_wallet(
-                    rpc_client.client(),
-                    &payer,
-                    &config_arc.app,
-                    &config_arc.targets,
-                    config_arc.rebalance_padding,
-                    first_market_mints,
-                ).await {
-                    warn!("Failed to rebalance wallet: {}", e);
+                // Rebalance wallet if targets configured
+                if let Some(base) = rebalance_base.as_ref().filter(|_| !config_arc.targets.is_empty()) {
+                    if let Err(e) = wallet::rebalance_wallet(
+                        rpc_client.client(),
+                        payer,
+                        base,
+                        &config_arc.targets,
+                        config_arc.rebalance_padding,
+                        &token_mints,
+                        &jupiter,
+                        &config_arc.slippage,
+                        config_arc.swap_priority_fee_lamports,
+                        config_arc.max_price_impact_pct,
+                    ).await {
+                        warn!("Failed to rebalance wallet {}: {}", payer.pubkey(), e);
+                    }
                 }
-             }
+            }
         }
         
         overall_metrics.log_summary();
+        if args_arc.dry_run {
+            overall_metrics.dry_run.log();
+        }
+        if args_arc.shadow {
+            shadow_totals.merge(&overall_metrics.shadow);
+            info!("Shadow opportunities this epoch: {}", overall_metrics.shadow.render());
+            info!("Shadow opportunities since start: {}", shadow_totals.render());
+        }
+        if let Some(prometheus) = &prometheus {
+            prometheus.record_epoch(&overall_metrics);
+        }
+        health.record_epoch(epoch, rpc_ok);
+        if !rpc_ok {
+            failed_epochs += 1;
+        }
+        if let Some(path) = &config_arc.metrics_file {
+            if let Err(e) = overall_metrics.append_to_jsonl(path, epoch) {
+                warn!("Failed to write metrics to {}: {}", path, e);
+            }
+        }
+        if args_arc.epoch_limit().is_some_and(|limit| epoch >= limit) {
+            info!("Epoch {} complete, epoch limit reached, exiting", epoch);
+            return if failed_epochs == 0 {
+                Ok(())
+            } else {
+                Err(anyhow!("Markets failed in {} of {} epochs", failed_epochs, epoch))
+            };
+        }
         info!("Epoch {} complete, starting next iteration...\n", epoch);
         
         // Throttle to avoid rate limiting
@@ -197,14 +476,246 @@ async fn main() -> Result<()> {
     }
 }
 
+/// Print every unhealthy obligation across `markets`, most lucrative first
+/// Read-only: nothing is signed, so no wallet is loaded
+async fn scan_markets(
+    rpc_client: &SolendRpcClient,
+    config: &Config,
+    markets: &[models::MarketConfig],
+) -> Result<()> {
+    let mut rows = Vec::new();
+    
+    for market in markets {
+        let oracle_data = oracle::get_tokens_oracle_data(
+            rpc_client,
+            market,
+            config.max_conf_ratio,
+            config.oracle_tolerance_percent,
+            &config.oracle_staleness,
+        ).await?;
+        let reserves: HashMap<_, _> = rpc_client.get_reserves(&market.address)?.into_iter().collect();
+        let obligations = rpc_client.get_obligations(&market.address)?;
+        
+        for (pubkey, obligation) in &obligations {
+            let Ok(refreshed) = calculate_refreshed_obligation(
+                obligation,
+                &reserves,
+                &oracle_data,
+                config.use_ema_for_health,
+            ) else {
+                continue;
+            };
+            
+            if !refreshed.is_unhealthy() {
+                continue;
+            }
+            
+            rows.push(report::ScanRow {
+                obligation: pubkey.to_string(),
+                market: market.name.clone(),
+                borrowed_value: refreshed.borrowed_value,
+                unhealthy_borrow_value: refreshed.unhealthy_borrow_value,
+                health_factor: refreshed.health_factor(),
+                pair: refreshed
+                    .select_best_liquidation_pair(&reserves)
+                    .map(|(borrow, deposit)| (borrow.symbol.clone(), deposit.symbol.clone())),
+                bonus_value: refreshed.liquidation_priority(&reserves),
+            });
+        }
+    }
+    
+    println!("{}", report::render_scan(&rows));
+    Ok(())
+}
+
+/// Print what would be liquidated in a snapshot directory, using its recorded markets if present
+async fn replay_snapshot(config: &Config, args: &Args, dir: &std::path::Path) -> Result<()> {
+    let snapshot = replay::Snapshot::load(dir)?;
+    let mut markets = match &snapshot.markets {
+        Some(markets) => markets.clone(),
+        None => config.fetch_markets(args.refresh_markets).await?,
+    };
+    if let Some(selected) = &args.markets {
+        markets.retain(|m| selected.iter().any(|s| s == &m.address || s == &m.name));
+    }
+    
+    let slot = snapshot.latest_slot();
+    info!("Replaying {} accounts from {} at slot {}", snapshot.accounts.len(), dir.display(), slot);
+    
+    let mut decisions = Vec::new();
+    for market in &markets {
+        decisions.extend(replay::replay_market(&snapshot, market, config, slot)?);
+    }
+    
+    println!("{}", replay::render_replay(&decisions));
+    Ok(())
+}
+
+/// Check one obligation and attempt a single liquidation of it
+/// Its market comes from the obligation's lending market, and only the reserves
+/// and oracles it touches are fetched
+#[allow(clippy::too_many_arguments)]
+async fn liquidate_single(
+    rpc_client: &SolendRpcClient,
+    blockhashes: &Arc<BlockhashManager>,
+    config: &Config,
+    args: &Args,
+    markets: &[models::MarketConfig],
+    wallets: &[Arc<solana_sdk::signature::Keypair>],
+    obligation_pubkey: solana_sdk::pubkey::Pubkey,
+) -> Result<()> {
+    let account = rpc_client.get_account(&obligation_pubkey)?;
+    let mut obligation = models::Obligation::parse_detailed(&account.data)
+        .map_err(|e| anyhow!("Failed to parse obligation {}: {}", obligation_pubkey, e))?;
+    
+    let lending_market = obligation.lending_market.to_string();
+    let Some(index) = markets.iter().position(|m| m.address == lending_market) else {
+        return Err(anyhow!(
+            "Obligation {} belongs to lending market {}, which is not among the loaded markets",
+            obligation_pubkey,
+            lending_market
+        ));
+    };
+    let market = &markets[index];
+    // Same wallet the market is assigned in the main loop
+    let payer = &wallets[index % wallets.len()];
+    
+    let touched: std::collections::HashSet<solana_sdk::pubkey::Pubkey> = obligation.deposits
+        .iter()
+        .map(|d| d.deposit_reserve)
+        .chain(obligation.borrows.iter().map(|b| b.borrow_reserve))
+        .collect();
+    let mut reserves_map = HashMap::new();
+    for pubkey in &touched {
+        reserves_map.insert(*pubkey, rpc_client.get_reserve_cached(pubkey)?);
+    }
+    
+    let oracle_market = models::MarketConfig {
+        reserves: market.reserves
+            .iter()
+            .filter(|r| {
+                solana_sdk::pubkey::Pubkey::from_str(&r.address).is_ok_and(|address| touched.contains(&address))
+            })
+            .cloned()
+            .collect(),
+        ..market.clone()
+    };
+    let oracle_data = oracle::get_tokens_oracle_data(
+        rpc_client,
+        &oracle_market,
+        config.max_conf_ratio,
+        config.oracle_tolerance_percent,
+        &config.oracle_staleness,
+    ).await?;
+    
+    if config.max_obligation_slot_age > 0 {
+        let slot = rpc_client.get_slot()?;
+        if obligation.is_stale(slot, config.max_obligation_slot_age) {
+            obligation.accrue_interest(&reserves_map);
+        }
+    }
+    
+    let refreshed = calculate_refreshed_obligation(
+        &obligation,
+        &reserves_map,
+        &oracle_data,
+        config.use_ema_for_health,
+    )?;
+    info!(
+        "[{}] Obligation {}: borrowed ${:.2}, unhealthy above ${:.2}, health factor {:.4}",
+        market.name,
+        obligation_pubkey,
+        refreshed.borrowed_value.round_dp(2),
+        refreshed.unhealthy_borrow_value.round_dp(2),
+        refreshed.health_factor().round_dp(4)
+    );
+    if !refreshed.is_liquidatable(config.liquidation_margin) {
+        info!("[{}] Obligation {} is not liquidatable", market.name, obligation_pubkey);
+        return Ok(());
+    }
+    
+    let mint_decimals = wallet::MintDecimalsCache::new();
+    for reserve in &market.reserves {
+        if let Ok(mint) = solana_sdk::pubkey::Pubkey::from_str(&reserve.liquidity_token.mint) {
+            mint_decimals.insert(mint, reserve.decimals());
+        }
+    }
+    let compute_units = config.estimate_compute_units
+        .then(|| ComputeUnitEstimator::new(config.compute_unit_margin_percent));
+    
+    let mut metrics = metrics::PerformanceMetrics::default();
+    liquidate_obligations(
+        rpc_client,
+        blockhashes,
+        config,
+        payer,
+        args,
+        market,
+        &reserves_map,
+        &oracle_data,
+        vec![(&obligation_pubkey, obligation, refreshed)],
+        &mint_decimals,
+        &notify::Notifiers::from_config(config),
+        compute_units.as_ref(),
+        &mut metrics,
+    ).await?;
+    
+    if args.dry_run {
+        metrics.dry_run.log();
+    }
+    if args.shadow {
+        info!("Shadow opportunities: {}", metrics.shadow.render());
+    }
+    if metrics.liquidations_failed > 0 {
+        return Err(anyhow!("Liquidation of obligation {} failed", obligation_pubkey));
+    }
+    Ok(())
+}
+
+/// Fetch obligations to evaluate this epoch
+/// With a watcher, only obligations changed since the last epoch are returned
+/// after the first full fetch
+async fn fetch_obligations(
+    rpc_client: &SolendRpcClient,
+    market_address: &str,
+    watcher: Option<&Arc<Mutex<ObligationWatcher>>>,
+    known: Option<&Arc<Mutex<KnownObligations>>>,
+) -> Result<Vec<(solana_sdk::pubkey::Pubkey, models::Obligation)>> {
+    let Some(watcher) = watcher else {
+        return match known {
+            Some(known) => rpc_client.get_obligations_incremental(&mut *known.lock().await, market_address).await,
+            None => rpc_client.get_obligations(market_address),
+        };
+    };
+    
+    let mut watcher = watcher.lock().await;
+    let changed = watcher.drain_changed();
+    
+    if watcher.seeded {
+        return Ok(changed);
+    }
+    
+    let obligations = rpc_client.get_obligations(market_address)?;
+    watcher.seeded = true;
+    Ok(obligations)
+}
+
 /// Process a single market: fetch data, check obligations, liquidate unhealthy ones
+#[allow(clippy::too_many_arguments)]
 async fn process_market(
     rpc_client: Arc<SolendRpcClient>,
     config: Arc<Config>,
     payer: Arc<solana_sdk::signature::Keypair>,
     args: Arc<Args>,
     market: models::MarketConfig,
-    token_mints_cache: Arc<HashMap<String, HashMap<String, (solana_sdk::pubkey::Pubkey, u8)>>>,
+    reserve_cache: Arc<std::sync::Mutex<ReserveCache>>,
+    mint_decimals: Arc<wallet::MintDecimalsCache>,
+    notifiers: Arc<notify::Notifiers>,
+    watcher: Option<Arc<Mutex<ObligationWatcher>>>,
+    known: Option<Arc<Mutex<KnownObligations>>>,
+    unhealthy_set: Arc<Mutex<report::UnhealthySet>>,
+    compute_units: Option<Arc<ComputeUnitEstimator>>,
+    blockhashes: Arc<BlockhashManager>,
 ) -> Result<metrics::PerformanceMetrics> {
     let mut metrics = metrics::PerformanceMetrics::default();
     
@@ -213,15 +724,21 @@ async fn process_market(
     // Fetch data in parallel
     let fetch_start = std::time::Instant::now();
     let (oracle_result, obligations_result, reserves_result) = tokio::join!(
-        oracle::get_tokens_oracle_data(&rpc_client, &market),
-        async { rpc_client.get_obligations(&market.address) },
-        async { rpc_client.get_reserves(&market.address) }
+        oracle::get_tokens_oracle_data_with_rejections(
+            &rpc_client,
+            &market,
+            config.max_conf_ratio,
+            config.oracle_tolerance_percent,
+            &config.oracle_staleness,
+        ),
+        fetch_obligations(&rpc_client, &market.address, watcher.as_ref(), known.as_ref()),
+        async { rpc_client.get_reserves_cached(&reserve_cache, &market.address) }
     );
     
     // Note: Simple accumulating timing for metrics (won't be perfect in parallel)
     metrics.oracle_fetch_ms = fetch_start.elapsed().as_millis() as u64;
 
-    let oracle_data = match oracle_result {
+    let (oracle_data, oracle_rejections) = match oracle_result {
         Ok(data) => data,
         Err(e) => return Err(anyhow!("Failed to fetch oracle data for market {}: {}", market.name, e)),
     };
@@ -236,48 +753,227 @@ async fn process_market(
         Err(e) => return Err(anyhow!("Failed to fetch reserves for market {}: {}", market.name, e)),
     };
     
+    // Every listed market has reserves, so an empty result means the filter missed
+    if reserves.is_empty() && !market.reserves.is_empty() {
+        warn!(
+            "[{}] No reserve accounts matched although the API lists {} reserves; \
+             the lending market filter offset ({}) may be wrong for this program",
+            market.name,
+            market.reserves.len(),
+            models::reserve::LENDING_MARKET_OFFSET
+        );
+    }
+    
     let reserves_map: HashMap<solana_sdk::pubkey::Pubkey, models::Reserve> = reserves.into_iter().collect();
     
+    for rejection in oracle_rejections {
+        notifiers.send(notify::Event::OracleRejected {
+            market: market.name.clone(),
+            symbol: rejection.symbol,
+            source: rejection.source.to_string(),
+            reason: rejection.reason,
+        }).await;
+    }
+    
     metrics.total_obligations = obligations.len();
     
+    // Obligations untouched for a long time carry borrows without the interest accrued since
+    let current_slot = if config.max_obligation_slot_age > 0 {
+        match rpc_client.get_slot() {
+            Ok(slot) => Some(slot),
+            Err(e) => {
+                warn!("[{}] Failed to get slot, skipping stale obligation check: {}", market.name, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    
     // Filter unhealthy obligations
     let processing_start = std::time::Instant::now();
-    let unhealthy_obligations: Vec<_> = obligations.iter()
+    let mut near_liquidation = 0;
+    let mut stale_obligations = 0;
+    let mut healthy = Vec::new();
+    let mut unhealthy_obligations: Vec<_> = obligations.iter()
         .filter_map(|(pubkey, obligation)| {
+            let mut obligation = obligation.clone();
+            if let Some(slot) = current_slot {
+                if obligation.is_stale(slot, config.max_obligation_slot_age) {
+                    debug!(
+                        "[{}] Obligation {} last refreshed at slot {} ({} slots ago)",
+                        market.name,
+                        pubkey,
+                        obligation.last_update.slot,
+                        slot.saturating_sub(obligation.last_update.slot)
+                    );
+                    obligation.accrue_interest(&reserves_map);
+                    stale_obligations += 1;
+                }
+            }
+            
             let refreshed = calculate_refreshed_obligation(
-                obligation,
+                &obligation,
                 &reserves_map,
                 &oracle_data,
+                config.use_ema_for_health,
             ).ok()?;
             
-            if refreshed.is_unhealthy() {
-                Some((pubkey, obligation.clone(), refreshed))
+            if refreshed.is_near_liquidation(config.health_warn_margin) {
+                near_liquidation += 1;
+            } else if !refreshed.is_unhealthy() {
+                healthy.push(*pubkey);
+            }
+            
+            if refreshed.is_liquidatable(config.liquidation_margin) {
+                Some((pubkey, obligation, refreshed))
             } else {
                 None
             }
         })
         .collect();
     
+    if let Some(known) = &known {
+        known.lock().await.set_healthy(healthy);
+    }
+    
+    let unfiltered = unhealthy_obligations.len();
+    unhealthy_obligations.retain(|(_, obligation, _)| config.is_owner_allowed(&obligation.owner));
+    if unhealthy_obligations.len() < unfiltered {
+        info!(
+            "[{}] Skipping {} unhealthy obligations excluded by owner filters",
+            market.name,
+            unfiltered - unhealthy_obligations.len()
+        );
+    }
+    
+    // Dust positions cost more in fees than their liquidation bonus
+    let before_dust = unhealthy_obligations.len();
+    unhealthy_obligations.retain(|(_, _, refreshed)| {
+        refreshed.borrowed_value.to_f64().unwrap_or(0.0) >= config.min_borrowed_value_usd
+    });
+    if unhealthy_obligations.len() < before_dust {
+        info!(
+            "[{}] Skipping {} dust obligations borrowing under ${:.2}",
+            market.name,
+            before_dust - unhealthy_obligations.len(),
+            config.min_borrowed_value_usd
+        );
+    }
+    
+    if stale_obligations > 0 {
+        info!(
+            "[{}] Accrued interest on {} obligations older than {} slots",
+            market.name,
+            stale_obligations,
+            config.max_obligation_slot_age
+        );
+    }
+    
+    if near_liquidation > 0 {
+        info!(
+            "[{}] {} obligations within {:.1}% of liquidation",
+            market.name,
+            near_liquidation,
+            config.health_warn_margin * 100.0
+        );
+    }
+    
+    let diff = unhealthy_set
+        .lock()
+        .await
+        .update(unhealthy_obligations.iter().map(|(pubkey, _, _)| **pubkey));
+    for pubkey in &diff.new {
+        info!("[{}] Obligation {} became unhealthy", market.name, pubkey);
+    }
+    for pubkey in &diff.recovered {
+        info!("[{}] Obligation {} is no longer unhealthy", market.name, pubkey);
+    }
+    for pubkey in &diff.unchanged {
+        debug!("[{}] Obligation {} still unhealthy", market.name, pubkey);
+    }
+    
     if unhealthy_obligations.is_empty() {
         metrics.processing_ms = processing_start.elapsed().as_millis() as u64;
         return Ok(metrics);
     }
     
-    info!("[{}] Found {} unhealthy obligations", market.name, unhealthy_obligations.len());
+    // Spend a limited wallet on the most lucrative positions first
+    unhealthy_obligations.sort_by_cached_key(|(_, _, refreshed)| {
+        std::cmp::Reverse(refreshed.liquidation_priority(&reserves_map))
+    });
+    
+    info!(
+        "[{}] Found {} unhealthy obligations ({} new)",
+        market.name,
+        unhealthy_obligations.len(),
+        diff.new.len()
+    );
     metrics.unhealthy_obligations = unhealthy_obligations.len();
 
-    // Batch fetch wallet balances
-    let mut decimals_map = HashMap::new();
-    let mut needed_mints = std::collections::HashSet::new();
+    liquidate_obligations(
+        &rpc_client,
+        &blockhashes,
+        &config,
+        &payer,
+        &args,
+        &market,
+        &reserves_map,
+        &oracle_data,
+        unhealthy_obligations,
+        &mint_decimals,
+        &notifiers,
+        compute_units.as_deref(),
+        &mut metrics,
+    ).await?;
     
-    for reserve in &market.reserves {
-        if let Ok(mint) = solana_sdk::pubkey::Pubkey::from_str(&reserve.liquidity_token.mint) {
-            decimals_map.insert(mint, reserve.decimals());
-        }
+    metrics.processing_ms = processing_start.elapsed().as_millis() as u64;
+    Ok(metrics)
+
+}
+
+/// USD value of `lamports`, priced by the market's SOL oracle, or else by quoting
+/// the SOL into `borrow`'s token for markets without a SOL reserve
+async fn lamports_to_usd(
+    jupiter: &JupiterClient,
+    oracle_data: &HashMap<String, oracle::TokenOracleData>,
+    borrow: &RefreshedBorrow,
+    lamports: u64,
+) -> Result<Decimal> {
+    let native_mint = spl_token::native_mint::ID.to_string();
+    if let Some(sol) = oracle_data.values().find(|data| data.mint_address == native_mint) {
+        return Ok(utils::to_human(lamports, spl_token::native_mint::DECIMALS) * sol.price);
     }
+    
+    let borrow_mint = solana_sdk::pubkey::Pubkey::from_str(&borrow.mint_address)?;
+    let quote = jupiter.get_quote(&spl_token::native_mint::ID, &borrow_mint, lamports, 0).await?;
+    Ok(repay_value_usd(borrow, quote.out_amount.parse()?))
+}
 
+/// Liquidate `unhealthy_obligations` in order, repeating on each until it is healthy
+/// or can't be liquidated further; `--liquidate` stops after the first attempt
+#[allow(clippy::too_many_arguments)]
+async fn liquidate_obligations(
+    rpc_client: &SolendRpcClient,
+    blockhashes: &Arc<BlockhashManager>,
+    config: &Config,
+    payer: &solana_sdk::signature::Keypair,
+    args: &Args,
+    market: &models::MarketConfig,
+    reserves_map: &HashMap<solana_sdk::pubkey::Pubkey, models::Reserve>,
+    oracle_data: &HashMap<String, oracle::TokenOracleData>,
+    unhealthy_obligations: Vec<(&solana_sdk::pubkey::Pubkey, models::Obligation, RefreshedObligation)>,
+    mint_decimals: &wallet::MintDecimalsCache,
+    notifiers: &notify::Notifiers,
+    compute_units: Option<&ComputeUnitEstimator>,
+    metrics: &mut metrics::PerformanceMetrics,
+) -> Result<()> {
+    // Batch fetch wallet balances
+    let mut needed_mints = std::collections::HashSet::new();
+
+    // Every borrow is a candidate, since the best one may be a token we don't hold
     for (_, _, refreshed) in &unhealthy_obligations {
-        if let Some(borrow) = refreshed.select_repay_borrow() {
+        for borrow in &refreshed.borrows {
             if let Ok(mint) = solana_sdk::pubkey::Pubkey::from_str(&borrow.mint_address) {
                 needed_mints.insert(mint);
             }
@@ -285,82 +981,345 @@ async fn process_market(
     }
 
     let needed_mints_vec: Vec<_> = needed_mints.into_iter().collect();
-    let wallet_balances = if !needed_mints_vec.is_empty() {
-        wallet::get_wallet_token_balances_batched(
-            &rpc_client,
-            &payer.pubkey(),
-            &needed_mints_vec,
-            &decimals_map,
-        ).await.unwrap_or_default()
-    } else {
-        HashMap::new()
+    let mut wallet_balances = wallet::WalletBalances::new(payer.pubkey());
+    wallet_balances.prefetch(rpc_client, &needed_mints_vec, mint_decimals).await;
+    
+    let jupiter = wallet::JupiterClient::with_config(&config.jupiter_api_url, config.jupiter_api_key.clone())
+        .with_http_config(&config.http)
+        .with_max_swap_attempts(config.swap_max_attempts)
+        .with_blockhashes(blockhashes.clone());
+    let jito = config.use_jito
+        .then(|| JitoClient::new(&config.jito_block_engine_url, config.jito_tip_lamports));
+    
+    let confirm = ConfirmOptions {
+        timeout: std::time::Duration::from_secs(config.confirm_timeout_secs),
+        resend_interval: std::time::Duration::from_millis(config.resend_interval_ms),
     };
     
+    let lookup_table = config.lookup_table
+        .as_deref()
+        .map(solana_sdk::pubkey::Pubkey::from_str)
+        .transpose()
+        .map_err(|e| anyhow!("Invalid LOOKUP_TABLE: {}", e))?;
+    
     // Process liquidations
+    let mut sent_liquidation = false;
     for (obligation_pubkey, mut obligation, mut refreshed) in unhealthy_obligations {
+        // A single manipulated feed must not be able to trigger a liquidation
+        if let Some(symbol) = refreshed.untrusted_symbol(oracle_data) {
+            warn!("[{}] Skipping obl {}: {} oracles disagree", market.name, obligation_pubkey, symbol);
+            metrics.liquidations_skipped += 1;
+            continue;
+        }
+        
+        let mut blockhash_retries = 0;
         loop {
-            if !refreshed.is_unhealthy() {
+            if !refreshed.is_liquidatable(config.liquidation_margin) {
                 break;
             }
             
-            metrics.liquidations_attempted += 1;
+            // Balances are only valid until a liquidation spends them;
+            // touched mints are invalidated below and re-read here
+            let mut repay_balances = HashMap::new();
+            for borrow in &refreshed.borrows {
+                let mint = solana_sdk::pubkey::Pubkey::from_str(&borrow.mint_address)?;
+                let decimals = mint_decimals.get(rpc_client, &mint).await?;
+                let (_, mut balance) = wallet_balances.get(rpc_client.client(), &mint, decimals)?;
+                // SOL debt can also be repaid by wrapping native SOL
+                if mint == spl_token::native_mint::ID && !args.dry_run {
+                    let lamports = rpc_client.client().get_balance(&payer.pubkey()).unwrap_or(0);
+                    let keep = solana_sdk::native_token::sol_to_lamports(config.min_sol_balance);
+                    balance += Decimal::from(lamports.saturating_sub(keep))
+                        / Decimal::from(solana_sdk::native_token::LAMPORTS_PER_SOL);
+                }
+                repay_balances.insert(borrow.mint_address.clone(), balance);
+            }
             
-            let selected_borrow = match refreshed.select_repay_borrow() {
-                Some(b) => b,
-                None => break,
+            let selected = match refreshed.select_repay_borrow_fundable(&repay_balances) {
+                Some(borrow) => refreshed.select_best_liquidation_pair_for(borrow, reserves_map),
+                // Nothing fundable: keep the usual pick so the skip below names a token we're short of
+                None => refreshed.select_best_liquidation_pair(reserves_map),
             };
-            
-            let selected_deposit = match refreshed.select_withdraw_deposit() {
-                Some(d) => d,
+            let (selected_borrow, selected_deposit) = match selected {
+                Some(pair) => pair,
                 None => break,
             };
             
-            info!("[{}] Liquidating obl {} (borrow: {}, deposit: {})", 
-                market.name, obligation_pubkey, selected_borrow.symbol, selected_deposit.symbol);
+            if !config.is_pair_allowed(&selected_borrow.symbol, &selected_deposit.symbol) {
+                info!("[{}] Skipping obl {}: {}/{} not in the token allowlists",
+                    market.name, obligation_pubkey, selected_borrow.symbol, selected_deposit.symbol);
+                metrics.liquidations_skipped += 1;
+                break;
+            }
             
             let mint_pubkey = solana_sdk::pubkey::Pubkey::from_str(&selected_borrow.mint_address)?;
+            let repay_decimals = mint_decimals.get(rpc_client, &mint_pubkey).await?;
             
-            let (balance_base, _) = if let Some((base, human)) = wallet_balances.get(&mint_pubkey) {
-                (*base, *human)
-            } else {
-                // Fallback
-                 let decimals = market
-                    .find_reserve(&selected_borrow.symbol)
-                    .map(|r| r.decimals())
-                    .unwrap_or(9);
-
-                get_wallet_token_balance(
-                    rpc_client.client(),
-                    &mint_pubkey,
-                    &payer.pubkey(),
-                    decimals,
-                )?
-            };
+            let (mut balance_base, _) = wallet_balances.get(rpc_client.client(), &mint_pubkey, repay_decimals)?;
+            
+            // SOL debt is repaid from the wSOL ATA; top it up from native SOL
+            let mut wrapped_sol = false;
+            if mint_pubkey == spl_token::native_mint::ID && !args.dry_run {
+                let needed = selected_borrow.capped_repay_amount(u64::MAX, config.close_factor);
+                if balance_base < needed && args.shadow {
+                    // Count the SOL a live run would wrap, without wrapping it
+                    let lamports = rpc_client.client().get_balance(&payer.pubkey()).unwrap_or(0);
+                    let keep = solana_sdk::native_token::sol_to_lamports(config.min_sol_balance);
+                    balance_base += lamports.saturating_sub(keep).min(needed - balance_base);
+                } else if balance_base < needed {
+                    match wallet::wrap_sol_for_repay(
+                        rpc_client.client(),
+                        payer,
+                        needed - balance_base,
+                        solana_sdk::native_token::sol_to_lamports(config.min_sol_balance),
+                    ) {
+                        Ok(0) => {}
+                        Ok(lamports) => {
+                            balance_base += lamports;
+                            wrapped_sol = true;
+                            wallet_balances.invalidate(&mint_pubkey);
+                        }
+                        Err(e) => warn!("[{}] Failed to wrap SOL for repay: {}", market.name, e),
+                    }
+                }
+            }
             
             if balance_base == 0 {
-                info!("Insufficient {} balance", selected_borrow.symbol);
+                info!("[{}] Insufficient {} balance for obl {}", market.name, selected_borrow.symbol, obligation_pubkey);
+                if args.dry_run {
+                    metrics.dry_run.insufficient_balance.push(report::DryRunSkip {
+                        market: market.name.clone(),
+                        obligation: obligation_pubkey.to_string(),
+                        repay_symbol: selected_borrow.symbol.clone(),
+                        required: utils::to_human(
+                            selected_borrow.capped_repay_amount(u64::MAX, config.close_factor),
+                            repay_decimals,
+                        ),
+                        available: Decimal::ZERO,
+                    });
+                }
+                if !args.shadow {
+                    metrics.liquidations_skipped += 1;
+                    break;
+                }
+            }
+            
+            // Solend rejects repays above the close factor
+            // Shadow mode prices unfunded repays in full to report what was missed
+            let funded = balance_base > 0;
+            let repay_amount = selected_borrow
+                .capped_repay_amount(if funded { balance_base } else { u64::MAX }, config.close_factor);
+            if repay_amount == 0 {
+                break;
+            }
+            
+            // Skip liquidations whose bonus doesn't cover swapping the collateral back
+            let (liquidation_bonus, protocol_liquidation_fee) =
+                solana_sdk::pubkey::Pubkey::from_str(&selected_deposit.deposit_reserve)
+                    .ok()
+                    .and_then(|pk| reserves_map.get(&pk))
+                    .map(|r| (r.config.liquidation_bonus, r.config.protocol_liquidation_fee))
+                    .unwrap_or((0, 0));
+            
+            // The redeem step fails unless the reserve can pay out the seized collateral,
+            // so a thinly funded reserve gets a smaller repay
+            let available = selected_deposit.available_liquidity(reserves_map).unwrap_or(0);
+            let repay_amount = repay_amount.min(max_repay_for_available(
+                selected_borrow,
+                selected_deposit,
+                available,
+                liquidation_bonus,
+            ));
+            let seized_amount = seized_collateral_amount(
+                selected_borrow,
+                selected_deposit,
+                repay_amount,
+                liquidation_bonus,
+            );
+            let deposit_mint = solana_sdk::pubkey::Pubkey::from_str(&selected_deposit.mint_address)?;
+            let deposit_decimals = mint_decimals.get(rpc_client, &deposit_mint).await?;
+            
+            if repay_amount == 0 || seized_amount > available {
+                info!(
+                    "[{}] Skipping obl {}: {} reserve has {} available, too little to redeem",
+                    market.name, obligation_pubkey, selected_deposit.symbol,
+                    utils::to_human(available, deposit_decimals).normalize()
+                );
+                metrics.liquidations_skipped += 1;
+                break;
+            }
+            
+            info!("[{}] Liquidating obl {} (repay: {}, seize: ~{}, health factor: {:.4})",
+                market.name, obligation_pubkey,
+                utils::format_amount(repay_amount, repay_decimals, &selected_borrow.symbol),
+                utils::format_amount(seized_amount, deposit_decimals, &selected_deposit.symbol),
+                refreshed.health_factor().round_dp(4));
+            
+            // Fees, tip and rent for destination accounts never held before come out of the profit
+            let proceeds_owner = config.proceeds_wallet.unwrap_or_else(|| payer.pubkey());
+            let costs = LiquidationCosts {
+                compute_unit_limit: config.compute_unit_limit,
+                priority_fee_micro_lamports: config.priority_fee_micro_lamports,
+                jito_tip_lamports: if config.use_jito { config.jito_tip_lamports } else { 0 },
+                atas_created: market
+                    .find_reserve(&selected_deposit.symbol)
+                    .and_then(|reserve| {
+                        missing_destination_accounts(rpc_client.client(), &payer.pubkey(), &proceeds_owner, reserve).ok()
+                    })
+                    .unwrap_or(2),
+            };
+            
+            let profit = match async {
+                let costs_usd = lamports_to_usd(&jupiter, oracle_data, selected_borrow, costs.lamports()).await?;
+                let quote = jupiter
+                    .get_quote(&deposit_mint, &mint_pubkey, seized_amount, config.slippage.bps_for(&selected_deposit.symbol))
+                    .await?;
+                estimate_liquidation_profit(
+                    selected_borrow,
+                    selected_deposit,
+                    repay_amount,
+                    liquidation_bonus,
+                    protocol_liquidation_fee,
+                    &quote,
+                    costs_usd,
+                )
+            }
+            .await
+            {
+                Ok(p) => p,
+                Err(e) => {
+                    warn!("[{}] Could not estimate profit for {}: {}", market.name, obligation_pubkey, e);
+                    metrics.liquidations_skipped += 1;
+                    break;
+                }
+            };
+            
+            if profit.to_f64().unwrap_or(0.0) < config.min_profit_usd {
+                info!(
+                    "[{}] Skipping obl {}: estimated profit ${:.2} below minimum ${:.2}",
+                    market.name, obligation_pubkey, profit, config.min_profit_usd
+                );
+                metrics.liquidations_skipped += 1;
                 break;
             }
             
-            match liquidate_and_redeem(
+            if args.shadow {
+                info!(
+                    "[{}] Shadow: would liquidate obl {} for est. ${:.2}{}",
+                    market.name,
+                    obligation_pubkey,
+                    profit.round_dp(2),
+                    if funded { "" } else { " (missed: no balance)" }
+                );
+                // Counted by the shadow totals rather than as an attempt
+                metrics.shadow.record(profit, funded);
+                break;
+            }
+            
+            // Snapshot collateral so only the seized amount is swapped back
+            let collateral_before = if config.swaps_seized_collateral() && !args.dry_run {
+                get_wallet_token_balance(rpc_client.client(), &deposit_mint, &payer.pubkey(), 0)?.0
+            } else {
+                0
+            };
+            
+            // Pace sends so a burst of liquidations stays under RPC send limits
+            if sent_liquidation && config.inter_liquidation_throttle_ms > 0 && !args.dry_run {
+                utils::wait(config.inter_liquidation_throttle_ms).await;
+            }
+            sent_liquidation = true;
+            
+            metrics.liquidations_attempted += 1;
+            let result = liquidate_and_redeem(
                 rpc_client.client(),
+                blockhashes,
                 &config.app,
-                &payer, // usage of &Arc<Keypair> works as &Keypair
-                balance_base,
+                payer,
+                repay_amount,
                 &selected_borrow.symbol,
                 &selected_deposit.symbol,
-                &market,
+                market,
+                obligation_pubkey,
                 &obligation,
                 args.dry_run,
-            ).await {
-                Ok(_) => {
+                config.compute_unit_limit,
+                config.priority_fee_micro_lamports,
+                config.simulate_before_send,
+                lookup_table.as_ref(),
+                jito.as_ref(),
+                confirm,
+                config.skip_fresh_reserve_refresh,
+                config.proceeds_wallet.as_ref(),
+                compute_units,
+            ).await;
+            
+            // Unwrap leftovers and recover the rent whether or not the liquidation landed
+            if wrapped_sol {
+                if let Err(e) = wallet::close_wsol_account(rpc_client.client(), payer) {
+                    warn!("[{}] Failed to close wSOL account: {}", market.name, e);
+                }
+            }
+            
+            match result {
+                Ok(signature) => {
+                    // Nothing changed on-chain, re-checking would loop forever
+                    let Some(signature) = signature else {
+                        metrics.dry_run.liquidations.push(report::DryRunLiquidation {
+                            market: market.name.clone(),
+                            obligation: obligation_pubkey.to_string(),
+                            repay_symbol: selected_borrow.symbol.clone(),
+                            repay_amount: utils::to_human(repay_amount, repay_decimals),
+                            withdraw_symbol: selected_deposit.symbol.clone(),
+                            profit_usd: profit,
+                        });
+                        break;
+                    };
+                    
                     info!("Liquidation sent!");
+                    metrics.liquidations_succeeded += 1;
+                    notifiers.send(notify::Event::LiquidationSucceeded {
+                        market: market.name.clone(),
+                        obligation: obligation_pubkey.to_string(),
+                        repay_symbol: selected_borrow.symbol.clone(),
+                        withdraw_symbol: selected_deposit.symbol.clone(),
+                        signature: signature.to_string(),
+                        profit_usd: profit,
+                    }).await;
+                    // Repaid one mint and received the other
+                    wallet_balances.invalidate(&mint_pubkey);
+                    wallet_balances.invalidate(&deposit_mint);
+                    // Both reserves' liquidity moved
+                    for reserve in [&selected_borrow.borrow_reserve, &selected_deposit.deposit_reserve] {
+                        if let Ok(reserve) = solana_sdk::pubkey::Pubkey::from_str(reserve) {
+                            rpc_client.invalidate_reserve(&reserve);
+                        }
+                    }
+                    
+                    if config.swaps_seized_collateral() {
+                        if let Err(e) = swap_collateral_to_repay(
+                            rpc_client.client(),
+                            payer,
+                            &jupiter,
+                            &deposit_mint,
+                            &mint_pubkey,
+                            collateral_before,
+                            config.slippage.bps_for(&selected_deposit.symbol),
+                            config.swap_priority_fee_lamports,
+                            config.max_price_impact_pct,
+                        ).await {
+                            warn!("[{}] Failed to swap {} back to {}: {}",
+                                market.name, selected_deposit.symbol, selected_borrow.symbol, e);
+                        }
+                    }
+                    if args.liquidate.is_some() {
+                        break;
+                    }
                     // Refresh obligation logic (simplified for parallel version - might need fetch)
                      match rpc_client.client().get_account(obligation_pubkey) {
                         Ok(account) => {
                              if let Ok(updated) = models::Obligation::parse(&account.data) {
                                 obligation = updated;
-                                if let Ok(r) = calculate_refreshed_obligation(&obligation, &reserves_map, &oracle_data) {
+                                if let Ok(r) = calculate_refreshed_obligation(&obligation, reserves_map, oracle_data, config.use_ema_for_health) {
                                     refreshed = r;
                                 } else { break; }
                              } else { break; }
@@ -368,15 +1327,57 @@ async fn process_market(
                         Err(_) => break,
                     }
                 }
+                // Someone else got there first, nothing to report
+                Err(LiquidationError::StaleObligation(e)) => {
+                    info!("[{}] Obligation {} no longer liquidatable: {}", market.name, obligation_pubkey, e);
+                    break;
+                }
+                // The cached balance is out of date, re-read it next time
+                Err(LiquidationError::InsufficientBalance { symbol, required, available }) => {
+                    warn!("[{}] Insufficient {} balance: need {}, have {}", market.name, symbol, required, available);
+                    if args.dry_run {
+                        metrics.dry_run.insufficient_balance.push(report::DryRunSkip {
+                            market: market.name.clone(),
+                            obligation: obligation_pubkey.to_string(),
+                            repay_symbol: symbol,
+                            required: utils::to_human(required, repay_decimals),
+                            available: utils::to_human(available, repay_decimals),
+                        });
+                    }
+                    wallet_balances.invalidate(&mint_pubkey);
+                    break;
+                }
+                // Nothing landed, so the same liquidation can be rebuilt
+                Err(LiquidationError::BlockhashExpired(e)) if blockhash_retries < MAX_BLOCKHASH_RETRIES => {
+                    warn!("[{}] Retrying obl {} with a fresh blockhash: {}", market.name, obligation_pubkey, e);
+                    blockhash_retries += 1;
+                }
+                Err(e @ LiquidationError::ReserveNotFound(_)) => {
+                    warn!("[{}] Skipping obl {}: {}", market.name, obligation_pubkey, e);
+                    break;
+                }
                 Err(e) => {
                     error!("Liquidation failed: {}", e);
+                    // A failed confirmation may still have landed, so drop the cached balance
+                    if let LiquidationError::TransactionFailed { signature: Some(signature), .. }
+                        | LiquidationError::ConfirmationTimeout { signature, .. } = &e
+                    {
+                        warn!("[{}] Unconfirmed liquidation signature: {}", market.name, signature);
+                        wallet_balances.invalidate(&mint_pubkey);
+                    }
+                    metrics.liquidations_failed += 1;
+                    notifiers.send(notify::Event::LiquidationFailed {
+                        market: market.name.clone(),
+                        obligation: obligation_pubkey.to_string(),
+                        repay_symbol: selected_borrow.symbol.clone(),
+                        withdraw_symbol: selected_deposit.symbol.clone(),
+                        error: e.to_string(),
+                    }).await;
                     break;
                 }
             }
         }
     }
     
-    metrics.processing_ms = processing_start.elapsed().as_millis() as u64;
-    Ok(metrics)
-
+    Ok(())
 }
@@ -0,0 +1,88 @@
+use futures::FutureExt;
+use std::any::Any;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+
+/// Why a market produced no results for an epoch
+#[derive(Debug, thiserror::Error)]
+pub enum MarketError {
+    #[error("market processing failed: {0}")]
+    Failed(anyhow::Error),
+    /// Caught panic, with its message
+    #[error("market processing panicked: {0}")]
+    Panicked(String),
+}
+
+/// Run one market's work, turning a panic into `MarketError::Panicked`
+/// so the epoch can still aggregate the other markets
+pub async fn run_guarded<T>(work: impl Future<Output = anyhow::Result<T>>) -> Result<T, MarketError> {
+    match AssertUnwindSafe(work).catch_unwind().await {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(e)) => Err(MarketError::Failed(e)),
+        Err(payload) => Err(MarketError::Panicked(panic_message(payload.as_ref()))),
+    }
+}
+
+/// Log panics with their location and backtrace instead of printing them to stderr
+/// The message itself is surfaced again by `run_guarded`
+pub fn install_panic_logger() {
+    std::panic::set_hook(Box::new(|info| {
+        log::error!("{}\n{}", info, std::backtrace::Backtrace::force_capture());
+    }));
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    /// Stands in for `process_market`, failing the way `mode` asks
+    async fn fake_market(mode: &str) -> anyhow::Result<usize> {
+        match mode {
+            "ok" => Ok(7),
+            "error" => Err(anyhow!("rpc down")),
+            _ => panic!("bad oracle data for {}", mode),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_guarded_converts_panics() {
+        assert_eq!(run_guarded(fake_market("ok")).await.unwrap(), 7);
+
+        let err = run_guarded(fake_market("error")).await.unwrap_err();
+        assert!(matches!(err, MarketError::Failed(_)));
+
+        let err = run_guarded(fake_market("SOL")).await.unwrap_err();
+        assert!(matches!(&err, MarketError::Panicked(message) if message == "bad oracle data for SOL"));
+
+        // Static-string payloads too
+        async fn panics() -> anyhow::Result<()> {
+            panic!("boom")
+        }
+        let err = run_guarded(panics()).await.unwrap_err();
+        assert_eq!(err.to_string(), "market processing panicked: boom");
+    }
+
+    #[tokio::test]
+    async fn test_panic_does_not_lose_other_results() {
+        let tasks: Vec<_> = ["ok", "USDC", "ok"]
+            .into_iter()
+            .map(|mode| tokio::spawn(run_guarded(fake_market(mode))))
+            .collect();
+
+        let results = futures::future::join_all(tasks).await;
+        let succeeded = results.iter().filter(|r| matches!(r, Ok(Ok(7)))).count();
+        assert_eq!(succeeded, 2);
+        assert!(matches!(results[1], Ok(Err(MarketError::Panicked(_)))));
+    }
+}
@@ -1,5 +1,17 @@
+use anyhow::Result;
 use log::info;
-use std::time::Instant;
+use prometheus::{Encoder, IntCounter, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use solana_sdk::native_token::lamports_to_sol;
+
+use crate::report::{DryRunReport, ShadowTotals};
 
 /// Performance metrics for a single epoch
 #[derive(Debug, Clone)]
@@ -11,7 +23,53 @@ pub struct PerformanceMetrics {
     pub processing_ms: u64,
     pub total_obligations: usize,
     pub unhealthy_obligations: usize,
+    /// Liquidation transactions built and sent (or simulated in dry-run mode)
+    pub liquidations_attempted: usize,
+    pub liquidations_succeeded: usize,
+    pub liquidations_failed: usize,
+    /// Liquidatable obligations passed over (filters, balance, liquidity, profit)
+    pub liquidations_skipped: usize,
+    /// Would-be liquidations, only collected in dry-run mode
+    pub dry_run: DryRunReport,
+    /// Hypothetical liquidations, only collected in shadow mode
+    pub shadow: ShadowTotals,
+    /// Native SOL (lamports) held by each wallet at the end of the epoch
+    pub sol_balances: Vec<(String, u64)>,
+    /// Per-market breakdown by market name, filled by `merge_market`
+    pub markets: BTreeMap<String, MarketMetrics>,
+}
+
+/// One market's share of an epoch
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MarketMetrics {
+    /// Oracle, obligation and reserve fetches combined
+    pub fetch_ms: u64,
+    pub processing_ms: u64,
+    pub total_obligations: usize,
+    pub unhealthy_obligations: usize,
+    pub liquidations_attempted: usize,
+    pub liquidations_succeeded: usize,
+    pub liquidations_failed: usize,
+    pub liquidations_skipped: usize,
+}
+
+/// Serializable view of one epoch's metrics
+#[derive(Debug, Serialize)]
+pub struct MetricsSnapshot {
+    pub timestamp: u64,
+    pub epoch: u64,
+    pub epoch_ms: u64,
+    pub oracle_fetch_ms: u64,
+    pub obligations_fetch_ms: u64,
+    pub reserves_fetch_ms: u64,
+    pub processing_ms: u64,
+    pub total_obligations: usize,
+    pub unhealthy_obligations: usize,
     pub liquidations_attempted: usize,
+    pub liquidations_succeeded: usize,
+    pub liquidations_failed: usize,
+    pub liquidations_skipped: usize,
+    pub markets: BTreeMap<String, MarketMetrics>,
 }
 
 impl Default for PerformanceMetrics {
@@ -25,6 +83,13 @@ impl Default for PerformanceMetrics {
             total_obligations: 0,
             unhealthy_obligations: 0,
             liquidations_attempted: 0,
+            liquidations_succeeded: 0,
+            liquidations_failed: 0,
+            liquidations_skipped: 0,
+            dry_run: DryRunReport::default(),
+            shadow: ShadowTotals::default(),
+            sol_balances: Vec::new(),
+            markets: BTreeMap::new(),
         }
     }
 }
@@ -41,6 +106,33 @@ impl PerformanceMetrics {
         }
     }
 
+    /// Add one market's epoch to the totals, keeping its own figures under `market`
+    pub fn merge_market(&mut self, market: &str, other: PerformanceMetrics) {
+        self.oracle_fetch_ms += other.oracle_fetch_ms;
+        self.obligations_fetch_ms += other.obligations_fetch_ms;
+        self.reserves_fetch_ms += other.reserves_fetch_ms;
+        self.processing_ms += other.processing_ms;
+        self.total_obligations += other.total_obligations;
+        self.unhealthy_obligations += other.unhealthy_obligations;
+        self.liquidations_attempted += other.liquidations_attempted;
+        self.liquidations_succeeded += other.liquidations_succeeded;
+        self.liquidations_failed += other.liquidations_failed;
+        self.liquidations_skipped += other.liquidations_skipped;
+        self.dry_run.merge(other.dry_run);
+        self.shadow.merge(&other.shadow);
+        
+        self.markets.insert(market.to_string(), MarketMetrics {
+            fetch_ms: other.oracle_fetch_ms + other.obligations_fetch_ms + other.reserves_fetch_ms,
+            processing_ms: other.processing_ms,
+            total_obligations: other.total_obligations,
+            unhealthy_obligations: other.unhealthy_obligations,
+            liquidations_attempted: other.liquidations_attempted,
+            liquidations_succeeded: other.liquidations_succeeded,
+            liquidations_failed: other.liquidations_failed,
+            liquidations_skipped: other.liquidations_skipped,
+        });
+    }
+    
     pub fn log_summary(&self) {
         let total_ms = self.epoch_start.elapsed().as_millis();
         info!("Epoch Performance Summary:");
@@ -53,5 +145,418 @@ impl PerformanceMetrics {
             "  Stats: {} total obs, {} unhealthy, {} liquidations",
             self.total_obligations, self.unhealthy_obligations, self.liquidations_attempted
         );
+        info!(
+            "  Liquidations: {} succeeded, {} failed, {} skipped",
+            self.liquidations_succeeded, self.liquidations_failed, self.liquidations_skipped
+        );
+        if let Some(rate) = self.success_rate() {
+            info!("  Success Rate:      {:.1}%", rate);
+        }
+        for (wallet, lamports) in &self.sol_balances {
+            info!("  SOL Balance:       {:.4} SOL ({})", lamports_to_sol(*lamports), wallet);
+        }
+        if !self.markets.is_empty() {
+            info!("  Per market:");
+        }
+        for (name, market) in &self.markets {
+            info!(
+                "    {:<16} {} obs, {} unhealthy, {} liquidations ({} ok, {} failed), {} skipped, fetch {} ms, processing {} ms",
+                name,
+                market.total_obligations,
+                market.unhealthy_obligations,
+                market.liquidations_attempted,
+                market.liquidations_succeeded,
+                market.liquidations_failed,
+                market.liquidations_skipped,
+                market.fetch_ms,
+                market.processing_ms
+            );
+        }
+    }
+    
+    pub fn snapshot(&self, epoch: u64) -> MetricsSnapshot {
+        MetricsSnapshot {
+            timestamp: unix_timestamp(),
+            epoch,
+            epoch_ms: self.epoch_start.elapsed().as_millis() as u64,
+            oracle_fetch_ms: self.oracle_fetch_ms,
+            obligations_fetch_ms: self.obligations_fetch_ms,
+            reserves_fetch_ms: self.reserves_fetch_ms,
+            processing_ms: self.processing_ms,
+            total_obligations: self.total_obligations,
+            unhealthy_obligations: self.unhealthy_obligations,
+            liquidations_attempted: self.liquidations_attempted,
+            liquidations_succeeded: self.liquidations_succeeded,
+            liquidations_failed: self.liquidations_failed,
+            liquidations_skipped: self.liquidations_skipped,
+            markets: self.markets.clone(),
+        }
+    }
+    
+    /// Append this epoch as one JSON object per line
+    pub fn append_to_jsonl(&self, path: &str, epoch: u64) -> Result<()> {
+        let mut line = serde_json::to_string(&self.snapshot(epoch))?;
+        line.push('\n');
+        
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+    
+    /// Percentage of sent liquidations that landed, if any were sent
+    pub fn success_rate(&self) -> Option<f64> {
+        let sent = self.liquidations_succeeded + self.liquidations_failed;
+        if sent == 0 {
+            return None;
+        }
+        Some(self.liquidations_succeeded as f64 / sent as f64 * 100.0)
+    }
+}
+
+/// Prometheus metrics accumulated across epochs
+pub struct PrometheusMetrics {
+    registry: Registry,
+    epochs: IntCounter,
+    obligations_scanned: IntCounter,
+    unhealthy_obligations: IntCounter,
+    liquidations_attempted: IntCounter,
+    last_total_obligations: IntGauge,
+    last_unhealthy_obligations: IntGauge,
+    stage_latency_ms: IntGaugeVec,
+}
+
+impl PrometheusMetrics {
+    pub fn new() -> Result<Self> {
+        let registry = Registry::new();
+        
+        let epochs = IntCounter::new("liquidator_epochs_total", "Completed epochs")?;
+        let obligations_scanned = IntCounter::new(
+            "liquidator_obligations_scanned_total",
+            "Obligations evaluated across all epochs",
+        )?;
+        let unhealthy_obligations = IntCounter::new(
+            "liquidator_unhealthy_obligations_total",
+            "Unhealthy obligations found across all epochs",
+        )?;
+        let liquidations_attempted = IntCounter::new(
+            "liquidator_liquidations_attempted_total",
+            "Liquidations attempted across all epochs",
+        )?;
+        let last_total_obligations = IntGauge::new(
+            "liquidator_obligations",
+            "Obligations evaluated in the last epoch",
+        )?;
+        let last_unhealthy_obligations = IntGauge::new(
+            "liquidator_unhealthy_obligations",
+            "Unhealthy obligations in the last epoch",
+        )?;
+        let stage_latency_ms = IntGaugeVec::new(
+            Opts::new("liquidator_stage_latency_ms", "Per-stage latency of the last epoch"),
+            &["stage"],
+        )?;
+        
+        registry.register(Box::new(epochs.clone()))?;
+        registry.register(Box::new(obligations_scanned.clone()))?;
+        registry.register(Box::new(unhealthy_obligations.clone()))?;
+        registry.register(Box::new(liquidations_attempted.clone()))?;
+        registry.register(Box::new(last_total_obligations.clone()))?;
+        registry.register(Box::new(last_unhealthy_obligations.clone()))?;
+        registry.register(Box::new(stage_latency_ms.clone()))?;
+        
+        Ok(Self {
+            registry,
+            epochs,
+            obligations_scanned,
+            unhealthy_obligations,
+            liquidations_attempted,
+            last_total_obligations,
+            last_unhealthy_obligations,
+            stage_latency_ms,
+        })
+    }
+    
+    /// Fold an epoch's aggregated metrics into the exported series
+    pub fn record_epoch(&self, metrics: &PerformanceMetrics) {
+        self.epochs.inc();
+        self.obligations_scanned.inc_by(metrics.total_obligations as u64);
+        self.unhealthy_obligations.inc_by(metrics.unhealthy_obligations as u64);
+        self.liquidations_attempted.inc_by(metrics.liquidations_attempted as u64);
+        self.last_total_obligations.set(metrics.total_obligations as i64);
+        self.last_unhealthy_obligations.set(metrics.unhealthy_obligations as i64);
+        
+        for (stage, ms) in [
+            ("oracle_fetch", metrics.oracle_fetch_ms),
+            ("obligations_fetch", metrics.obligations_fetch_ms),
+            ("reserves_fetch", metrics.reserves_fetch_ms),
+            ("processing", metrics.processing_ms),
+            ("epoch", metrics.epoch_start.elapsed().as_millis() as u64),
+        ] {
+            self.stage_latency_ms.with_label_values(&[stage]).set(ms as i64);
+        }
+    }
+    
+    /// Render all metrics in the Prometheus text format
+    pub fn render(&self) -> Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+/// Liveness state shared between the main loop and the health endpoint
+pub struct HealthState {
+    started: Instant,
+    inner: Mutex<HealthInner>,
+}
+
+#[derive(Debug, Default)]
+struct HealthInner {
+    last_epoch: u64,
+    last_success: Option<(Instant, u64)>,
+    rpc_ok: bool,
+}
+
+/// Body of the health endpoint
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    pub healthy: bool,
+    pub last_epoch: u64,
+    /// Unix timestamp of the last epoch whose RPC fetches all succeeded
+    pub last_success_timestamp: Option<u64>,
+    pub rpc_ok: bool,
+}
+
+impl HealthState {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            inner: Mutex::new(HealthInner::default()),
+        }
+    }
+    
+    /// Record a completed epoch; `rpc_ok` is false if any market fetch failed
+    pub fn record_epoch(&self, epoch: u64, rpc_ok: bool) {
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        inner.last_epoch = epoch;
+        inner.rpc_ok = rpc_ok;
+        if rpc_ok {
+            inner.last_success = Some((Instant::now(), unix_timestamp()));
+        }
+    }
+    
+    /// Unhealthy once no successful epoch has completed within `stale_after`
+    pub fn report(&self, stale_after: Duration) -> HealthReport {
+        let inner = match self.inner.lock() {
+            Ok(inner) => inner,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let since_success = inner
+            .last_success
+            .map(|(at, _)| at.elapsed())
+            .unwrap_or_else(|| self.started.elapsed());
+        
+        HealthReport {
+            healthy: since_success <= stale_after,
+            last_epoch: inner.last_epoch,
+            last_success_timestamp: inner.last_success.map(|(_, ts)| ts),
+            rpc_ok: inner.rpc_ok,
+        }
+    }
+}
+
+impl Default for HealthState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Status line, content type and body of an HTTP response
+type HttpResponse = (&'static str, &'static str, String);
+
+/// Minimal HTTP/1.1 server answering each connection with `handler(request_line)`
+async fn serve_http<F>(port: u16, handler: F) -> Result<()>
+where
+    F: Fn(&str) -> HttpResponse + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind(("0.0.0.0", port)).await?;
+    let handler = Arc::new(handler);
+    
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let handler = handler.clone();
+        
+        tokio::spawn(async move {
+            let mut request = [0u8; 1024];
+            let n = stream.read(&mut request).await.unwrap_or(0);
+            let request_line = String::from_utf8_lossy(&request[..n]);
+            
+            let (status, content_type, body) = handler(&request_line);
+            
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                content_type,
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        });
+    }
+}
+
+/// Serve `GET /metrics` on the given port until the process exits
+pub async fn serve_metrics(port: u16, metrics: Arc<PrometheusMetrics>) -> Result<()> {
+    info!("Prometheus metrics listening on :{}/metrics", port);
+    
+    serve_http(port, move |request_line| {
+        if !request_line.starts_with("GET /metrics") {
+            return ("404 Not Found", "text/plain", String::new());
+        }
+        match metrics.render() {
+            Ok(body) => ("200 OK", "text/plain; version=0.0.4", body),
+            Err(e) => ("500 Internal Server Error", "text/plain", e.to_string()),
+        }
+    })
+    .await
+}
+
+/// Serve `GET /health` on the given port until the process exits
+/// Returns 503 once no successful epoch has completed within `stale_after`
+pub async fn serve_health(port: u16, state: Arc<HealthState>, stale_after: Duration) -> Result<()> {
+    info!("Health endpoint listening on :{}/health", port);
+    
+    serve_http(port, move |request_line| {
+        if !request_line.starts_with("GET /health") {
+            return ("404 Not Found", "text/plain", String::new());
+        }
+        let report = state.report(stale_after);
+        let status = if report.healthy { "200 OK" } else { "503 Service Unavailable" };
+        let body = serde_json::to_string(&report).unwrap_or_default();
+        (status, "application/json", body)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_prometheus_accumulates_across_epochs() {
+        let prometheus = PrometheusMetrics::new().unwrap();
+        let epoch = PerformanceMetrics {
+            total_obligations: 10,
+            unhealthy_obligations: 2,
+            liquidations_attempted: 1,
+            processing_ms: 42,
+            ..Default::default()
+        };
+        
+        prometheus.record_epoch(&epoch);
+        prometheus.record_epoch(&epoch);
+        
+        let rendered = prometheus.render().unwrap();
+        assert!(rendered.contains("liquidator_epochs_total 2"));
+        assert!(rendered.contains("liquidator_obligations_scanned_total 20"));
+        assert!(rendered.contains("liquidator_obligations 10"));
+        assert!(rendered.contains("liquidator_stage_latency_ms{stage=\"processing\"} 42"));
+    }
+    
+    #[test]
+    fn test_append_to_jsonl() {
+        let path = std::env::temp_dir().join(format!("metrics-{}.jsonl", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        
+        let metrics = PerformanceMetrics {
+            total_obligations: 7,
+            ..Default::default()
+        };
+        metrics.append_to_jsonl(path, 1).unwrap();
+        metrics.append_to_jsonl(path, 2).unwrap();
+        
+        let contents = std::fs::read_to_string(path).unwrap();
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        std::fs::remove_file(path).unwrap();
+        
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["epoch"], 2);
+        assert_eq!(lines[1]["total_obligations"], 7);
+        assert!(lines[0]["timestamp"].as_u64().unwrap() > 0);
+    }
+    
+    #[test]
+    fn test_health_report() {
+        let state = HealthState::new();
+        
+        // Nothing has completed yet, but the window hasn't elapsed either
+        let report = state.report(Duration::from_secs(60));
+        assert!(report.healthy);
+        assert_eq!(report.last_success_timestamp, None);
+        assert!(!state.report(Duration::ZERO).healthy);
+        
+        state.record_epoch(1, true);
+        let report = state.report(Duration::from_secs(60));
+        assert!(report.healthy);
+        assert_eq!(report.last_epoch, 1);
+        assert!(report.rpc_ok);
+        assert!(report.last_success_timestamp.is_some());
+        
+        // A failed epoch keeps the last success time
+        state.record_epoch(2, false);
+        let report = state.report(Duration::from_secs(60));
+        assert!(report.healthy);
+        assert_eq!(report.last_epoch, 2);
+        assert!(!report.rpc_ok);
+    }
+    
+    #[test]
+    fn test_success_rate() {
+        let mut metrics = PerformanceMetrics::new();
+        assert_eq!(metrics.success_rate(), None);
+        
+        metrics.liquidations_succeeded = 3;
+        metrics.liquidations_failed = 1;
+        assert_eq!(metrics.success_rate(), Some(75.0));
+    }
+    
+    #[test]
+    fn test_merge_market_keeps_breakdown() {
+        let market = |obligations, unhealthy, fetch_ms| PerformanceMetrics {
+            oracle_fetch_ms: fetch_ms,
+            obligations_fetch_ms: fetch_ms,
+            total_obligations: obligations,
+            unhealthy_obligations: unhealthy,
+            ..Default::default()
+        };
+        
+        let mut overall = PerformanceMetrics::start_epoch();
+        overall.merge_market("main", PerformanceMetrics { liquidations_skipped: 2, ..market(100, 3, 50) });
+        overall.merge_market("TURBO", market(20, 0, 400));
+        
+        assert_eq!(overall.total_obligations, 120);
+        assert_eq!(overall.unhealthy_obligations, 3);
+        assert_eq!(overall.markets["main"].unhealthy_obligations, 3);
+        assert_eq!(overall.markets["TURBO"].fetch_ms, 800);
+        assert_eq!((overall.liquidations_skipped, overall.markets["main"].liquidations_skipped), (2, 2));
+        
+        let snapshot = serde_json::to_value(overall.snapshot(1)).unwrap();
+        assert_eq!(snapshot["markets"]["TURBO"]["total_obligations"], 20);
+        assert_eq!(snapshot["liquidations_skipped"], 2);
     }
 }
@@ -11,4 +11,9 @@ impl LastUpdate {
     pub fn is_zero(&self) -> bool {
         self.slot == 0
     }
+    
+    /// Refreshed in `current_slot` (or later) and not marked stale since
+    pub fn is_fresh_at(&self, current_slot: u64) -> bool {
+        !self.stale && self.slot >= current_slot
+    }
 }
@@ -0,0 +1,20 @@
+//! Little-endian readers for packed Solend accounts
+//! Callers check the account size up front, so out-of-range offsets are bugs
+
+use solana_sdk::pubkey::Pubkey;
+
+pub(super) fn invalid_data(msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+pub(super) fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+pub(super) fn read_u128(data: &[u8], offset: usize) -> u128 {
+    u128::from_le_bytes(data[offset..offset + 16].try_into().unwrap())
+}
+
+pub(super) fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    Pubkey::new_from_array(data[offset..offset + 32].try_into().unwrap())
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// Market configuration from Solend API
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,6 +48,42 @@ pub struct LiquidityToken {
 pub struct TokenCount {
     pub symbol: String,
     pub target: f64,
+    /// Absolute lower bound, replacing the padding-based one
+    pub min: Option<f64>,
+    /// Absolute upper bound, replacing the padding-based one
+    pub max: Option<f64>,
+}
+
+impl TokenCount {
+    /// Balance range that needs no rebalancing: the absolute bounds where set,
+    /// else `padding` (a fraction of the target) either side of the target
+    pub fn bounds(&self, padding: f64) -> (f64, f64) {
+        (
+            self.min.unwrap_or(self.target * (1.0 - padding)),
+            self.max.unwrap_or(self.target * (1.0 + padding)),
+        )
+    }
+}
+
+/// Kamino kToken mint and the Kamino Lend reserve that redeems it
+#[derive(Debug, Clone)]
+pub struct KTokenReserve {
+    pub mint: String,
+    pub reserve: String,
+}
+
+/// Jupiter slippage tolerance per token symbol, with a fallback
+#[derive(Debug, Clone)]
+pub struct SlippageConfig {
+    pub default_bps: u16,
+    pub per_token: HashMap<String, u16>,
+}
+
+impl SlippageConfig {
+    /// Slippage in basis points for swaps involving `symbol`
+    pub fn bps_for(&self, symbol: &str) -> u16 {
+        self.per_token.get(symbol).copied().unwrap_or(self.default_bps)
+    }
 }
 
 impl MarketConfig {
@@ -2,8 +2,11 @@ pub mod market;
 pub mod obligation;
 pub mod reserve;
 pub mod last_update;
+mod layout;
 
 pub use market::{MarketConfig, MarketConfigReserve, LiquidityToken};
-pub use obligation::{Obligation, ObligationCollateral, ObligationLiquidity};
+pub use obligation::{
+    Obligation, ObligationCollateral, ObligationHealthView, ObligationLiquidity, ObligationParseError,
+};
 pub use reserve::{Reserve, ReserveLiquidity, ReserveCollateral, ReserveConfig};
 pub use last_update::LastUpdate;
@@ -1,6 +1,11 @@
 use borsh::{BorshDeserialize, BorshSerialize};
+use rust_decimal::Decimal;
 use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
 use super::last_update::LastUpdate;
+use super::layout::{invalid_data, read_pubkey, read_u128, read_u64};
+use super::reserve::Reserve;
+use crate::utils::WAD;
 
 /// Obligation account size
 pub const OBLIGATION_SIZE: usize = 1300;
@@ -37,60 +42,184 @@ pub struct ObligationLiquidity {
     pub market_value: u128,
 }
 
+/// Max deposits + borrows in one obligation
+pub const MAX_OBLIGATION_RESERVES: usize = 10;
+
+/// Packed sizes of data_flat entries (including 32 bytes of trailing padding each)
+pub const OBLIGATION_COLLATERAL_LEN: usize = 88;
+pub const OBLIGATION_LIQUIDITY_LEN: usize = 112;
+
+/// Solend obligation layout offsets
+/// version (1) | last_update (8 + 1) | lending_market (32) | owner (32)
+/// | deposited/borrowed/allowed/unhealthy values (4 x 16) | padding (64)
+/// | deposits_len (1) | borrows_len (1) | data_flat (1096)
+const LAST_UPDATE_OFFSET: usize = 1;
+/// Also the memcmp offset for filtering obligations by market
+pub const LENDING_MARKET_OFFSET: usize = 10;
+const OWNER_OFFSET: usize = 42;
+const DEPOSITED_VALUE_OFFSET: usize = 74;
+const BORROWED_VALUE_OFFSET: usize = 90;
+const ALLOWED_BORROW_VALUE_OFFSET: usize = 106;
+const UNHEALTHY_BORROW_VALUE_OFFSET: usize = 122;
+const HEALTH_VALUES_END: usize = 138;
+const DEPOSITS_LEN_OFFSET: usize = 202;
+const BORROWS_LEN_OFFSET: usize = 203;
+const DATA_FLAT_OFFSET: usize = 204;
+
+/// Byte range holding `last_update` through `unhealthy_borrow_value`, for `dataSlice` fetches
+pub const HEALTH_VIEW_OFFSET: usize = LAST_UPDATE_OFFSET;
+pub const HEALTH_VIEW_LEN: usize = HEALTH_VALUES_END - LAST_UPDATE_OFFSET;
+
+/// Health fields of an obligation, decoded from a `HEALTH_VIEW_*` data slice
+/// Values are as of the last on-chain refresh, so this is only a first screen
+#[derive(Debug, Clone, Copy)]
+pub struct ObligationHealthView {
+    pub last_update: LastUpdate,
+    pub deposited_value: u128,
+    pub borrowed_value: u128,
+    pub allowed_borrow_value: u128,
+    pub unhealthy_borrow_value: u128,
+}
+
+/// WAD-scaled value as a `Decimal`
+fn wad_to_decimal(value: u128) -> Decimal {
+    // Exact while the raw value fits Decimal's 96-bit mantissa
+    Decimal::try_from_i128_with_scale(value as i128, 18).unwrap_or_else(|_| Decimal::from(value / WAD))
+}
+
+impl ObligationHealthView {
+    /// Parse from the sliced bytes, which start at `HEALTH_VIEW_OFFSET`
+    pub fn parse(data: &[u8]) -> Result<Self, std::io::Error> {
+        if data.len() < HEALTH_VIEW_LEN {
+            return Err(invalid_data(format!("Obligation health slice too short: {} bytes", data.len())));
+        }
+        
+        // Offsets are relative to the start of the slice
+        let at = |offset: usize| offset - HEALTH_VIEW_OFFSET;
+        Ok(Self {
+            last_update: LastUpdate {
+                slot: read_u64(data, at(LAST_UPDATE_OFFSET)),
+                stale: data[at(LAST_UPDATE_OFFSET) + 8] != 0,
+            },
+            deposited_value: read_u128(data, at(DEPOSITED_VALUE_OFFSET)),
+            borrowed_value: read_u128(data, at(BORROWED_VALUE_OFFSET)),
+            allowed_borrow_value: read_u128(data, at(ALLOWED_BORROW_VALUE_OFFSET)),
+            unhealthy_borrow_value: read_u128(data, at(UNHEALTHY_BORROW_VALUE_OFFSET)),
+        })
+    }
+    
+    /// Check if the obligation was healthy at its last refresh
+    pub fn is_healthy(&self) -> bool {
+        self.borrowed_value <= self.unhealthy_borrow_value
+    }
+}
+
+/// Why obligation account data couldn't be decoded, naming the field and its byte offset
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ObligationParseError {
+    #[error("obligation data is {len} bytes, expected at least {expected}")]
+    DataSize { len: usize, expected: usize },
+    /// The deposit and borrow counts (at `DEPOSITS_LEN_OFFSET`) add up to more than the program allows
+    #[error("deposits_len/borrows_len at offset {offset}: {deposits} deposits + {borrows} borrows exceeds {max}")]
+    TooManyReserves { offset: usize, deposits: usize, borrows: usize, max: usize },
+    /// A deposit or borrow entry in data_flat runs past the end of the account
+    #[error("{field} at offset {offset} ends past the {size}-byte account")]
+    EntryOutOfBounds { field: String, offset: usize, size: usize },
+}
+
+impl From<ObligationParseError> for std::io::Error {
+    fn from(e: ObligationParseError) -> Self {
+        invalid_data(e.to_string())
+    }
+}
+
+/// Check the declared deposits and borrows fit in data_flat before any of them is read
+/// data_flat is sized for one deposit and nine borrows, so the reserve limit alone
+/// doesn't guarantee every mix of ten positions fits
+fn check_data_flat(deposits_len: usize, borrows_len: usize) -> Result<(), ObligationParseError> {
+    let deposits = (0..deposits_len).map(|i| ("deposits", i, OBLIGATION_COLLATERAL_LEN));
+    let borrows = (0..borrows_len).map(|i| ("borrows", i, OBLIGATION_LIQUIDITY_LEN));
+    
+    let mut offset = DATA_FLAT_OFFSET;
+    for (kind, i, len) in deposits.chain(borrows) {
+        if offset + len > OBLIGATION_SIZE {
+            return Err(ObligationParseError::EntryOutOfBounds {
+                field: format!("{}[{}]", kind, i),
+                offset,
+                size: OBLIGATION_SIZE,
+            });
+        }
+        offset += len;
+    }
+    Ok(())
+}
+
 impl Obligation {
     /// Parse obligation from account data
-    /// Note: Solend uses custom layout, may need manual parsing
+    /// Follows the packed Solend layout; reads are bounds-checked by the size check
     pub fn parse(data: &[u8]) -> Result<Self, std::io::Error> {
+        Ok(Self::parse_detailed(data)?)
+    }
+    
+    /// `parse`, reporting which field made the data undecodable
+    pub fn parse_detailed(data: &[u8]) -> Result<Self, ObligationParseError> {
         if data.len() < OBLIGATION_SIZE {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Invalid obligation data size",
-            ));
+            return Err(ObligationParseError::DataSize { len: data.len(), expected: OBLIGATION_SIZE });
         }
         
-        // Custom parsing logic for Solend obligation layout
-        // This is a simplified version - actual implementation needs to match exact layout
-        let mut reader = &data[..];
+        let version = data[0];
+        let last_update = LastUpdate {
+            slot: read_u64(data, LAST_UPDATE_OFFSET),
+            stale: data[LAST_UPDATE_OFFSET + 8] != 0,
+        };
         
-        let version = u8::deserialize(&mut reader)?;
-        let last_update = LastUpdate::deserialize(&mut reader)?;
+        let deposits_len = data[DEPOSITS_LEN_OFFSET] as usize;
+        let borrows_len = data[BORROWS_LEN_OFFSET] as usize;
         
-        // Skip to avoid complex layout parsing for now
-        // In production, use exact offset calculations matching the TypeScript version
-        let lending_market = Pubkey::deserialize(&mut reader)?;
-        let owner = Pubkey::deserialize(&mut reader)?;
-        let deposited_value = u128::deserialize(&mut reader)?;
-        let borrowed_value = u128::deserialize(&mut reader)?;
-        let allowed_borrow_value = u128::deserialize(&mut reader)?;
-        let unhealthy_borrow_value = u128::deserialize(&mut reader)?;
-        
-        // Skip padding (64 bytes)
-        reader = &reader[64..];
+        if deposits_len + borrows_len > MAX_OBLIGATION_RESERVES {
+            return Err(ObligationParseError::TooManyReserves {
+                offset: DEPOSITS_LEN_OFFSET,
+                deposits: deposits_len,
+                borrows: borrows_len,
+                max: MAX_OBLIGATION_RESERVES,
+            });
+        }
         
-        let deposits_len = u8::deserialize(&mut reader)?;
-        let borrows_len = u8::deserialize(&mut reader)?;
+        check_data_flat(deposits_len, borrows_len)?;
         
-        // Parse deposits and borrows from data_flat
-        let mut deposits = Vec::new();
-        let mut borrows = Vec::new();
+        // data_flat holds all deposits followed by all borrows
+        let mut offset = DATA_FLAT_OFFSET;
         
+        let mut deposits = Vec::with_capacity(deposits_len);
         for _ in 0..deposits_len {
-            deposits.push(ObligationCollateral::deserialize(&mut reader)?);
+            deposits.push(ObligationCollateral {
+                deposit_reserve: read_pubkey(data, offset),
+                deposited_amount: read_u64(data, offset + 32),
+                market_value: read_u128(data, offset + 40),
+            });
+            offset += OBLIGATION_COLLATERAL_LEN;
         }
         
+        let mut borrows = Vec::with_capacity(borrows_len);
         for _ in 0..borrows_len {
-            borrows.push(ObligationLiquidity::deserialize(&mut reader)?);
+            borrows.push(ObligationLiquidity {
+                borrow_reserve: read_pubkey(data, offset),
+                cumulative_borrow_rate_wads: read_u128(data, offset + 32),
+                borrowed_amount_wads: read_u128(data, offset + 48),
+                market_value: read_u128(data, offset + 64),
+            });
+            offset += OBLIGATION_LIQUIDITY_LEN;
         }
         
         Ok(Obligation {
             version,
             last_update,
-            lending_market,
-            owner,
-            deposited_value,
-            borrowed_value,
-            allowed_borrow_value,
-            unhealthy_borrow_value,
+            lending_market: read_pubkey(data, LENDING_MARKET_OFFSET),
+            owner: read_pubkey(data, OWNER_OFFSET),
+            deposited_value: read_u128(data, DEPOSITED_VALUE_OFFSET),
+            borrowed_value: read_u128(data, BORROWED_VALUE_OFFSET),
+            allowed_borrow_value: read_u128(data, ALLOWED_BORROW_VALUE_OFFSET),
+            unhealthy_borrow_value: read_u128(data, UNHEALTHY_BORROW_VALUE_OFFSET),
             deposits,
             borrows,
         })
@@ -100,4 +229,308 @@ impl Obligation {
     pub fn is_healthy(&self) -> bool {
         self.borrowed_value <= self.unhealthy_borrow_value
     }
+    
+    /// Deposited value in USD as of the last on-chain refresh (not live oracle prices)
+    pub fn deposited_value_usd(&self) -> Decimal {
+        wad_to_decimal(self.deposited_value)
+    }
+    
+    /// Borrowed value in USD as of the last on-chain refresh (not live oracle prices)
+    pub fn borrowed_value_usd(&self) -> Decimal {
+        wad_to_decimal(self.borrowed_value)
+    }
+    
+    /// Borrow limit in USD as of the last on-chain refresh (not live oracle prices)
+    pub fn allowed_borrow_value_usd(&self) -> Decimal {
+        wad_to_decimal(self.allowed_borrow_value)
+    }
+    
+    /// Liquidation threshold in USD as of the last on-chain refresh (not live oracle prices)
+    pub fn unhealthy_borrow_value_usd(&self) -> Decimal {
+        wad_to_decimal(self.unhealthy_borrow_value)
+    }
+    
+    /// Borrowed value over the liquidation threshold from the cached on-chain values; above 1 is liquidatable
+    /// May disagree with `RefreshedObligation::health_factor`, which reprices with current oracles
+    pub fn health_factor_cached(&self) -> Decimal {
+        if self.borrowed_value == 0 {
+            Decimal::ZERO
+        } else if self.unhealthy_borrow_value == 0 {
+            Decimal::MAX
+        } else {
+            self.borrowed_value_usd() / self.unhealthy_borrow_value_usd()
+        }
+    }
+    
+    /// Whether the obligation was last refreshed more than `max_age` slots before `current_slot`
+    pub fn is_stale(&self, current_slot: u64, max_age: u64) -> bool {
+        current_slot.saturating_sub(self.last_update.slot) > max_age
+    }
+    
+    /// Compound each borrow up to its reserve's cumulative borrow rate, as `RefreshObligation` would
+    /// Borrows whose reserve is unknown or behind the obligation are left unchanged
+    pub fn accrue_interest(&mut self, reserves: &HashMap<Pubkey, Reserve>) {
+        for borrow in &mut self.borrows {
+            let Some(reserve) = reserves.get(&borrow.borrow_reserve) else {
+                continue;
+            };
+            let current_rate = reserve.liquidity.cumulative_borrow_rate_wads;
+            if borrow.cumulative_borrow_rate_wads == 0 || current_rate <= borrow.cumulative_borrow_rate_wads {
+                continue;
+            }
+            
+            borrow.borrowed_amount_wads = borrow.accrued_amount_wads(current_rate);
+            borrow.cumulative_borrow_rate_wads = current_rate;
+        }
+    }
+}
+
+impl ObligationLiquidity {
+    /// Borrowed amount grown by `current_rate / cumulative_borrow_rate_wads`
+    /// Unchanged when the reserve's rate is unknown or behind the obligation's
+    pub fn accrued_amount_wads(&self, current_rate: u128) -> u128 {
+        if self.cumulative_borrow_rate_wads == 0 || current_rate <= self.cumulative_borrow_rate_wads {
+            return self.borrowed_amount_wads;
+        }
+        
+        // Split the multiplication so large borrows can't overflow u128
+        let growth = current_rate.saturating_mul(WAD) / self.cumulative_borrow_rate_wads;
+        let whole = self.borrowed_amount_wads / WAD;
+        let fraction = self.borrowed_amount_wads % WAD;
+        whole
+            .saturating_mul(growth)
+            .saturating_add(fraction.saturating_mul(growth) / WAD)
+    }
+}
+
+#[cfg(test)]
+impl Obligation {
+    /// Pack an obligation into the on-chain layout, mirroring Solend's `Pack` impl
+    pub(crate) fn pack(&self) -> Vec<u8> {
+        let mut data = vec![0u8; OBLIGATION_SIZE];
+        data[0] = self.version;
+        data[1..9].copy_from_slice(&self.last_update.slot.to_le_bytes());
+        data[9] = self.last_update.stale as u8;
+        data[10..42].copy_from_slice(self.lending_market.as_ref());
+        data[42..74].copy_from_slice(self.owner.as_ref());
+        data[74..90].copy_from_slice(&self.deposited_value.to_le_bytes());
+        data[90..106].copy_from_slice(&self.borrowed_value.to_le_bytes());
+        data[106..122].copy_from_slice(&self.allowed_borrow_value.to_le_bytes());
+        data[122..138].copy_from_slice(&self.unhealthy_borrow_value.to_le_bytes());
+        // Newer program versions store extra fields in the padding; they must be ignored
+        data[138..202].fill(0xAB);
+        data[202] = self.deposits.len() as u8;
+        data[203] = self.borrows.len() as u8;
+        
+        let mut offset = 204;
+        for d in &self.deposits {
+            data[offset..offset + 32].copy_from_slice(d.deposit_reserve.as_ref());
+            data[offset + 32..offset + 40].copy_from_slice(&d.deposited_amount.to_le_bytes());
+            data[offset + 40..offset + 56].copy_from_slice(&d.market_value.to_le_bytes());
+            data[offset + 56..offset + 88].fill(0xCD);
+            offset += OBLIGATION_COLLATERAL_LEN;
+        }
+        for b in &self.borrows {
+            data[offset..offset + 32].copy_from_slice(b.borrow_reserve.as_ref());
+            data[offset + 32..offset + 48].copy_from_slice(&b.cumulative_borrow_rate_wads.to_le_bytes());
+            data[offset + 48..offset + 64].copy_from_slice(&b.borrowed_amount_wads.to_le_bytes());
+            data[offset + 64..offset + 80].copy_from_slice(&b.market_value.to_le_bytes());
+            data[offset + 80..offset + 112].fill(0xEF);
+            offset += OBLIGATION_LIQUIDITY_LEN;
+        }
+        
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn sample_obligation() -> Obligation {
+        Obligation {
+            version: 1,
+            last_update: LastUpdate { slot: 250_000_000, stale: true },
+            lending_market: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            deposited_value: 1_500 * crate::utils::WAD,
+            borrowed_value: 900 * crate::utils::WAD,
+            allowed_borrow_value: 1_000 * crate::utils::WAD,
+            unhealthy_borrow_value: 1_100 * crate::utils::WAD,
+            deposits: vec![
+                ObligationCollateral {
+                    deposit_reserve: Pubkey::new_unique(),
+                    deposited_amount: 5_000_000_000,
+                    market_value: 1_000 * crate::utils::WAD,
+                },
+                ObligationCollateral {
+                    deposit_reserve: Pubkey::new_unique(),
+                    deposited_amount: 500_000_000,
+                    market_value: 500 * crate::utils::WAD,
+                },
+            ],
+            borrows: vec![
+                ObligationLiquidity {
+                    borrow_reserve: Pubkey::new_unique(),
+                    cumulative_borrow_rate_wads: crate::utils::WAD + 1,
+                    borrowed_amount_wads: 600_000_000 * crate::utils::WAD,
+                    market_value: 600 * crate::utils::WAD,
+                },
+                ObligationLiquidity {
+                    borrow_reserve: Pubkey::new_unique(),
+                    cumulative_borrow_rate_wads: crate::utils::WAD + 2,
+                    borrowed_amount_wads: 300_000_000 * crate::utils::WAD,
+                    market_value: 300 * crate::utils::WAD,
+                },
+            ],
+        }
+    }
+    
+    #[test]
+    fn test_parse_round_trip_mixed_positions() {
+        let expected = sample_obligation();
+        let parsed = Obligation::parse(&expected.pack()).unwrap();
+        
+        assert_eq!(parsed.version, expected.version);
+        assert_eq!(parsed.last_update.slot, expected.last_update.slot);
+        assert!(parsed.last_update.stale);
+        assert_eq!(parsed.lending_market, expected.lending_market);
+        assert_eq!(parsed.owner, expected.owner);
+        assert_eq!(parsed.borrowed_value, expected.borrowed_value);
+        assert_eq!(parsed.unhealthy_borrow_value, expected.unhealthy_borrow_value);
+        
+        assert_eq!(parsed.deposits.len(), 2);
+        assert_eq!(parsed.deposits[1].deposit_reserve, expected.deposits[1].deposit_reserve);
+        assert_eq!(parsed.deposits[1].deposited_amount, expected.deposits[1].deposited_amount);
+        assert_eq!(parsed.deposits[1].market_value, expected.deposits[1].market_value);
+        
+        assert_eq!(parsed.borrows.len(), 2);
+        assert_eq!(parsed.borrows[0].borrow_reserve, expected.borrows[0].borrow_reserve);
+        assert_eq!(parsed.borrows[1].borrow_reserve, expected.borrows[1].borrow_reserve);
+        assert_eq!(parsed.borrows[1].cumulative_borrow_rate_wads, expected.borrows[1].cumulative_borrow_rate_wads);
+        assert_eq!(parsed.borrows[1].borrowed_amount_wads, expected.borrows[1].borrowed_amount_wads);
+    }
+    
+    #[test]
+    fn test_parse_rejects_too_many_reserves() {
+        let mut data = sample_obligation().pack();
+        data[202] = 8;
+        data[203] = 3;
+        assert!(Obligation::parse(&data).is_err());
+        assert_eq!(
+            Obligation::parse_detailed(&data).unwrap_err(),
+            ObligationParseError::TooManyReserves { offset: 202, deposits: 8, borrows: 3, max: 10 }
+        );
+    }
+    
+    #[test]
+    fn test_parse_rejects_short_data() {
+        assert!(Obligation::parse(&[0u8; 100]).is_err());
+        let err = Obligation::parse_detailed(&[0u8; 100]).unwrap_err();
+        assert_eq!(err.to_string(), "obligation data is 100 bytes, expected at least 1300");
+    }
+    
+    #[test]
+    fn test_parse_max_positions_boundary() {
+        // One deposit and nine borrows fill data_flat exactly
+        let mut obligation = sample_obligation();
+        obligation.deposits.truncate(1);
+        let borrow = obligation.borrows[0].clone();
+        obligation.borrows = vec![borrow; 9];
+        obligation.borrows[8].borrowed_amount_wads = 42;
+        
+        let parsed = Obligation::parse_detailed(&obligation.pack()).unwrap();
+        assert_eq!((parsed.deposits.len(), parsed.borrows.len()), (1, 9));
+        assert_eq!(parsed.borrows[8].borrowed_amount_wads, 42);
+        
+        // Ten deposits are within the limit and fit too
+        let mut data = obligation.pack();
+        data[202] = 10;
+        data[203] = 0;
+        assert_eq!(Obligation::parse_detailed(&data).unwrap().deposits.len(), 10);
+        
+        // An eleventh position is over the limit however it's split
+        data[202] = 1;
+        data[203] = 10;
+        assert!(matches!(
+            Obligation::parse_detailed(&data),
+            Err(ObligationParseError::TooManyReserves { deposits: 1, borrows: 10, .. })
+        ));
+    }
+    
+    #[test]
+    fn test_parse_names_the_entry_past_the_account() {
+        // Ten borrows fit the reserve limit but not the account
+        let mut data = sample_obligation().pack();
+        data[202] = 0;
+        data[203] = 10;
+        let err = Obligation::parse_detailed(&data).unwrap_err();
+        assert_eq!(
+            err,
+            ObligationParseError::EntryOutOfBounds { field: "borrows[9]".to_string(), offset: 1212, size: 1300 }
+        );
+        assert!(Obligation::parse(&data).unwrap_err().to_string().starts_with("borrows[9] at offset 1212"));
+    }
+    
+    #[test]
+    fn test_is_stale_by_slot_age() {
+        let obligation = sample_obligation();
+        let slot = obligation.last_update.slot;
+        
+        assert!(!obligation.is_stale(slot + 100, 100));
+        assert!(obligation.is_stale(slot + 101, 100));
+        // A node lagging behind the obligation's slot doesn't make it stale
+        assert!(!obligation.is_stale(slot - 10, 0));
+    }
+    
+    #[test]
+    fn test_accrue_interest_compounds_to_reserve_rate() {
+        let mut obligation = sample_obligation();
+        obligation.borrows[0].cumulative_borrow_rate_wads = WAD;
+        
+        // A reserve whose cumulative rate has grown 10% since the obligation was refreshed
+        let mut data = vec![0u8; super::super::reserve::RESERVE_SIZE];
+        data[195..211].copy_from_slice(&(WAD + WAD / 10).to_le_bytes());
+        let reserve = Reserve::parse(&data).unwrap();
+        let reserves = HashMap::from([(obligation.borrows[0].borrow_reserve, reserve)]);
+        
+        obligation.accrue_interest(&reserves);
+        
+        assert_eq!(obligation.borrows[0].borrowed_amount_wads, 660_000_000 * WAD);
+        assert_eq!(obligation.borrows[0].cumulative_borrow_rate_wads, WAD + WAD / 10);
+        // No reserve data for the second borrow
+        assert_eq!(obligation.borrows[1].borrowed_amount_wads, 300_000_000 * WAD);
+    }
+    
+    #[test]
+    fn test_health_view_from_slice() {
+        let obligation = sample_obligation();
+        let data = obligation.pack();
+        let view = ObligationHealthView::parse(&data[HEALTH_VIEW_OFFSET..HEALTH_VIEW_OFFSET + HEALTH_VIEW_LEN]).unwrap();
+        
+        assert_eq!(view.last_update.slot, obligation.last_update.slot);
+        assert!(view.last_update.stale);
+        assert_eq!(view.deposited_value, obligation.deposited_value);
+        assert_eq!(view.borrowed_value, obligation.borrowed_value);
+        assert_eq!(view.allowed_borrow_value, obligation.allowed_borrow_value);
+        assert_eq!(view.unhealthy_borrow_value, obligation.unhealthy_borrow_value);
+        assert!(view.is_healthy());
+        
+        assert!(ObligationHealthView::parse(&data[..HEALTH_VIEW_LEN - 1]).is_err());
+    }
+    
+    #[test]
+    fn test_usd_accessors_unscale_wads() {
+        let mut obligation = sample_obligation();
+        obligation.borrowed_value = 1_210 * WAD + WAD / 2;
+        
+        assert_eq!(obligation.deposited_value_usd(), Decimal::from(1_500));
+        assert_eq!(obligation.borrowed_value_usd(), Decimal::new(12_105, 1));
+        assert_eq!(obligation.allowed_borrow_value_usd(), Decimal::from(1_000));
+        assert_eq!(obligation.unhealthy_borrow_value_usd(), Decimal::from(1_100));
+        assert_eq!(obligation.health_factor_cached().round_dp(4), Decimal::new(11_005, 4));
+        
+        obligation.unhealthy_borrow_value = 0;
+        assert_eq!(obligation.health_factor_cached(), Decimal::MAX);
+    }
 }
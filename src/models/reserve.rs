@@ -2,6 +2,7 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use rust_decimal::Decimal;
 use solana_sdk::pubkey::Pubkey;
 use super::last_update::LastUpdate;
+use super::layout::{invalid_data, read_pubkey, read_u128, read_u64};
 
 /// Reserve account size
 pub const RESERVE_SIZE: usize = 619;
@@ -64,21 +65,94 @@ pub struct ReserveConfig {
     pub deposit_limit: u64,
     pub borrow_limit: u64,
     pub fee_receiver: Pubkey,
+    /// Solend's cut of seized collateral, in deca-bps of the amount before the bonus
+    pub protocol_liquidation_fee: u8,
 }
 
+/// Solend reserve layout offsets
+/// version (1) | last_update (8 + 1) | lending_market (32)
+/// | liquidity: mint (32), decimals (1), supply (32), pyth (32), switchboard (32),
+///   available (8), borrowed wads (16), cumulative rate wads (16), market price (16)
+/// | collateral: mint (32), total supply (8), supply (32)
+/// | config: 7 rates (1 each), fees (8 + 8 + 1), deposit limit (8), borrow limit (8), fee receiver (32),
+///   protocol liquidation fee (1)
+/// | protocol take rate, protocol fees, newer config fields and padding (247), which are not read
+const LAST_UPDATE_OFFSET: usize = 1;
+/// Also the memcmp offset for filtering reserves by market
+pub const LENDING_MARKET_OFFSET: usize = 10;
+const LIQUIDITY_MINT_OFFSET: usize = 42;
+const LIQUIDITY_MINT_DECIMALS_OFFSET: usize = 74;
+const LIQUIDITY_SUPPLY_OFFSET: usize = 75;
+const LIQUIDITY_PYTH_ORACLE_OFFSET: usize = 107;
+const LIQUIDITY_SWITCHBOARD_ORACLE_OFFSET: usize = 139;
+const LIQUIDITY_AVAILABLE_AMOUNT_OFFSET: usize = 171;
+const LIQUIDITY_BORROWED_AMOUNT_WADS_OFFSET: usize = 179;
+const LIQUIDITY_CUMULATIVE_BORROW_RATE_WADS_OFFSET: usize = 195;
+const LIQUIDITY_MARKET_PRICE_OFFSET: usize = 211;
+const COLLATERAL_MINT_OFFSET: usize = 227;
+const COLLATERAL_MINT_TOTAL_SUPPLY_OFFSET: usize = 259;
+const COLLATERAL_SUPPLY_OFFSET: usize = 267;
+const CONFIG_OFFSET: usize = 299;
+const CONFIG_BORROW_FEE_WAD_OFFSET: usize = 306;
+const CONFIG_FLASH_LOAN_FEE_WAD_OFFSET: usize = 314;
+const CONFIG_HOST_FEE_PERCENTAGE_OFFSET: usize = 322;
+const CONFIG_DEPOSIT_LIMIT_OFFSET: usize = 323;
+const CONFIG_BORROW_LIMIT_OFFSET: usize = 331;
+const CONFIG_FEE_RECEIVER_OFFSET: usize = 339;
+const CONFIG_PROTOCOL_LIQUIDATION_FEE_OFFSET: usize = 371;
+
 impl Reserve {
     /// Parse reserve from account data
+    /// Follows the packed Solend layout; reads are bounds-checked by the size check
     pub fn parse(data: &[u8]) -> Result<Self, std::io::Error> {
         if data.len() < RESERVE_SIZE {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Invalid reserve data size",
-            ));
+            return Err(invalid_data("Invalid reserve data size".to_string()));
         }
         
-        // Deserialize using Borsh
-        // Note: In production, ensure exact layout matches Solend program
-        Self::deserialize(&mut &data[..])
+        let config = &data[CONFIG_OFFSET..];
+        
+        Ok(Reserve {
+            version: data[0],
+            last_update: LastUpdate {
+                slot: read_u64(data, LAST_UPDATE_OFFSET),
+                stale: data[LAST_UPDATE_OFFSET + 8] != 0,
+            },
+            lending_market: read_pubkey(data, LENDING_MARKET_OFFSET),
+            liquidity: ReserveLiquidity {
+                mint_pubkey: read_pubkey(data, LIQUIDITY_MINT_OFFSET),
+                mint_decimals: data[LIQUIDITY_MINT_DECIMALS_OFFSET],
+                supply_pubkey: read_pubkey(data, LIQUIDITY_SUPPLY_OFFSET),
+                pyth_oracle_pubkey: read_pubkey(data, LIQUIDITY_PYTH_ORACLE_OFFSET),
+                switchboard_oracle_pubkey: read_pubkey(data, LIQUIDITY_SWITCHBOARD_ORACLE_OFFSET),
+                available_amount: read_u64(data, LIQUIDITY_AVAILABLE_AMOUNT_OFFSET),
+                borrowed_amount_wads: read_u128(data, LIQUIDITY_BORROWED_AMOUNT_WADS_OFFSET),
+                cumulative_borrow_rate_wads: read_u128(data, LIQUIDITY_CUMULATIVE_BORROW_RATE_WADS_OFFSET),
+                market_price: read_u128(data, LIQUIDITY_MARKET_PRICE_OFFSET),
+            },
+            collateral: ReserveCollateral {
+                mint_pubkey: read_pubkey(data, COLLATERAL_MINT_OFFSET),
+                mint_total_supply: read_u64(data, COLLATERAL_MINT_TOTAL_SUPPLY_OFFSET),
+                supply_pubkey: read_pubkey(data, COLLATERAL_SUPPLY_OFFSET),
+            },
+            config: ReserveConfig {
+                optimal_utilization_rate: config[0],
+                loan_to_value_ratio: config[1],
+                liquidation_bonus: config[2],
+                liquidation_threshold: config[3],
+                min_borrow_rate: config[4],
+                optimal_borrow_rate: config[5],
+                max_borrow_rate: config[6],
+                fees: ReserveFees {
+                    borrow_fee_wad: read_u64(data, CONFIG_BORROW_FEE_WAD_OFFSET),
+                    flash_loan_fee_wad: read_u64(data, CONFIG_FLASH_LOAN_FEE_WAD_OFFSET),
+                    host_fee_percentage: data[CONFIG_HOST_FEE_PERCENTAGE_OFFSET],
+                },
+                deposit_limit: read_u64(data, CONFIG_DEPOSIT_LIMIT_OFFSET),
+                borrow_limit: read_u64(data, CONFIG_BORROW_LIMIT_OFFSET),
+                fee_receiver: read_pubkey(data, CONFIG_FEE_RECEIVER_OFFSET),
+                protocol_liquidation_fee: data[CONFIG_PROTOCOL_LIQUIDATION_FEE_OFFSET],
+            },
+        })
     }
     
     /// Calculate collateral exchange rate
@@ -105,4 +179,216 @@ impl Reserve {
     pub fn get_liquidation_threshold_rate(&self) -> Decimal {
         Decimal::from(self.config.liquidation_threshold) / Decimal::from(100)
     }
+    
+    /// Fraction of total liquidity currently borrowed
+    pub fn utilization_rate(&self) -> Decimal {
+        let borrowed = Decimal::from(self.liquidity.borrowed_amount_wads) / Decimal::from(WAD);
+        let total_liquidity = Decimal::from(self.liquidity.available_amount) + borrowed;
+        
+        if total_liquidity.is_zero() {
+            Decimal::ZERO
+        } else {
+            borrowed / total_liquidity
+        }
+    }
+    
+    /// Current borrow APR as decimal
+    /// Solend's piecewise-linear model: min -> optimal rate up to the optimal
+    /// utilization, then optimal -> max rate up to full utilization
+    pub fn current_borrow_rate(&self) -> Decimal {
+        let percent = |rate: u8| Decimal::from(rate) / Decimal::from(100);
+        let utilization_rate = self.utilization_rate();
+        let optimal_utilization_rate = percent(self.config.optimal_utilization_rate);
+        let min_rate = percent(self.config.min_borrow_rate);
+        let optimal_rate = percent(self.config.optimal_borrow_rate);
+        let max_rate = percent(self.config.max_borrow_rate);
+        
+        if self.config.optimal_utilization_rate == 100 || utilization_rate < optimal_utilization_rate {
+            let normalized_rate = utilization_rate / optimal_utilization_rate;
+            min_rate + normalized_rate * (optimal_rate - min_rate)
+        } else {
+            let normalized_rate = (utilization_rate - optimal_utilization_rate)
+                / (Decimal::ONE - optimal_utilization_rate);
+            optimal_rate + normalized_rate * (max_rate - optimal_rate)
+        }
+    }
+}
+
+#[cfg(test)]
+impl Reserve {
+    /// Reserve with default keys, 6 decimals and an 80% liquidation threshold
+    pub(crate) fn test_reserve(available_amount: u64, borrowed_amount: u64) -> Reserve {
+        Reserve {
+            version: 1,
+            last_update: LastUpdate { slot: 1, stale: false },
+            lending_market: Pubkey::default(),
+            liquidity: ReserveLiquidity {
+                mint_pubkey: Pubkey::default(),
+                mint_decimals: 6,
+                supply_pubkey: Pubkey::default(),
+                pyth_oracle_pubkey: Pubkey::default(),
+                switchboard_oracle_pubkey: Pubkey::default(),
+                available_amount,
+                borrowed_amount_wads: borrowed_amount as u128 * WAD,
+                cumulative_borrow_rate_wads: WAD,
+                market_price: 0,
+            },
+            collateral: ReserveCollateral {
+                mint_pubkey: Pubkey::default(),
+                mint_total_supply: 0,
+                supply_pubkey: Pubkey::default(),
+            },
+            config: ReserveConfig {
+                optimal_utilization_rate: 80,
+                loan_to_value_ratio: 75,
+                liquidation_bonus: 5,
+                liquidation_threshold: 80,
+                min_borrow_rate: 0,
+                optimal_borrow_rate: 4,
+                max_borrow_rate: 30,
+                fees: ReserveFees {
+                    borrow_fee_wad: 0,
+                    flash_loan_fee_wad: 0,
+                    host_fee_percentage: 0,
+                },
+                deposit_limit: u64::MAX,
+                borrow_limit: u64::MAX,
+                fee_receiver: Pubkey::default(),
+                protocol_liquidation_fee: 0,
+            },
+        }
+    }
+    
+    /// Pack a reserve into the on-chain layout, mirroring Solend's `Pack` impl
+    pub(crate) fn pack(&self) -> Vec<u8> {
+        let mut data = vec![0u8; RESERVE_SIZE];
+        data[0] = self.version;
+        data[1..9].copy_from_slice(&self.last_update.slot.to_le_bytes());
+        data[9] = self.last_update.stale as u8;
+        data[10..42].copy_from_slice(self.lending_market.as_ref());
+        
+        let liquidity = &self.liquidity;
+        data[42..74].copy_from_slice(liquidity.mint_pubkey.as_ref());
+        data[74] = liquidity.mint_decimals;
+        data[75..107].copy_from_slice(liquidity.supply_pubkey.as_ref());
+        data[107..139].copy_from_slice(liquidity.pyth_oracle_pubkey.as_ref());
+        data[139..171].copy_from_slice(liquidity.switchboard_oracle_pubkey.as_ref());
+        data[171..179].copy_from_slice(&liquidity.available_amount.to_le_bytes());
+        data[179..195].copy_from_slice(&liquidity.borrowed_amount_wads.to_le_bytes());
+        data[195..211].copy_from_slice(&liquidity.cumulative_borrow_rate_wads.to_le_bytes());
+        data[211..227].copy_from_slice(&liquidity.market_price.to_le_bytes());
+        
+        let collateral = &self.collateral;
+        data[227..259].copy_from_slice(collateral.mint_pubkey.as_ref());
+        data[259..267].copy_from_slice(&collateral.mint_total_supply.to_le_bytes());
+        data[267..299].copy_from_slice(collateral.supply_pubkey.as_ref());
+        
+        let config = &self.config;
+        data[299..306].copy_from_slice(&[
+            config.optimal_utilization_rate,
+            config.loan_to_value_ratio,
+            config.liquidation_bonus,
+            config.liquidation_threshold,
+            config.min_borrow_rate,
+            config.optimal_borrow_rate,
+            config.max_borrow_rate,
+        ]);
+        data[306..314].copy_from_slice(&config.fees.borrow_fee_wad.to_le_bytes());
+        data[314..322].copy_from_slice(&config.fees.flash_loan_fee_wad.to_le_bytes());
+        data[322] = config.fees.host_fee_percentage;
+        data[323..331].copy_from_slice(&config.deposit_limit.to_le_bytes());
+        data[331..339].copy_from_slice(&config.borrow_limit.to_le_bytes());
+        data[339..371].copy_from_slice(config.fee_receiver.as_ref());
+        data[371] = config.protocol_liquidation_fee;
+        // Protocol fees and newer config fields live past the fields we read
+        data[372..].fill(0xAB);
+        
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_main_pool_sol_reserve() {
+        // Main pool SOL reserve with its mainnet mint, oracles and config
+        let mut expected = Reserve::test_reserve(215_000_000_000_000, 130_000_000_000_000);
+        expected.last_update = LastUpdate { slot: 250_000_000, stale: true };
+        expected.lending_market = "4UpD2fh7xH3VP9QQaXtsS1YY3bxzWhtfpks7FatyKvdY".parse().unwrap();
+        expected.liquidity.mint_pubkey = "So11111111111111111111111111111111111111112".parse().unwrap();
+        expected.liquidity.mint_decimals = 9;
+        expected.liquidity.pyth_oracle_pubkey = "H6ARHf6YXhGYeQfUzQNGk6rDNnLBQKrenN712K4AQJEG".parse().unwrap();
+        expected.liquidity.switchboard_oracle_pubkey = "GvDMxPzN1sCj7L26YDK2HnMRXEQmQ2aemov8YBtPS7vR".parse().unwrap();
+        expected.collateral.mint_total_supply = 1_500_000_000_000_000;
+        expected.config.optimal_borrow_rate = 8;
+        expected.config.max_borrow_rate = 50;
+        expected.config.fees.borrow_fee_wad = 10_000_000_000_000;
+        expected.config.fees.host_fee_percentage = 20;
+        expected.config.fee_receiver = Pubkey::new_unique();
+        expected.config.protocol_liquidation_fee = 30;
+        
+        let parsed = Reserve::parse(&expected.pack()).unwrap();
+        
+        assert_eq!(parsed.last_update.slot, 250_000_000);
+        assert!(parsed.last_update.stale);
+        assert_eq!(parsed.lending_market, expected.lending_market);
+        assert_eq!(parsed.liquidity.mint_pubkey.to_string(), "So11111111111111111111111111111111111111112");
+        assert_eq!(parsed.liquidity.mint_decimals, 9);
+        assert_eq!(parsed.liquidity.pyth_oracle_pubkey, expected.liquidity.pyth_oracle_pubkey);
+        assert_eq!(parsed.liquidity.switchboard_oracle_pubkey, expected.liquidity.switchboard_oracle_pubkey);
+        assert_eq!(parsed.liquidity.available_amount, 215_000_000_000_000);
+        assert_eq!(parsed.liquidity.borrowed_amount_wads, expected.liquidity.borrowed_amount_wads);
+        assert_eq!(parsed.collateral.mint_total_supply, 1_500_000_000_000_000);
+        assert_eq!(parsed.config.loan_to_value_ratio, 75);
+        assert_eq!(parsed.config.liquidation_bonus, 5);
+        assert_eq!(parsed.config.liquidation_threshold, 80);
+        assert_eq!(parsed.config.max_borrow_rate, 50);
+        assert_eq!(parsed.config.fees.borrow_fee_wad, 10_000_000_000_000);
+        assert_eq!(parsed.config.fees.host_fee_percentage, 20);
+        assert_eq!(parsed.config.deposit_limit, u64::MAX);
+        assert_eq!(parsed.config.fee_receiver, expected.config.fee_receiver);
+        assert_eq!(parsed.config.protocol_liquidation_fee, 30);
+    }
+    
+    #[test]
+    fn test_parse_rejects_short_data() {
+        assert!(Reserve::parse(&[0u8; 371]).is_err());
+    }
+    
+    #[test]
+    fn test_utilization_rate() {
+        assert_eq!(Reserve::test_reserve(0, 0).utilization_rate(), Decimal::ZERO);
+        assert_eq!(Reserve::test_reserve(1_000, 0).utilization_rate(), Decimal::ZERO);
+        assert_eq!(Reserve::test_reserve(200, 800).utilization_rate(), Decimal::new(8, 1));
+        assert_eq!(Reserve::test_reserve(0, 1_000).utilization_rate(), Decimal::ONE);
+    }
+
+    #[test]
+    fn test_current_borrow_rate() {
+        // 0% utilization pays the minimum rate
+        assert_eq!(Reserve::test_reserve(1_000, 0).current_borrow_rate(), Decimal::ZERO);
+        // Halfway to optimal: halfway from 0% to 4%
+        assert_eq!(Reserve::test_reserve(600, 400).current_borrow_rate(), Decimal::new(2, 2));
+        // Optimal utilization pays the optimal rate
+        assert_eq!(Reserve::test_reserve(200, 800).current_borrow_rate(), Decimal::new(4, 2));
+        // Halfway from optimal to full: halfway from 4% to 30%
+        assert_eq!(Reserve::test_reserve(100, 900).current_borrow_rate(), Decimal::new(17, 2));
+        // Full utilization pays the max rate
+        assert_eq!(Reserve::test_reserve(0, 1_000).current_borrow_rate(), Decimal::new(30, 2));
+    }
+
+    #[test]
+    fn test_current_borrow_rate_edge_configs() {
+        // Optimal at 100% never reaches the max rate segment
+        let mut reserve = Reserve::test_reserve(0, 1_000);
+        reserve.config.optimal_utilization_rate = 100;
+        assert_eq!(reserve.current_borrow_rate(), Decimal::new(4, 2));
+        
+        // Optimal at 0% is entirely the upper segment
+        let mut reserve = Reserve::test_reserve(1_000, 0);
+        reserve.config.optimal_utilization_rate = 0;
+        assert_eq!(reserve.current_borrow_rate(), Decimal::new(4, 2));
+    }
 }
@@ -0,0 +1,413 @@
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
+use rust_decimal::Decimal;
+use std::sync::Mutex;
+
+use crate::cache::Cache;
+use crate::config::Config;
+use crate::utils::{http_client_builder, HttpClientConfig};
+
+/// Telegram Bot API base URL
+const TELEGRAM_API_URL: &str = "https://api.telegram.org";
+
+/// Repeat oracle and low-balance alerts for the same subject at most this often
+const ALERT_INTERVAL_SECS: u64 = 3600;
+
+/// Alert subjects remembered for rate limiting; the least recently alerted are forgotten first
+const MAX_TRACKED_ALERTS: usize = 1024;
+
+/// Discord embed colors
+const DISCORD_GREEN: u32 = 0x2ecc71;
+const DISCORD_RED: u32 = 0xe74c3c;
+const DISCORD_ORANGE: u32 = 0xe67e22;
+
+/// Events worth telling an operator about
+#[derive(Debug, Clone)]
+pub enum Event {
+    LiquidationSucceeded {
+        market: String,
+        obligation: String,
+        repay_symbol: String,
+        withdraw_symbol: String,
+        signature: String,
+        profit_usd: Decimal,
+    },
+    LiquidationFailed {
+        market: String,
+        obligation: String,
+        repay_symbol: String,
+        withdraw_symbol: String,
+        error: String,
+    },
+    OracleRejected {
+        market: String,
+        symbol: String,
+        source: String,
+        reason: String,
+    },
+    LowSolBalance {
+        wallet: String,
+        balance_sol: f64,
+        min_sol: f64,
+    },
+}
+
+impl Event {
+    pub fn title(&self) -> &'static str {
+        match self {
+            Event::LiquidationSucceeded { .. } => "✅ Liquidation succeeded",
+            Event::LiquidationFailed { .. } => "❌ Liquidation failed",
+            Event::OracleRejected { .. } => "⚠️ Oracle price rejected",
+            Event::LowSolBalance { .. } => "⛽ Low SOL balance",
+        }
+    }
+
+    /// Labelled details, in display order
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Event::LiquidationSucceeded {
+                market,
+                obligation,
+                repay_symbol,
+                withdraw_symbol,
+                signature,
+                profit_usd,
+            } => vec![
+                ("Market", market.clone()),
+                ("Obligation", obligation.clone()),
+                ("Repay", repay_symbol.clone()),
+                ("Withdraw", withdraw_symbol.clone()),
+                ("Est. profit", format!("${:.2}", profit_usd.round_dp(2))),
+                ("Signature", signature.clone()),
+            ],
+            Event::LiquidationFailed {
+                market,
+                obligation,
+                repay_symbol,
+                withdraw_symbol,
+                error,
+            } => vec![
+                ("Market", market.clone()),
+                ("Obligation", obligation.clone()),
+                ("Repay", repay_symbol.clone()),
+                ("Withdraw", withdraw_symbol.clone()),
+                ("Error", error.clone()),
+            ],
+            Event::OracleRejected {
+                market,
+                symbol,
+                source,
+                reason,
+            } => vec![
+                ("Market", market.clone()),
+                ("Token", symbol.clone()),
+                ("Oracle", source.clone()),
+                ("Reason", reason.clone()),
+            ],
+            Event::LowSolBalance {
+                wallet,
+                balance_sol,
+                min_sol,
+            } => vec![
+                ("Wallet", wallet.clone()),
+                ("Balance", format!("{:.4} SOL", balance_sol)),
+                ("Minimum", format!("{} SOL", min_sol)),
+            ],
+        }
+    }
+
+    /// Plain-text message for chat-style sinks
+    pub fn message(&self) -> String {
+        let mut message = self.title().to_string();
+        for (name, value) in self.fields() {
+            message.push_str(&format!("\n{}: {}", name, value));
+        }
+        message
+    }
+
+    fn discord_color(&self) -> u32 {
+        match self {
+            Event::LiquidationSucceeded { .. } => DISCORD_GREEN,
+            Event::LiquidationFailed { .. } => DISCORD_RED,
+            Event::OracleRejected { .. } | Event::LowSolBalance { .. } => DISCORD_ORANGE,
+        }
+    }
+
+    /// Subject of a recurring alert; repeats for the same subject are throttled
+    fn alert_key(&self) -> Option<(String, String)> {
+        match self {
+            Event::OracleRejected { market, symbol, .. } => Some((market.clone(), symbol.clone())),
+            Event::LowSolBalance { wallet, .. } => Some(("SOL balance".to_string(), wallet.clone())),
+            _ => None,
+        }
+    }
+}
+
+/// A sink for operator notifications
+pub trait Notifier: Send + Sync {
+    fn notify<'a>(&'a self, event: &'a Event) -> BoxFuture<'a, Result<()>>;
+}
+
+/// Posts notifications to a Telegram chat through a bot
+pub struct TelegramNotifier {
+    client: reqwest::Client,
+    bot_token: String,
+    chat_id: String,
+}
+
+impl TelegramNotifier {
+    pub fn new(bot_token: String, chat_id: String, http: &HttpClientConfig) -> Self {
+        Self {
+            client: http_client(http),
+            bot_token,
+            chat_id,
+        }
+    }
+}
+
+impl Notifier for TelegramNotifier {
+    fn notify<'a>(&'a self, event: &'a Event) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let url = format!("{}/bot{}/sendMessage", TELEGRAM_API_URL, self.bot_token);
+            let body = serde_json::json!({
+                "chat_id": self.chat_id,
+                "text": event.message(),
+            });
+
+            // The URL carries the bot token, so it's kept out of the error
+            let response = self
+                .client
+                .post(&url)
+                .json(&body)
+                .send()
+                .await
+                .map_err(|e| anyhow!("Telegram request failed: {}", e.without_url()))?;
+            if !response.status().is_success() {
+                return Err(anyhow!("Telegram API error ({})", response.status()));
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// Posts notifications as embeds to a Discord webhook
+pub struct DiscordWebhookNotifier {
+    client: reqwest::Client,
+    webhook_url: String,
+}
+
+impl DiscordWebhookNotifier {
+    pub fn new(webhook_url: String, http: &HttpClientConfig) -> Self {
+        Self {
+            client: http_client(http),
+            webhook_url,
+        }
+    }
+
+    fn payload(event: &Event) -> serde_json::Value {
+        let fields: Vec<serde_json::Value> = event
+            .fields()
+            .into_iter()
+            .map(|(name, value)| serde_json::json!({ "name": name, "value": value }))
+            .collect();
+
+        serde_json::json!({
+            "embeds": [{
+                "title": event.title(),
+                "color": event.discord_color(),
+                "fields": fields,
+            }]
+        })
+    }
+}
+
+impl Notifier for DiscordWebhookNotifier {
+    fn notify<'a>(&'a self, event: &'a Event) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let response = self
+                .client
+                .post(&self.webhook_url)
+                .json(&Self::payload(event))
+                .send()
+                .await
+                // The webhook URL is a secret, so it's kept out of the error
+                .map_err(|e| anyhow!("Discord webhook request failed: {}", e.without_url()))?;
+            if !response.status().is_success() {
+                return Err(anyhow!("Discord webhook error ({})", response.status()));
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// Falls back to reqwest's defaults if the TLS backend can't be configured
+fn http_client(http: &HttpClientConfig) -> reqwest::Client {
+    http_client_builder(http).build().unwrap_or_else(|_| reqwest::Client::new())
+}
+
+/// All configured notification sinks
+pub struct Notifiers {
+    sinks: Vec<Box<dyn Notifier>>,
+    /// Recently alerted subjects, so a bad feed or empty wallet doesn't alert every epoch
+    alerts: Mutex<Cache<(String, String), ()>>,
+}
+
+impl Notifiers {
+    pub fn new(sinks: Vec<Box<dyn Notifier>>) -> Self {
+        Self {
+            sinks,
+            alerts: Mutex::new(Cache::new(ALERT_INTERVAL_SECS).with_max_entries(MAX_TRACKED_ALERTS)),
+        }
+    }
+
+    /// Build every sink whose credentials are configured
+    pub fn from_config(config: &Config) -> Self {
+        let mut sinks: Vec<Box<dyn Notifier>> = Vec::new();
+
+        if let (Some(bot_token), Some(chat_id)) = (&config.telegram_bot_token, &config.telegram_chat_id) {
+            sinks.push(Box::new(TelegramNotifier::new(bot_token.clone(), chat_id.clone(), &config.http)));
+        }
+
+        if let Some(webhook_url) = &config.discord_webhook_url {
+            sinks.push(Box::new(DiscordWebhookNotifier::new(webhook_url.clone(), &config.http)));
+        }
+
+        Self::new(sinks)
+    }
+
+    pub fn len(&self) -> usize {
+        self.sinks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    /// Send an event to every sink, logging rather than propagating delivery failures
+    pub async fn send(&self, event: Event) {
+        if self.sinks.is_empty() || self.is_repeat_alert(&event) {
+            return;
+        }
+
+        for sink in &self.sinks {
+            if let Err(e) = sink.notify(&event).await {
+                log::warn!("Failed to send notification: {}", e);
+            }
+        }
+    }
+
+    fn is_repeat_alert(&self, event: &Event) -> bool {
+        let Some(key) = event.alert_key() else {
+            return false;
+        };
+        let Ok(mut alerts) = self.alerts.lock() else {
+            return false;
+        };
+
+        if alerts.get(&key).is_some() {
+            return true;
+        }
+        alerts.prune_expired();
+        alerts.insert(key, ());
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct CountingNotifier(Arc<AtomicUsize>);
+
+    impl Notifier for CountingNotifier {
+        fn notify<'a>(&'a self, _event: &'a Event) -> BoxFuture<'a, Result<()>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    fn oracle_event(symbol: &str) -> Event {
+        Event::OracleRejected {
+            market: "main".to_string(),
+            symbol: symbol.to_string(),
+            source: "Pyth".to_string(),
+            reason: "status: 0".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_event_message() {
+        let event = Event::LiquidationSucceeded {
+            market: "main".to_string(),
+            obligation: "Obl1".to_string(),
+            repay_symbol: "USDC".to_string(),
+            withdraw_symbol: "SOL".to_string(),
+            signature: "Sig1".to_string(),
+            profit_usd: Decimal::new(123456, 4),
+        };
+        let message = event.message();
+        assert!(message.contains("Obligation: Obl1"));
+        assert!(message.contains("$12.35"));
+        assert!(message.contains("Signature: Sig1"));
+
+        let event = Event::LiquidationFailed {
+            market: "main".to_string(),
+            obligation: "Obl1".to_string(),
+            repay_symbol: "USDC".to_string(),
+            withdraw_symbol: "SOL".to_string(),
+            error: "simulation failed".to_string(),
+        };
+        assert!(event.message().contains("Error: simulation failed"));
+    }
+
+    #[test]
+    fn test_discord_payload() {
+        let payload = DiscordWebhookNotifier::payload(&oracle_event("SOL"));
+        let embed = &payload["embeds"][0];
+        assert_eq!(embed["color"], DISCORD_ORANGE);
+        assert_eq!(embed["fields"][1]["name"], "Token");
+        assert_eq!(embed["fields"][1]["value"], "SOL");
+    }
+
+    #[tokio::test]
+    async fn test_discord_errors_hide_the_webhook_url() {
+        let discord = DiscordWebhookNotifier::new(
+            "http://127.0.0.1:1/api/webhooks/1/secret-token".to_string(),
+            &HttpClientConfig::default(),
+        );
+
+        let error = discord.notify(&oracle_event("SOL")).await.unwrap_err().to_string();
+        assert!(error.contains("Discord webhook request failed"));
+        assert!(!error.contains("secret-token"));
+    }
+
+    #[tokio::test]
+    async fn test_notifiers_fan_out_and_dedupe_oracle_alerts() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let notifiers = Notifiers::new(vec![
+            Box::new(CountingNotifier(count.clone())),
+            Box::new(CountingNotifier(count.clone())),
+        ]);
+
+        notifiers.send(oracle_event("SOL")).await;
+        notifiers.send(oracle_event("SOL")).await;
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+
+        notifiers.send(oracle_event("USDC")).await;
+        assert_eq!(count.load(Ordering::SeqCst), 4);
+
+        let low_sol = Event::LowSolBalance {
+            wallet: "Wallet1".to_string(),
+            balance_sol: 0.01,
+            min_sol: 0.05,
+        };
+        assert!(low_sol.message().contains("Balance: 0.0100 SOL"));
+        notifiers.send(low_sol.clone()).await;
+        notifiers.send(low_sol).await;
+        assert_eq!(count.load(Ordering::SeqCst), 6);
+    }
+}
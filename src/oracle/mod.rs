@@ -3,88 +3,224 @@ pub mod switchboard;
 pub mod validation;
 
 use anyhow::Result;
+use rust_decimal::Decimal;
 use std::collections::HashMap;
 
-use crate::models::MarketConfig;
+use crate::models::{MarketConfig, MarketConfigReserve};
 use crate::rpc::SolendRpcClient;
 
 pub use pyth::TokenOracleData;
-pub use pyth::NULL_ORACLE;
+pub use pyth::{DEFAULT_MAX_CONF_RATIO, NULL_ORACLE};
+pub use validation::{StalenessConfig, DEFAULT_MAX_SLOT_AGE, DEFAULT_ORACLE_TOLERANCE_PERCENT};
 
+use solana_sdk::account::Account as SolanaAccount;
 use solana_sdk::pubkey::Pubkey;
 use std::str::FromStr;
 
+/// An oracle price that failed parsing or validation
+#[derive(Debug, Clone)]
+pub struct OracleRejection {
+    pub symbol: String,
+    pub source: &'static str,
+    pub reason: String,
+}
+
 /// Fetch oracle data for all tokens in a market
 /// Optimized to use batch fetching (1 RPC call instead of N)
+/// Pyth prices whose confidence / price exceeds `max_conf_ratio` are skipped
+/// Pyth prices more than `tolerance_percent` away from the reserve's Switchboard feed are untrusted
+/// Prices older than `staleness` allows for their token are dropped
 pub async fn get_tokens_oracle_data(
     client: &SolendRpcClient,
     market: &MarketConfig,
+    max_conf_ratio: f64,
+    tolerance_percent: f64,
+    staleness: &StalenessConfig,
 ) -> Result<HashMap<String, TokenOracleData>> {
+    let (oracle_data, _) =
+        get_tokens_oracle_data_with_rejections(client, market, max_conf_ratio, tolerance_percent, staleness).await?;
+    Ok(oracle_data)
+}
+
+/// Like `get_tokens_oracle_data`, also returning the prices that were skipped or untrusted
+pub async fn get_tokens_oracle_data_with_rejections(
+    client: &SolendRpcClient,
+    market: &MarketConfig,
+    max_conf_ratio: f64,
+    tolerance_percent: f64,
+    staleness: &StalenessConfig,
+) -> Result<(HashMap<String, TokenOracleData>, Vec<OracleRejection>)> {
+    let oracle_requests = oracle_requests(market);
+    if oracle_requests.is_empty() {
+        return Ok((HashMap::new(), Vec::new()));
+    }
+    
+    // Fetch all accounts in batches
+    // Cross-check feeds are appended after the primary feeds
+    let mut pubkeys: Vec<Pubkey> = oracle_requests.iter().map(|(_, pk, _)| *pk).collect();
+    pubkeys.extend(oracle_requests.iter().filter_map(|(_, _, cross_check)| *cross_check));
+    let accounts = client.get_multiple_accounts_batched(&pubkeys, 100).await?;
+    let accounts: HashMap<Pubkey, SolanaAccount> = pubkeys
+        .into_iter()
+        .zip(accounts)
+        .filter_map(|(pubkey, account)| Some((pubkey, account?)))
+        .collect();
+    
+    let current_slot = client.get_slot()?;
+    Ok(parse_oracle_accounts(&oracle_requests, &accounts, max_conf_ratio, tolerance_percent, staleness, current_slot))
+}
+
+/// Parse and validate a market's oracle prices from already-fetched accounts
+/// Same checks as `get_tokens_oracle_data_with_rejections`, with staleness measured at `current_slot`
+pub fn oracle_data_from_accounts(
+    market: &MarketConfig,
+    accounts: &HashMap<Pubkey, SolanaAccount>,
+    max_conf_ratio: f64,
+    tolerance_percent: f64,
+    staleness: &StalenessConfig,
+    current_slot: u64,
+) -> (HashMap<String, TokenOracleData>, Vec<OracleRejection>) {
+    let oracle_requests = oracle_requests(market);
+    parse_oracle_accounts(&oracle_requests, accounts, max_conf_ratio, tolerance_percent, staleness, current_slot)
+}
+
+fn parse_oracle_accounts(
+    oracle_requests: &[(&MarketConfigReserve, Pubkey, Option<Pubkey>)],
+    accounts: &HashMap<Pubkey, SolanaAccount>,
+    max_conf_ratio: f64,
+    tolerance_percent: f64,
+    staleness: &StalenessConfig,
+    current_slot: u64,
+) -> (HashMap<String, TokenOracleData>, Vec<OracleRejection>) {
     let mut oracle_data = HashMap::new();
+    let mut rejections = Vec::new();
+    // symbol -> (oracle source, publish slot) for validation
+    let mut sources = HashMap::new();
+    
+    for &(reserve, pubkey, cross_check) in oracle_requests {
+        let cross_check_account = cross_check.and_then(|pubkey| accounts.get(&pubkey));
+        
+        if let Some(account) = accounts.get(&pubkey) {
+            // The address was picked from pyth_oracle first, so parse accordingly
+            let (source, parsed) = if reserve.pyth_oracle != pyth::NULL_ORACLE {
+                let parsed = pyth::parse_price_from_account(account).and_then(|p| {
+                    p.check_confidence(max_conf_ratio)?;
+                    Ok((p.price, p.confidence, p.ema_price, p.publish_slot))
+                });
+                ("Pyth", parsed)
+            } else {
+                // Switchboard feeds don't publish a confidence band
+                let parsed = switchboard::parse_price_from_account(account)
+                    .map(|p| (p.price, Decimal::ZERO, p.price, p.slot));
+                ("Switchboard", parsed)
+            };
+            
+            match parsed {
+                Ok((price, confidence, ema_price, slot)) => {
+                    let symbol = &reserve.liquidity_token.symbol;
+                    let trusted = match cross_check_account.map(switchboard::parse_price_from_account) {
+                        Some(Ok(sb)) => match validation::compare_oracle_sources(
+                            symbol,
+                            Some(price),
+                            Some(sb.price),
+                            tolerance_percent,
+                        ) {
+                            Ok(()) => true,
+                            Err(e) => {
+                                rejections.push(OracleRejection {
+                                    symbol: symbol.clone(),
+                                    source: "Pyth/Switchboard",
+                                    reason: e.to_string(),
+                                });
+                                false
+                            }
+                        },
+                        // A dead secondary feed can't vouch either way
+                        Some(Err(e)) => {
+                            log::debug!("Cannot cross-check {} against Switchboard: {}", symbol, e);
+                            true
+                        }
+                        None => true,
+                    };
+                    
+                    let data = TokenOracleData {
+                        symbol: reserve.liquidity_token.symbol.clone(),
+                        reserve_address: reserve.address.clone(),
+                        mint_address: reserve.liquidity_token.mint.clone(),
+                        decimals: 10u32.pow(reserve.liquidity_token.decimals as u32),
+                        price,
+                        confidence,
+                        ema_price,
+                        trusted,
+                    };
+                    sources.insert(data.symbol.clone(), (source, slot));
+                    oracle_data.insert(data.symbol.clone(), data);
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Skipping {} oracle for {}: {}",
+                        source,
+                        reserve.liquidity_token.symbol,
+                        e
+                    );
+                    rejections.push(OracleRejection {
+                        symbol: reserve.liquidity_token.symbol.clone(),
+                        source,
+                        reason: e.to_string(),
+                    });
+                }
+            }
+        } else {
+            log::warn!("Oracle account not found for {}", reserve.liquidity_token.symbol);
+        }
+    }
+    
+    log::info!("Fetched oracle data for {} tokens (batched)", oracle_data.len());
+    
+    // Drop stale or out-of-range prices before they reach liquidation math
+    let prices: HashMap<String, (Decimal, u64)> = oracle_data
+        .values()
+        .map(|d| (d.symbol.clone(), (d.price, sources[&d.symbol].1)))
+        .collect();
+    
+    for (symbol, warnings) in validation::validate_oracle_prices(&prices, current_slot, staleness) {
+        oracle_data.remove(&symbol);
+        rejections.push(OracleRejection {
+            source: sources[&symbol].0,
+            symbol,
+            reason: warnings.join("; "),
+        });
+    }
+    (oracle_data, rejections)
+}
+
+/// Each reserve's oracle address, plus the Switchboard feed to cross-check Pyth against
+fn oracle_requests(market: &MarketConfig) -> Vec<(&MarketConfigReserve, Pubkey, Option<Pubkey>)> {
     let mut oracle_requests = Vec::new();
     
-    // 1. Collect all oracle addresses to fetch
     for reserve in &market.reserves {
         let oracle_addr_str = if reserve.pyth_oracle != pyth::NULL_ORACLE {
             &reserve.pyth_oracle
         } else if reserve.switchboard_oracle != pyth::NULL_ORACLE {
-            // For now, we only batch fetch Pyth. Switchboard is handled individually if needed,
-            // or we could add it here if we implemented parsing.
-            // Given the placeholder status of Switchboard, we'll focus on Pyth.
             &reserve.switchboard_oracle
         } else {
             log::warn!("No valid oracle for {}", reserve.liquidity_token.symbol);
             continue;
         };
         
-        if let Ok(pubkey) = Pubkey::from_str(oracle_addr_str) {
-            oracle_requests.push((reserve, pubkey, oracle_addr_str.clone()));
-        }
-    }
-    
-    if oracle_requests.is_empty() {
-        return Ok(oracle_data);
-    }
-    
-    // 2. Fetch all accounts in batches
-    let pubkeys: Vec<Pubkey> = oracle_requests.iter().map(|(_, pk, _)| *pk).collect();
-    let accounts = client.get_multiple_accounts_batched(&pubkeys, 100).await?;
-    
-    // 3. Parse results
-    for ((reserve, _, _), account_opt) in oracle_requests.iter().zip(accounts.iter()) {
-        if let Some(account) = account_opt {
-            // Currently assuming Pyth for parsing as it's the primary oracle
-            // If Switchboard becomes active, we'd need to distinguish based on the address source
-            if reserve.pyth_oracle != pyth::NULL_ORACLE {
-                match pyth::parse_price_from_account(account) {
-                    Ok(price) => {
-                        let data = TokenOracleData {
-                            symbol: reserve.liquidity_token.symbol.clone(),
-                            reserve_address: reserve.address.clone(),
-                            mint_address: reserve.liquidity_token.mint.clone(),
-                            decimals: 10u32.pow(reserve.liquidity_token.decimals as u32),
-                            price,
-                        };
-                        oracle_data.insert(data.symbol.clone(), data);
-                    }
-                    Err(e) => {
-                        log::warn!(
-                            "Failed to parse Pyth oracle for {}: {}",
-                            reserve.liquidity_token.symbol,
-                            e
-                        );
-                    }
-                }
-            } else {
-                 // Fallback for Switchboard (if we fetched it) - logic similar to before
-                 // Since we don't have parsing logic exposed properly for Switchboard yet,
-                 // and it's a placeholder, we skip or could implement a basic check.
-            }
+        // Reserves with both feeds have their Pyth price cross-checked against Switchboard
+        let cross_check = if reserve.pyth_oracle != pyth::NULL_ORACLE
+            && reserve.switchboard_oracle != pyth::NULL_ORACLE
+        {
+            Pubkey::from_str(&reserve.switchboard_oracle).ok()
         } else {
-            log::warn!("Oracle account not found for {}", reserve.liquidity_token.symbol);
+            None
+        };
+        
+        if let Ok(pubkey) = Pubkey::from_str(oracle_addr_str) {
+            oracle_requests.push((reserve, pubkey, cross_check));
         }
     }
     
-    log::info!("Fetched oracle data for {} tokens (batched)", oracle_data.len());
-    Ok(oracle_data)
+    oracle_requests
 }
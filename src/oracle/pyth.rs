@@ -5,11 +5,20 @@ use solana_sdk::account::Account as SolanaAccount;
 use solana_sdk::pubkey::Pubkey;
 use std::str::FromStr;
 
+use borsh::BorshDeserialize;
+use pyth_sdk_solana::state::{load_price_account, PriceStatus};
+
 use crate::models::MarketConfigReserve;
 use crate::rpc::SolendRpcClient;
 
 pub const NULL_ORACLE: &str = "nu11111111111111111111111111111111111111111";
 
+/// Default maximum confidence / price ratio (2%)
+pub const DEFAULT_MAX_CONF_RATIO: f64 = 0.02;
+
+/// Anchor discriminator of the Pyth receiver's pull `PriceUpdateV2` account
+pub const PRICE_UPDATE_V2_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+
 /// Token oracle data
 #[derive(Debug, Clone)]
 pub struct TokenOracleData {
@@ -18,6 +27,22 @@ pub struct TokenOracleData {
     pub mint_address: String,
     pub decimals: u32,
     pub price: Decimal,
+    pub confidence: Decimal,
+    /// Pyth EMA price; equal to `price` for sources without one
+    pub ema_price: Decimal,
+    /// False when a second oracle disagrees; liquidations touching the token are skipped
+    pub trusted: bool,
+}
+
+impl TokenOracleData {
+    /// Price used for obligation health: EMA when requested, spot otherwise
+    pub fn health_price(&self, use_ema: bool) -> Decimal {
+        if use_ema {
+            self.ema_price
+        } else {
+            self.price
+        }
+    }
 }
 
 /// Fetch token price from Pyth oracle
@@ -25,12 +50,13 @@ pub async fn get_token_oracle_data(
     client: &SolendRpcClient,
     reserve: &MarketConfigReserve,
 ) -> Result<TokenOracleData> {
-    let price = if reserve.pyth_oracle != NULL_ORACLE {
+    let PythPrice { price, confidence, ema_price, .. } = if reserve.pyth_oracle != NULL_ORACLE {
         // Try Pyth oracle first
         fetch_pyth_price(client, &reserve.pyth_oracle).await?
     } else if reserve.switchboard_oracle != NULL_ORACLE {
-        // Fallback to Switchboard
-        super::switchboard::fetch_switchboard_price(client, &reserve.switchboard_oracle).await?
+        // Fallback to Switchboard (no confidence band published)
+        let price = super::switchboard::fetch_switchboard_price(client, &reserve.switchboard_oracle).await?;
+        PythPrice { price, confidence: Decimal::ZERO, ema_price: price, publish_slot: 0 }
     } else {
         return Err(anyhow!("No valid oracle for {}", reserve.liquidity_token.symbol));
     };
@@ -41,54 +67,303 @@ pub async fn get_token_oracle_data(
         mint_address: reserve.liquidity_token.mint.clone(),
         decimals: 10u32.pow(reserve.liquidity_token.decimals as u32),
         price,
+        confidence,
+        ema_price,
+        trusted: true,
     })
 }
 
 /// Fetch price from Pyth oracle
-async fn fetch_pyth_price(client: &SolendRpcClient, oracle_address: &str) -> Result<Decimal> {
+async fn fetch_pyth_price(client: &SolendRpcClient, oracle_address: &str) -> Result<PythPrice> {
     let pubkey = Pubkey::from_str(oracle_address)?;
     let account = client.get_account(&pubkey)?;
     parse_price_from_account(&account)
 }
 
-/// Parse price from Pyth account data
-pub fn parse_price_from_account(account: &SolanaAccount) -> Result<Decimal> {
-    // Check if account data is large enough for Pyth price feed
-    if account.data.len() < 200 {
-        return Err(anyhow!("Invalid Pyth account data size"));
+/// Price and confidence interval parsed from a Pyth price account
+#[derive(Debug, Clone, Copy)]
+pub struct PythPrice {
+    pub price: Decimal,
+    pub confidence: Decimal,
+    /// Exponential moving average of the aggregate price
+    pub ema_price: Decimal,
+    /// Slot the aggregate price was published in
+    pub publish_slot: u64,
+}
+
+impl PythPrice {
+    /// Confidence interval as a fraction of the price
+    pub fn confidence_ratio(&self) -> Decimal {
+        if self.price.is_zero() {
+            return Decimal::MAX;
+        }
+        self.confidence / self.price
     }
     
-    // Pyth V2 price offset is at byte 208-215 (price as i64)
-    // Expo is at byte 216-219 (expo as i32)
-    // This is a simplified parsing - in production use pyth-sdk properly
+    /// Reject prices whose confidence band is wider than `max_conf_ratio`
+    pub fn check_confidence(&self, max_conf_ratio: f64) -> Result<()> {
+        let ratio = self.confidence_ratio().to_f64().unwrap_or(f64::MAX);
+        if ratio > max_conf_ratio {
+            return Err(anyhow!(
+                "Pyth confidence too wide ({} +/- {}, ratio {:.4} > {})",
+                self.price,
+                self.confidence,
+                ratio,
+                max_conf_ratio
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Scale a raw Pyth integer by 10^expo
+fn apply_expo(value: Decimal, expo: i32) -> Decimal {
+    let exponent = Decimal::from(10i64.pow(expo.unsigned_abs()));
+    
+    if expo < 0 {
+        value / exponent
+    } else {
+        value * exponent
+    }
+}
+
+/// How many Wormhole guardian signatures the receiver checked before posting an update
+#[derive(Debug, Clone, Copy, PartialEq, BorshDeserialize)]
+enum VerificationLevel {
+    Partial { num_signatures: u8 },
+    Full,
+}
+
+/// Price message of a pull update, in the receiver's Borsh layout
+#[derive(Debug, BorshDeserialize)]
+struct PriceFeedMessage {
+    _feed_id: [u8; 32],
+    price: i64,
+    conf: u64,
+    exponent: i32,
+    _publish_time: i64,
+    _prev_publish_time: i64,
+    ema_price: i64,
+    _ema_conf: u64,
+}
+
+/// Pyth receiver `PriceUpdateV2` account, after the discriminator
+#[derive(Debug, BorshDeserialize)]
+struct PriceUpdateV2 {
+    _write_authority: Pubkey,
+    verification_level: VerificationLevel,
+    price_message: PriceFeedMessage,
+    posted_slot: u64,
+}
+
+/// Parse price and confidence from a Pyth account, either a legacy push price
+/// account or a pull `PriceUpdateV2` account
+pub fn parse_price_from_account(account: &SolanaAccount) -> Result<PythPrice> {
+    match account.data.strip_prefix(&PRICE_UPDATE_V2_DISCRIMINATOR) {
+        Some(update) => parse_price_update_v2(update),
+        None => parse_legacy_price_account(account),
+    }
+}
+
+/// Pull updates are only trusted once every guardian signature was verified
+/// Staleness is judged by the slot the update was posted in
+fn parse_price_update_v2(mut data: &[u8]) -> Result<PythPrice> {
+    // The account is sized for the larger `Partial` variant, so `Full` updates leave a trailing byte
+    let update = PriceUpdateV2::deserialize(&mut data)
+        .map_err(|e| anyhow!("Invalid Pyth price update account: {}", e))?;
     
-    let price_bytes = &account.data[208..216];
-    let expo_bytes = &account.data[216..220];
+    if update.verification_level != VerificationLevel::Full {
+        return Err(anyhow!(
+            "Pyth price update is only partially verified ({:?})",
+            update.verification_level
+        ));
+    }
     
-    // Safety check for panic-free slicing
-    if account.data.len() < 220 {
-        return Err(anyhow!("Pyth account data too small"));
+    let message = &update.price_message;
+    let price = apply_expo(Decimal::from(message.price), message.exponent);
+    if price.is_sign_negative() {
+        return Err(anyhow!("Invalid negative price from Pyth oracle: {}", price));
     }
     
-    let price_i64 = i64::from_le_bytes(price_bytes.try_into()?);
-    let expo = i32::from_le_bytes(expo_bytes.try_into()?);
+    Ok(PythPrice {
+        price,
+        confidence: apply_expo(Decimal::from(message.conf), message.exponent),
+        ema_price: apply_expo(Decimal::from(message.ema_price), message.exponent),
+        publish_slot: update.posted_slot,
+    })
+}
+
+/// Only the aggregate price of a trading feed is used; the previous price a halted
+/// feed falls back to may be arbitrarily old
+fn parse_legacy_price_account(account: &SolanaAccount) -> Result<PythPrice> {
+    let price_account = load_price_account::<32, ()>(&account.data)
+        .map_err(|e| anyhow!("Invalid Pyth price account: {}", e))?;
+    let aggregate = &price_account.agg;
     
-    // Convert to decimal: price * 10^expo
-    let price = Decimal::from(price_i64);
-    let exponent = Decimal::from(10i64.pow(expo.abs() as u32));
+    if aggregate.status != PriceStatus::Trading {
+        return Err(anyhow!(
+            "Pyth price feed is not trading (status: {}, {})",
+            aggregate.status as u8,
+            aggregate.status
+        ));
+    }
     
-    let final_price = if expo < 0 {
-        price / exponent
-    } else {
-        price * exponent
-    };
+    // Convert to decimal: value * 10^expo
+    let expo = price_account.expo;
+    let final_price = apply_expo(Decimal::from(aggregate.price), expo);
+    let confidence = apply_expo(Decimal::from(aggregate.conf), expo);
+    let ema_price = apply_expo(Decimal::from(price_account.ema_price.val), expo);
+    let publish_slot = aggregate.pub_slot;
     
     // Allow zero prices for now if valid, but typically liquidations rely on non-zero
     // Some feeds might momentarily be zero? Better to validate in caller.
-    // Logic kept consistent with previous implementation
     if final_price.is_sign_negative() {
          return Err(anyhow!("Invalid negative price from Pyth oracle: {}", final_price));
     }
     
-    Ok(final_price)
+    Ok(PythPrice {
+        price: final_price,
+        confidence,
+        ema_price,
+        publish_slot,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyth_sdk_solana::state::AccountType;
+    use rust_decimal_macros::dec;
+    
+    /// Solana price account size: header plus 32 publisher components
+    const PRICE_ACCOUNT_SIZE: usize = 3312;
+    
+    fn price_account(price: i64, conf: u64, expo: i32) -> SolanaAccount {
+        price_account_with_status(price, conf, expo, PriceStatus::Trading as u8)
+    }
+    
+    /// Pyth V2 price account: magic/version/type header, expo at 20, EMA price at 48,
+    /// aggregate price/conf/status/slot from 208
+    fn price_account_with_status(price: i64, conf: u64, expo: i32, status: u8) -> SolanaAccount {
+        let mut data = vec![0u8; PRICE_ACCOUNT_SIZE];
+        data[0..4].copy_from_slice(&pyth_sdk_solana::state::MAGIC.to_le_bytes());
+        data[4..8].copy_from_slice(&pyth_sdk_solana::state::VERSION_2.to_le_bytes());
+        data[8..12].copy_from_slice(&(AccountType::Price as u32).to_le_bytes());
+        data[12..16].copy_from_slice(&(PRICE_ACCOUNT_SIZE as u32).to_le_bytes());
+        data[20..24].copy_from_slice(&expo.to_le_bytes());
+        data[48..56].copy_from_slice(&(price - 50_000_000).to_le_bytes());
+        data[208..216].copy_from_slice(&price.to_le_bytes());
+        data[216..224].copy_from_slice(&conf.to_le_bytes());
+        data[224] = status;
+        data[232..240].copy_from_slice(&1234u64.to_le_bytes());
+        SolanaAccount {
+            data,
+            ..Default::default()
+        }
+    }
+    
+    #[test]
+    fn test_parse_price_and_confidence() {
+        let account = price_account(10_050_000_000, 5_000_000, -8);
+        let parsed = parse_price_from_account(&account).unwrap();
+        assert_eq!(parsed.price, dec!(100.5));
+        assert_eq!(parsed.confidence, dec!(0.05));
+        assert_eq!(parsed.publish_slot, 1234);
+        assert_eq!(parsed.ema_price, dec!(100.0));
+    }
+    
+    #[test]
+    fn test_confidence_ratio_threshold() {
+        // 1 +/- 0.03 is a 3% band
+        let account = price_account(100_000_000, 3_000_000, -8);
+        let parsed = parse_price_from_account(&account).unwrap();
+        assert!(parsed.check_confidence(0.02).is_err());
+        assert!(parsed.check_confidence(0.05).is_ok());
+    }
+    
+    #[test]
+    fn test_non_trading_status_rejected() {
+        // Halted
+        let account = price_account_with_status(10_050_000_000, 5_000_000, -8, 2);
+        let err = parse_price_from_account(&account).unwrap_err();
+        assert!(err.to_string().contains("status: 2, halted"));
+    }
+    
+    #[test]
+    fn test_parse_mainnet_sol_usd_layout() {
+        // Header and aggregate shaped like the mainnet SOL/USD feed (H6ARHf6YXhGYeQfUzQNGk6rDNnLBQKrenN712K4AQJEG)
+        let mut account = price_account(14_523_180_000, 7_914_000, -8);
+        let data = &mut account.data;
+        data[16..20].copy_from_slice(&1u32.to_le_bytes()); // ptype: price
+        data[24..28].copy_from_slice(&19u32.to_le_bytes()); // num publishers
+        data[32..40].copy_from_slice(&250_000_012u64.to_le_bytes()); // last_slot
+        data[40..48].copy_from_slice(&250_000_011u64.to_le_bytes()); // valid_slot
+        data[48..56].copy_from_slice(&14_498_730_000i64.to_le_bytes()); // EMA price
+        data[232..240].copy_from_slice(&250_000_011u64.to_le_bytes());
+        // Publisher components follow the aggregate and don't affect the result
+        data[240..].fill(0x5A);
+        
+        let parsed = parse_price_from_account(&account).unwrap();
+        assert_eq!(parsed.price, dec!(145.2318));
+        assert_eq!(parsed.confidence, dec!(0.07914));
+        assert_eq!(parsed.ema_price, dec!(144.9873));
+        assert_eq!(parsed.publish_slot, 250_000_011);
+    }
+    
+    /// Pull `PriceUpdateV2` account; `partial` stores a partially verified update
+    fn price_update_account(price: i64, conf: u64, exponent: i32, partial: bool) -> SolanaAccount {
+        let mut data = PRICE_UPDATE_V2_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        if partial {
+            data.extend_from_slice(&[0, 5]);
+        } else {
+            data.push(1);
+        }
+        data.extend_from_slice(&[7u8; 32]); // feed id
+        data.extend_from_slice(&price.to_le_bytes());
+        data.extend_from_slice(&conf.to_le_bytes());
+        data.extend_from_slice(&exponent.to_le_bytes());
+        data.extend_from_slice(&1_717_000_000i64.to_le_bytes()); // publish time
+        data.extend_from_slice(&1_716_999_999i64.to_le_bytes()); // previous publish time
+        data.extend_from_slice(&(price - 50_000_000).to_le_bytes()); // EMA price
+        data.extend_from_slice(&conf.to_le_bytes()); // EMA conf
+        data.extend_from_slice(&4321u64.to_le_bytes()); // posted slot
+        // Accounts are allocated for the larger `Partial` variant
+        data.resize(134, 0);
+        SolanaAccount {
+            data,
+            ..Default::default()
+        }
+    }
+    
+    #[test]
+    fn test_parse_pull_price_update() {
+        let parsed = parse_price_from_account(&price_update_account(10_050_000_000, 5_000_000, -8, false)).unwrap();
+        assert_eq!(parsed.price, dec!(100.5));
+        assert_eq!(parsed.confidence, dec!(0.05));
+        assert_eq!(parsed.ema_price, dec!(100.0));
+        assert_eq!(parsed.publish_slot, 4321);
+        
+        let err = parse_price_from_account(&price_update_account(10_050_000_000, 5_000_000, -8, true)).unwrap_err();
+        assert!(err.to_string().contains("partially verified"));
+        
+        let mut truncated = price_update_account(10_050_000_000, 5_000_000, -8, false);
+        truncated.data.truncate(100);
+        assert!(parse_price_from_account(&truncated).is_err());
+    }
+    
+    #[test]
+    fn test_rejects_non_price_accounts() {
+        let mut wrong_magic = price_account(10_050_000_000, 5_000_000, -8);
+        wrong_magic.data[0] ^= 0xFF;
+        assert!(parse_price_from_account(&wrong_magic).is_err());
+        
+        let mut product = price_account(10_050_000_000, 5_000_000, -8);
+        product.data[8..12].copy_from_slice(&(AccountType::Product as u32).to_le_bytes());
+        assert!(parse_price_from_account(&product).is_err());
+        
+        let mut truncated = price_account(10_050_000_000, 5_000_000, -8);
+        truncated.data.truncate(240);
+        assert!(parse_price_from_account(&truncated).is_err());
+    }
 }
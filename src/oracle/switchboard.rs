@@ -1,33 +1,104 @@
 use anyhow::{anyhow, Result};
 use rust_decimal::Decimal;
+use solana_sdk::account::Account as SolanaAccount;
 use solana_sdk::pubkey::Pubkey;
 use std::str::FromStr;
 
 use crate::rpc::SolendRpcClient;
 
-/// Fetch price from Switchboard oracle
-/// Note: This is a simplified implementation using switchboard-on-demand
-/// For production use, you may need to implement more sophisticated oracle handling
+/// Switchboard On-Demand values are fixed-point with 18 decimals
+pub const SWITCHBOARD_PRECISION: u32 = 18;
+
+/// Offset of `PullFeedAccountData.result` (8-byte discriminator + 2256 bytes of header)
+const RESULT_OFFSET: usize = 8 + 2256;
+
+/// `result.value` is the first field of `CurrentResult` (i128)
+const RESULT_VALUE_OFFSET: usize = RESULT_OFFSET;
+
+/// `result.slot` follows six i128 fields plus 8 bytes of counters/padding
+const RESULT_SLOT_OFFSET: usize = RESULT_OFFSET + 6 * 16 + 8;
+
+/// Fetch price from Switchboard On-Demand pull feed
 pub async fn fetch_switchboard_price(
     client: &SolendRpcClient,
     oracle_address: &str,
 ) -> Result<Decimal> {
     let pubkey = Pubkey::from_str(oracle_address)?;
     let account = client.get_account(&pubkey)?;
-    
-    // For now, return a placeholder error
-    // Full implementation would require understanding the specific Switchboard feed format
-    // and using the switchboard-on-demand crate's parsing functions
-    
-    log::warn!(
-        "Switchboard oracle support is basic. Oracle address: {}. Consider using Pyth oracles for production.",
-        oracle_address
-    );
-    
-    // Attempt basic parsing - this is a simplified approach
-    // In production, you'd use switchboard_on_demand::PullFeedAccountData or similar
-    Err(anyhow!(
-        "Switchboard oracle parsing not fully implemented. Please use Pyth oracles for now. Oracle: {}",
-        oracle_address
-    ))
+    Ok(parse_price_from_account(&account)?.price)
+}
+
+/// Price parsed from a Switchboard pull feed
+#[derive(Debug, Clone, Copy)]
+pub struct SwitchboardPrice {
+    pub price: Decimal,
+    /// Slot the result was last updated in
+    pub slot: u64,
+}
+
+/// Parse price from Switchboard `PullFeedAccountData` account data
+pub fn parse_price_from_account(account: &SolanaAccount) -> Result<SwitchboardPrice> {
+    if account.data.len() < RESULT_SLOT_OFFSET + 8 {
+        return Err(anyhow!("Invalid Switchboard account data size"));
+    }
+
+    let value_bytes = &account.data[RESULT_VALUE_OFFSET..RESULT_VALUE_OFFSET + 16];
+    let slot_bytes = &account.data[RESULT_SLOT_OFFSET..RESULT_SLOT_OFFSET + 8];
+
+    let value = i128::from_le_bytes(value_bytes.try_into()?);
+    let slot = u64::from_le_bytes(slot_bytes.try_into()?);
+
+    // A feed that has never been updated has an all-zero result
+    if slot == 0 && value == 0 {
+        return Err(anyhow!("Switchboard feed has never been updated"));
+    }
+
+    let price = Decimal::try_from_i128_with_scale(value, SWITCHBOARD_PRECISION)
+        .map_err(|e| anyhow!("Switchboard value out of range: {}", e))?;
+
+    if price.is_sign_negative() {
+        return Err(anyhow!("Invalid negative price from Switchboard oracle: {}", price));
+    }
+
+    Ok(SwitchboardPrice { price, slot })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn feed_account(value: i128, slot: u64) -> SolanaAccount {
+        let mut data = vec![0u8; 3208];
+        data[RESULT_VALUE_OFFSET..RESULT_VALUE_OFFSET + 16].copy_from_slice(&value.to_le_bytes());
+        data[RESULT_SLOT_OFFSET..RESULT_SLOT_OFFSET + 8].copy_from_slice(&slot.to_le_bytes());
+        SolanaAccount {
+            data,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_switchboard_price() {
+        // 142.5 with 18 decimals
+        let account = feed_account(142_500_000_000_000_000_000, 1000);
+        let parsed = parse_price_from_account(&account).unwrap();
+        assert_eq!(parsed.price, dec!(142.5));
+        assert_eq!(parsed.slot, 1000);
+    }
+
+    #[test]
+    fn test_parse_switchboard_never_updated() {
+        let account = feed_account(0, 0);
+        assert!(parse_price_from_account(&account).is_err());
+    }
+
+    #[test]
+    fn test_parse_switchboard_too_small() {
+        let account = SolanaAccount {
+            data: vec![0u8; 100],
+            ..Default::default()
+        };
+        assert!(parse_price_from_account(&account).is_err());
+    }
 }
@@ -9,8 +9,35 @@ const MIN_PRICE: f64 = 0.000001;
 /// Maximum reasonable price (to catch overflow/corrupted data)
 const MAX_PRICE: f64 = 1_000_000_000.0;
 
-/// Maximum slot age before price is considered stale (approximately 2 minutes at 400ms/slot)
-const MAX_SLOT_AGE: u64 = 300;
+/// Default maximum Pyth/Switchboard divergence before a token is untrusted
+pub const DEFAULT_ORACLE_TOLERANCE_PERCENT: f64 = 5.0;
+
+/// Default maximum slot age before price is considered stale (approximately 2 minutes at 400ms/slot)
+pub const DEFAULT_MAX_SLOT_AGE: u64 = 300;
+
+/// Maximum oracle slot age per token symbol, with a fallback
+/// Feeds that publish rarely (e.g. some LSTs) need a looser bound
+#[derive(Debug, Clone)]
+pub struct StalenessConfig {
+    pub default_slots: u64,
+    pub per_token: HashMap<String, u64>,
+}
+
+impl StalenessConfig {
+    /// Slots a price for `symbol` may lag the current slot
+    pub fn max_age_for(&self, symbol: &str) -> u64 {
+        self.per_token.get(symbol).copied().unwrap_or(self.default_slots)
+    }
+}
+
+impl Default for StalenessConfig {
+    fn default() -> Self {
+        Self {
+            default_slots: DEFAULT_MAX_SLOT_AGE,
+            per_token: HashMap::new(),
+        }
+    }
+}
 
 /// Oracle price validation result
 #[derive(Debug, Clone)]
@@ -20,11 +47,13 @@ pub struct PriceValidation {
 }
 
 /// Validate a single oracle price
+/// Prices published more than `max_slot_age` slots before `current_slot` are stale
 pub fn validate_price(
     symbol: &str,
     price: Decimal,
     slot: u64,
     current_slot: u64,
+    max_slot_age: u64,
 ) -> PriceValidation {
     let mut warnings = Vec::new();
     let mut is_valid = true;
@@ -50,6 +79,7 @@ pub fn validate_price(
             symbol, price, MIN_PRICE
         ));
         warn!("⚠️  {}", warnings.last().unwrap());
+        is_valid = false;
     }
     
     if price_f64 > MAX_PRICE {
@@ -58,39 +88,44 @@ pub fn validate_price(
             symbol, price, MAX_PRICE
         ));
         warn!("⚠️  {}", warnings.last().unwrap());
+        is_valid = false;
     }
     
     // Check for stale price
     let slot_age = current_slot.saturating_sub(slot);
-    if slot_age > MAX_SLOT_AGE {
+    if slot_age > max_slot_age {
         warnings.push(format!(
             "{}: Price may be stale (slot age: {}, max: {})",
-            symbol, slot_age, MAX_SLOT_AGE
+            symbol, slot_age, max_slot_age
         ));
         warn!("⚠️  {}", warnings.last().unwrap());
+        is_valid = false;
     }
     
     PriceValidation { is_valid, warnings }
 }
 
 /// Validate all oracle prices and log warnings
+/// Returns the warnings of every price that failed, keyed by symbol
 pub fn validate_oracle_prices(
     prices: &HashMap<String, (Decimal, u64)>,
     current_slot: u64,
-) -> Result<()> {
+    staleness: &StalenessConfig,
+) -> HashMap<String, Vec<String>> {
     let mut total_warnings = 0;
-    let mut invalid_count = 0;
+    let mut invalid = HashMap::new();
     
     for (symbol, (price, slot)) in prices {
-        let validation = validate_price(symbol, *price, *slot, current_slot);
+        let validation = validate_price(symbol, *price, *slot, current_slot, staleness.max_age_for(symbol));
+        
+        total_warnings += validation.warnings.len();
         
         if !validation.is_valid {
-            invalid_count += 1;
+            invalid.insert(symbol.clone(), validation.warnings);
         }
-        
-        total_warnings += validation.warnings.len();
     }
     
+    let invalid_count = invalid.len();
     if invalid_count > 0 {
         warn!(
             "⚠️  {} oracle prices failed validation (total warnings: {})",
@@ -105,10 +140,11 @@ pub fn validate_oracle_prices(
         info!("✓ All oracle prices validated successfully");
     }
     
-    Ok(())
+    invalid
 }
 
 /// Compare prices from different oracle sources
+/// Errors when they diverge by more than `tolerance_percent`
 pub fn compare_oracle_sources(
     symbol: &str,
     pyth_price: Option<Decimal>,
@@ -126,6 +162,10 @@ pub fn compare_oracle_sources(
                 "⚠️  {}: Large price discrepancy between oracles (Pyth: {}, Switchboard: {}, diff: {:.2}%)",
                 symbol, pyth, sb, diff_percent
             );
+            return Err(anyhow!(
+                "Pyth {} and Switchboard {} diverge by {:.2}% (tolerance {}%)",
+                pyth, sb, diff_percent, tolerance_percent
+            ));
         }
     }
     
@@ -139,21 +179,21 @@ mod tests {
     
     #[test]
     fn test_validate_price_valid() {
-        let validation = validate_price("SOL", dec!(100.50), 1000, 1100);
+        let validation = validate_price("SOL", dec!(100.50), 1000, 1100, DEFAULT_MAX_SLOT_AGE);
         assert!(validation.is_valid);
         assert!(validation.warnings.is_empty());
     }
     
     #[test]
     fn test_validate_price_zero() {
-        let validation = validate_price("SOL", dec!(0.0), 1000, 1100);
+        let validation = validate_price("SOL", dec!(0.0), 1000, 1100, DEFAULT_MAX_SLOT_AGE);
         assert!(!validation.is_valid);
         assert!(!validation.warnings.is_empty());
     }
     
     #[test]
     fn test_validate_price_negative() {
-        let validation = validate_price("SOL", dec!(-10.0), 1000, 1100);
+        let validation = validate_price("SOL", dec!(-10.0), 1000, 1100, DEFAULT_MAX_SLOT_AGE);
         assert!(!validation.is_valid);
         assert!(!validation.warnings.is_empty());
     }
@@ -161,19 +201,50 @@ mod tests {
     #[test]
     fn test_price_staleness() {
         // Price is 400 slots old (stale)
-        let validation = validate_price("SOL", dec!(100.0), 1000, 1400);
+        let validation = validate_price("SOL", dec!(100.0), 1000, 1400, DEFAULT_MAX_SLOT_AGE);
+        assert!(!validation.is_valid);
         assert!(!validation.warnings.is_empty());
+        
+        // Within a looser bound
+        assert!(validate_price("SOL", dec!(100.0), 1000, 1400, 500).is_valid);
+    }
+    
+    #[test]
+    fn test_validate_oracle_prices_returns_invalid() {
+        let prices: HashMap<String, (Decimal, u64)> = [
+            ("SOL".to_string(), (dec!(100.0), 1000)),
+            ("USDC".to_string(), (dec!(1.0), 500)),
+        ].into_iter().collect();
+        
+        let invalid = validate_oracle_prices(&prices, 1100, &StalenessConfig::default());
+        assert_eq!(invalid.len(), 1);
+        assert!(invalid["USDC"][0].contains("stale"));
+        
+        // A per-token override lets the slow feed through
+        let staleness = StalenessConfig {
+            default_slots: DEFAULT_MAX_SLOT_AGE,
+            per_token: HashMap::from([("USDC".to_string(), 1_000)]),
+        };
+        assert!(validate_oracle_prices(&prices, 1100, &staleness).is_empty());
+    }
+    
+    #[test]
+    fn test_compare_oracle_sources() {
+        assert!(compare_oracle_sources("SOL", Some(dec!(100)), Some(dec!(102)), 5.0).is_ok());
+        assert!(compare_oracle_sources("SOL", Some(dec!(100)), Some(dec!(110)), 5.0).is_err());
+        // Nothing to compare against
+        assert!(compare_oracle_sources("SOL", Some(dec!(100)), None, 5.0).is_ok());
     }
     
     #[test]
     fn test_price_too_low() {
-        let validation = validate_price("SOL", dec!(0.0000001), 1000, 1100);
+        let validation = validate_price("SOL", dec!(0.0000001), 1000, 1100, DEFAULT_MAX_SLOT_AGE);
         assert!(!validation.warnings.is_empty());
     }
     
     #[test]
     fn test_price_too_high() {
-        let validation = validate_price("SOL", dec!(2000000000.0), 1000, 1100);
+        let validation = validate_price("SOL", dec!(2000000000.0), 1000, 1100, DEFAULT_MAX_SLOT_AGE);
         assert!(!validation.warnings.is_empty());
     }
 }
@@ -0,0 +1,484 @@
+//! Offline replay of liquidation decisions over recorded account snapshots
+//! Runs the same refresh and selection path as a live epoch, minus wallet balances and swap quotes
+
+use anyhow::{anyhow, Result};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use solana_account_decoder::UiAccount;
+use solana_client::rpc_response::RpcKeyedAccount;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::config::{load_markets_file, Config};
+use crate::liquidation::{calculate_refreshed_obligation, max_repay_for_available, seized_collateral_amount};
+use crate::models::obligation::OBLIGATION_SIZE;
+use crate::models::reserve::RESERVE_SIZE;
+use crate::models::{MarketConfig, Obligation, Reserve};
+use crate::{oracle, utils};
+
+/// Optional markets file in a snapshot directory, in the Solend API's format
+pub const MARKETS_FILE: &str = "markets.json";
+
+/// One recorded account, as written by `solana account --output json` or returned by `getAccountInfo`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AccountDump {
+    Keyed(RpcKeyedAccount),
+    /// Keyed by the file name instead
+    Bare(UiAccount),
+}
+
+/// Accounts recorded from mainnet, keyed by pubkey
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    pub accounts: HashMap<Pubkey, Account>,
+    /// Markets recorded alongside the accounts, if any
+    pub markets: Option<Vec<MarketConfig>>,
+}
+
+impl Snapshot {
+    /// Load every `*.json` account dump in `dir`, plus `markets.json` if present
+    pub fn load(dir: &Path) -> Result<Self> {
+        let mut snapshot = Snapshot::default();
+        let entries = std::fs::read_dir(dir).map_err(|e| anyhow!("Failed to read {}: {}", dir.display(), e))?;
+
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+
+            if path.file_name().is_some_and(|name| name == MARKETS_FILE) {
+                snapshot.markets = Some(load_markets_file(&path.to_string_lossy())?);
+                continue;
+            }
+
+            let contents = std::fs::read_to_string(&path)
+                .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+            let file_stem = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+            let (pubkey, account) = parse_account_dump(&contents, &file_stem)
+                .map_err(|e| anyhow!("Invalid account dump {}: {}", path.display(), e))?;
+            snapshot.accounts.insert(pubkey, account);
+        }
+
+        if snapshot.accounts.is_empty() {
+            return Err(anyhow!("No account dumps in {}", dir.display()));
+        }
+        Ok(snapshot)
+    }
+
+    /// Reserves of `market` present in the snapshot
+    pub fn reserves(&self, market: &MarketConfig) -> HashMap<Pubkey, Reserve> {
+        market
+            .reserves
+            .iter()
+            .filter_map(|reserve| {
+                let pubkey = Pubkey::from_str(&reserve.address).ok()?;
+                let account = self.accounts.get(&pubkey)?;
+                match Reserve::parse(&account.data) {
+                    Ok(parsed) => Some((pubkey, parsed)),
+                    Err(e) => {
+                        log::warn!("Failed to parse reserve {}: {}", pubkey, e);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Initialized obligations of the lending market `market`, in pubkey order
+    pub fn obligations(&self, market: &Pubkey) -> Vec<(Pubkey, Obligation)> {
+        let mut obligations: Vec<_> = self
+            .accounts
+            .iter()
+            .filter(|(_, account)| account.data.len() == OBLIGATION_SIZE)
+            .filter_map(|(pubkey, account)| match Obligation::parse_detailed(&account.data) {
+                Ok(obligation) => Some((*pubkey, obligation)),
+                Err(e) => {
+                    log::warn!("Failed to parse obligation {}: {}", pubkey, e);
+                    None
+                }
+            })
+            .filter(|(_, obligation)| obligation.lending_market == *market && !obligation.last_update.is_zero())
+            .collect();
+        obligations.sort_by_key(|(pubkey, _)| *pubkey);
+        obligations
+    }
+
+    /// Latest slot any reserve or obligation in the snapshot was refreshed at
+    /// Stands in for the current slot when judging oracle and obligation staleness
+    pub fn latest_slot(&self) -> u64 {
+        self.accounts
+            .values()
+            .filter_map(|account| match account.data.len() {
+                OBLIGATION_SIZE => Obligation::parse(&account.data).ok().map(|o| o.last_update.slot),
+                RESERVE_SIZE => Reserve::parse(&account.data).ok().map(|r| r.last_update.slot),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Decode one account dump; bare accounts take their pubkey from `file_stem`
+fn parse_account_dump(contents: &str, file_stem: &str) -> Result<(Pubkey, Account)> {
+    let (pubkey, account) = match serde_json::from_str::<AccountDump>(contents)? {
+        AccountDump::Keyed(keyed) => (keyed.pubkey, keyed.account),
+        AccountDump::Bare(account) => (file_stem.to_string(), account),
+    };
+    let pubkey = Pubkey::from_str(&pubkey).map_err(|e| anyhow!("Invalid pubkey {}: {}", pubkey, e))?;
+    let account = account
+        .decode::<Account>()
+        .ok_or_else(|| anyhow!("Account data must be base64 or base58 encoded"))?;
+    Ok((pubkey, account))
+}
+
+/// What the bot would do with a liquidatable obligation
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayAction {
+    /// Amounts are in token units; repay assumes an unlimited wallet
+    Liquidate {
+        repay_symbol: String,
+        repay_amount: Decimal,
+        withdraw_symbol: String,
+        seized_amount: Decimal,
+    },
+    Skip(String),
+}
+
+/// Decision for one liquidatable obligation
+#[derive(Debug, Clone)]
+pub struct ReplayDecision {
+    pub obligation: Pubkey,
+    pub market: String,
+    pub borrowed_value: Decimal,
+    pub health_factor: Decimal,
+    pub action: ReplayAction,
+}
+
+/// Decide on every liquidatable obligation of `market` in the snapshot, most lucrative first
+/// Applies the live filters (margin, owners, dust, untrusted oracles, token allowlists,
+/// reserve liquidity); profit checks need swap quotes and are left out
+pub fn replay_market(
+    snapshot: &Snapshot,
+    market: &MarketConfig,
+    config: &Config,
+    current_slot: u64,
+) -> Result<Vec<ReplayDecision>> {
+    let market_pubkey = Pubkey::from_str(&market.address)?;
+    let reserves = snapshot.reserves(market);
+    let (oracle_data, _) = oracle::oracle_data_from_accounts(
+        market,
+        &snapshot.accounts,
+        config.max_conf_ratio,
+        config.oracle_tolerance_percent,
+        &config.oracle_staleness,
+        current_slot,
+    );
+
+    let mut candidates = Vec::new();
+    for (pubkey, mut obligation) in snapshot.obligations(&market_pubkey) {
+        if config.max_obligation_slot_age > 0 && obligation.is_stale(current_slot, config.max_obligation_slot_age) {
+            obligation.accrue_interest(&reserves);
+        }
+
+        let refreshed = match calculate_refreshed_obligation(&obligation, &reserves, &oracle_data, config.use_ema_for_health) {
+            Ok(refreshed) => refreshed,
+            Err(e) => {
+                log::debug!("[{}] Cannot refresh obligation {}: {}", market.name, pubkey, e);
+                continue;
+            }
+        };
+        if refreshed.is_liquidatable(config.liquidation_margin) {
+            candidates.push((pubkey, obligation, refreshed));
+        }
+    }
+    candidates.sort_by_cached_key(|(_, _, refreshed)| std::cmp::Reverse(refreshed.liquidation_priority(&reserves)));
+
+    let decisions = candidates
+        .into_iter()
+        .map(|(pubkey, obligation, refreshed)| {
+            let action = if !config.is_owner_allowed(&obligation.owner) {
+                ReplayAction::Skip("owner excluded by filters".to_string())
+            } else if refreshed.borrowed_value.to_f64().unwrap_or(0.0) < config.min_borrowed_value_usd {
+                ReplayAction::Skip(format!("dust: borrowing under ${:.2}", config.min_borrowed_value_usd))
+            } else if let Some(symbol) = refreshed.untrusted_symbol(&oracle_data) {
+                ReplayAction::Skip(format!("{} oracles disagree", symbol))
+            } else {
+                match refreshed.select_best_liquidation_pair(&reserves) {
+                    None => ReplayAction::Skip("no liquidation pair with a known reserve".to_string()),
+                    Some((borrow, deposit)) if !config.is_pair_allowed(&borrow.symbol, &deposit.symbol) => {
+                        ReplayAction::Skip(format!("{}/{} not in the token allowlists", borrow.symbol, deposit.symbol))
+                    }
+                    Some((borrow, deposit)) => {
+                        let decimals = |reserve: &str| {
+                            Pubkey::from_str(reserve)
+                                .ok()
+                                .and_then(|pk| reserves.get(&pk))
+                                .map(|r| r.liquidity.mint_decimals)
+                                .unwrap_or(0)
+                        };
+                        let liquidation_bonus = Pubkey::from_str(&deposit.deposit_reserve)
+                            .ok()
+                            .and_then(|pk| reserves.get(&pk))
+                            .map(|r| r.config.liquidation_bonus)
+                            .unwrap_or(0);
+
+                        // A thinly funded withdraw reserve gets a smaller repay, as live
+                        let available = deposit.available_liquidity(&reserves).unwrap_or(0);
+                        let repay_amount = borrow
+                            .capped_repay_amount(u64::MAX, config.close_factor)
+                            .min(max_repay_for_available(borrow, deposit, available, liquidation_bonus));
+                        let seized_amount = seized_collateral_amount(borrow, deposit, repay_amount, liquidation_bonus);
+
+                        if repay_amount == 0 || seized_amount > available {
+                            ReplayAction::Skip(format!(
+                                "{} reserve has {} available, too little to redeem",
+                                deposit.symbol, available
+                            ))
+                        } else {
+                            ReplayAction::Liquidate {
+                                repay_symbol: borrow.symbol.clone(),
+                                repay_amount: utils::to_human(repay_amount, decimals(&borrow.borrow_reserve)),
+                                withdraw_symbol: deposit.symbol.clone(),
+                                seized_amount: utils::to_human(seized_amount, decimals(&deposit.deposit_reserve)),
+                            }
+                        }
+                    }
+                }
+            };
+
+            ReplayDecision {
+                obligation: pubkey,
+                market: market.name.clone(),
+                borrowed_value: refreshed.borrowed_value,
+                health_factor: refreshed.health_factor(),
+                action,
+            }
+        })
+        .collect();
+
+    Ok(decisions)
+}
+
+/// Table of replay decisions, in the order given
+pub fn render_replay(decisions: &[ReplayDecision]) -> String {
+    let liquidations = decisions
+        .iter()
+        .filter(|d| matches!(d.action, ReplayAction::Liquidate { .. }))
+        .count();
+    let mut lines = vec![
+        format!("{} liquidatable obligations, {} would be liquidated", decisions.len(), liquidations),
+        format!(
+            "{:<44} {:<12} {:>14} {:>8}  {}",
+            "Obligation", "Market", "Borrowed", "Health", "Decision"
+        ),
+    ];
+    for decision in decisions {
+        let action = match &decision.action {
+            ReplayAction::Liquidate { repay_symbol, repay_amount, withdraw_symbol, seized_amount } => format!(
+                "liquidate: repay {} {}, seize {} {}",
+                repay_amount.normalize(),
+                repay_symbol,
+                seized_amount.normalize(),
+                withdraw_symbol
+            ),
+            ReplayAction::Skip(reason) => format!("skip: {}", reason),
+        };
+        lines.push(format!(
+            "{:<44} {:<12} {:>14} {:>8}  {}",
+            decision.obligation.to_string(),
+            decision.market,
+            format!("${:.2}", decision.borrowed_value.round_dp(2)),
+            format!("{:.4}", decision.health_factor.round_dp(4)),
+            action
+        ));
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::obligation::LENDING_MARKET_OFFSET;
+    use base64::Engine;
+    use serde_json::json;
+
+    fn ui_account(data: &[u8]) -> serde_json::Value {
+        json!({
+            "data": [base64::engine::general_purpose::STANDARD.encode(data), "base64"],
+            "executable": false,
+            "lamports": 1_000_000,
+            "owner": Pubkey::new_unique().to_string(),
+            "rentEpoch": 0,
+            "space": data.len(),
+        })
+    }
+
+    #[test]
+    fn test_parse_account_dump_formats() {
+        let pubkey = Pubkey::new_unique();
+
+        // `solana account --output json`
+        let keyed = json!({ "pubkey": pubkey.to_string(), "account": ui_account(&[1, 2, 3]) }).to_string();
+        let (parsed, account) = parse_account_dump(&keyed, "ignored").unwrap();
+        assert_eq!((parsed, account.data), (pubkey, vec![1, 2, 3]));
+
+        // Bare account named after its pubkey
+        let bare = ui_account(&[4, 5]).to_string();
+        let (parsed, account) = parse_account_dump(&bare, &pubkey.to_string()).unwrap();
+        assert_eq!((parsed, account.data), (pubkey, vec![4, 5]));
+        assert!(parse_account_dump(&bare, "not-a-pubkey").is_err());
+        assert!(parse_account_dump("{}", &pubkey.to_string()).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_load_and_filters() {
+        let dir = std::env::temp_dir().join(format!("replay-snapshot-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let market = Pubkey::new_unique();
+        let obligation_account = |lending_market: &Pubkey, slot: u64| {
+            let mut data = vec![0u8; OBLIGATION_SIZE];
+            data[0] = 1;
+            data[1..9].copy_from_slice(&slot.to_le_bytes());
+            data[LENDING_MARKET_OFFSET..LENDING_MARKET_OFFSET + 32].copy_from_slice(lending_market.as_ref());
+            data
+        };
+        let (live, other_market, uninitialized) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        for (pubkey, data) in [
+            (live, obligation_account(&market, 300)),
+            (other_market, obligation_account(&Pubkey::new_unique(), 500)),
+            (uninitialized, obligation_account(&market, 0)),
+        ] {
+            std::fs::write(dir.join(format!("{}.json", pubkey)), ui_account(&data).to_string()).unwrap();
+        }
+        std::fs::write(dir.join("notes.txt"), "not an account").unwrap();
+
+        let snapshot = Snapshot::load(&dir).unwrap();
+        assert_eq!(snapshot.accounts.len(), 3);
+        assert!(snapshot.markets.is_none());
+        assert_eq!(snapshot.latest_slot(), 500);
+
+        let obligations = snapshot.obligations(&market);
+        assert_eq!(obligations.len(), 1);
+        assert_eq!(obligations[0].0, live);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(Snapshot::load(&dir).is_err());
+    }
+
+    /// Legacy Pyth price account trading at `price` (expo -8), published at `slot`
+    fn pyth_account(price: i64, slot: u64) -> Account {
+        use pyth_sdk_solana::state::{AccountType, PriceStatus, MAGIC, VERSION_2};
+
+        const PRICE_ACCOUNT_SIZE: usize = 3312;
+        let mut data = vec![0u8; PRICE_ACCOUNT_SIZE];
+        data[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        data[4..8].copy_from_slice(&VERSION_2.to_le_bytes());
+        data[8..12].copy_from_slice(&(AccountType::Price as u32).to_le_bytes());
+        data[12..16].copy_from_slice(&(PRICE_ACCOUNT_SIZE as u32).to_le_bytes());
+        data[20..24].copy_from_slice(&(-8i32).to_le_bytes());
+        data[48..56].copy_from_slice(&price.to_le_bytes());
+        data[208..216].copy_from_slice(&price.to_le_bytes());
+        data[216..224].copy_from_slice(&(price as u64 / 1_000).to_le_bytes());
+        data[224] = PriceStatus::Trading as u8;
+        data[232..240].copy_from_slice(&slot.to_le_bytes());
+        Account { data, ..Default::default() }
+    }
+
+    #[test]
+    fn test_replay_market_liquidates_only_unhealthy_obligations() {
+        use crate::models::{LastUpdate, ObligationCollateral, ObligationLiquidity};
+        use crate::utils::WAD;
+        use rust_decimal_macros::dec;
+
+        let slot = 1_000;
+        let key = || Pubkey::new_unique().to_string();
+        let reserve = |symbol: &str, decimals: u8| json!({
+            "liquidityToken": {
+                "coingeckoId": "", "decimals": decimals, "logo": "", "mint": key(), "name": symbol,
+                "symbol": symbol, "volume24h": "",
+            },
+            "pythOracle": key(), "switchboardOracle": oracle::NULL_ORACLE, "address": key(),
+            "collateralMintAddress": key(), "collateralSupplyAddress": key(), "liquidityAddress": key(),
+            "liquidityFeeReceiverAddress": key(), "userSupplyCap": 0,
+        });
+        let market: MarketConfig = serde_json::from_value(json!({
+            "name": "main", "isPrimary": true, "description": "", "creator": "", "address": key(),
+            "authorityAddress": key(), "owner": "", "reserves": [reserve("USDC", 6), reserve("SOL", 9)],
+        }))
+        .unwrap();
+        let market_pubkey = Pubkey::from_str(&market.address).unwrap();
+        let pubkey = |address: &str| Pubkey::from_str(address).unwrap();
+
+        let mut snapshot = Snapshot::default();
+        for (config_reserve, price, available) in [
+            (market.find_reserve("USDC").unwrap(), 100_000_000, 10_000_000_000),
+            (market.find_reserve("SOL").unwrap(), 10_000_000_000, 20_000_000_000),
+        ] {
+            // No borrows and as much collateral as liquidity: one collateral token per token
+            let mut reserve = Reserve::test_reserve(available, 0);
+            reserve.last_update.slot = slot;
+            reserve.lending_market = market_pubkey;
+            reserve.liquidity.mint_pubkey = pubkey(&config_reserve.liquidity_token.mint);
+            reserve.liquidity.mint_decimals = config_reserve.liquidity_token.decimals;
+            reserve.collateral.mint_total_supply = available;
+            snapshot.accounts.insert(
+                pubkey(&config_reserve.address),
+                Account { data: reserve.pack(), ..Default::default() },
+            );
+            snapshot.accounts.insert(pubkey(&config_reserve.pyth_oracle), pyth_account(price, slot));
+        }
+
+        // 10 SOL at $100 against an 80% threshold allows $800 of borrows
+        let obligation = |borrowed_usdc: u128| Obligation {
+            version: 1,
+            last_update: LastUpdate { slot, stale: false },
+            lending_market: market_pubkey,
+            owner: Pubkey::new_unique(),
+            deposited_value: 0,
+            borrowed_value: 0,
+            allowed_borrow_value: 0,
+            unhealthy_borrow_value: 0,
+            deposits: vec![ObligationCollateral {
+                deposit_reserve: pubkey(&market.find_reserve("SOL").unwrap().address),
+                deposited_amount: 10_000_000_000,
+                market_value: 0,
+            }],
+            borrows: vec![ObligationLiquidity {
+                borrow_reserve: pubkey(&market.find_reserve("USDC").unwrap().address),
+                cumulative_borrow_rate_wads: WAD,
+                borrowed_amount_wads: borrowed_usdc * 1_000_000 * WAD,
+                market_value: 0,
+            }],
+        };
+        let (unhealthy, healthy) = (Pubkey::new_unique(), Pubkey::new_unique());
+        snapshot.accounts.insert(unhealthy, Account { data: obligation(900).pack(), ..Default::default() });
+        snapshot.accounts.insert(healthy, Account { data: obligation(500).pack(), ..Default::default() });
+
+        std::env::set_var("RPC_ENDPOINT", "http://127.0.0.1:8899");
+        let config = Config::from_env().unwrap();
+        let decisions = replay_market(&snapshot, &market, &config, snapshot.latest_slot()).unwrap();
+
+        // The healthy obligation is not a candidate at all
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0].obligation, unhealthy);
+        assert_eq!(decisions[0].health_factor, dec!(1.125));
+        // A 20% close factor repays $180 and seizes $189 of SOL with the 5% bonus
+        assert_eq!(
+            decisions[0].action,
+            ReplayAction::Liquidate {
+                repay_symbol: "USDC".to_string(),
+                repay_amount: dec!(180),
+                withdraw_symbol: "SOL".to_string(),
+                seized_amount: dec!(1.89),
+            }
+        );
+    }
+}
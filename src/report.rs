@@ -0,0 +1,327 @@
+use log::info;
+use rust_decimal::Decimal;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+
+/// A liquidation that passed simulation in dry-run mode
+#[derive(Debug, Clone)]
+pub struct DryRunLiquidation {
+    pub market: String,
+    pub obligation: String,
+    pub repay_symbol: String,
+    /// Repay amount in token units
+    pub repay_amount: Decimal,
+    pub withdraw_symbol: String,
+    pub profit_usd: Decimal,
+}
+
+/// An unhealthy obligation the wallet couldn't cover
+#[derive(Debug, Clone)]
+pub struct DryRunSkip {
+    pub market: String,
+    pub obligation: String,
+    pub repay_symbol: String,
+    /// Largest repay allowed by the close factor, in token units
+    pub required: Decimal,
+    /// Wallet balance in token units
+    pub available: Decimal,
+}
+
+/// An unhealthy obligation found by `--scan`
+#[derive(Debug, Clone)]
+pub struct ScanRow {
+    pub obligation: String,
+    pub market: String,
+    pub borrowed_value: Decimal,
+    pub unhealthy_borrow_value: Decimal,
+    pub health_factor: Decimal,
+    /// Best (repay, withdraw) symbols, if any pair has a known reserve
+    pub pair: Option<(String, String)>,
+    /// Liquidation bonus on the best pair's seizable value, in USD
+    pub bonus_value: Decimal,
+}
+
+/// Table of unhealthy obligations, most lucrative first
+pub fn render_scan(rows: &[ScanRow]) -> String {
+    let mut rows: Vec<&ScanRow> = rows.iter().collect();
+    rows.sort_by_key(|row| std::cmp::Reverse(row.bonus_value));
+
+    let mut lines = vec![
+        format!("{} unhealthy obligations", rows.len()),
+        format!(
+            "{:<44} {:<12} {:>14} {:>14} {:>8} {:<16} {:>12}",
+            "Obligation", "Market", "Borrowed", "Threshold", "Health", "Repay/Withdraw", "Bonus value"
+        ),
+    ];
+    for row in rows {
+        let pair = match &row.pair {
+            Some((repay, withdraw)) => format!("{}/{}", repay, withdraw),
+            None => "-".to_string(),
+        };
+        lines.push(format!(
+            "{:<44} {:<12} {:>14} {:>14} {:>8} {:<16} {:>12}",
+            row.obligation,
+            row.market,
+            format!("${:.2}", row.borrowed_value.round_dp(2)),
+            format!("${:.2}", row.unhealthy_borrow_value.round_dp(2)),
+            format!("{:.4}", row.health_factor.round_dp(4)),
+            pair,
+            format!("${:.2}", row.bonus_value.round_dp(2))
+        ));
+    }
+
+    lines.join("\n")
+}
+
+/// Obligations found unhealthy in the previous epoch, so only changes need logging
+#[derive(Debug, Default)]
+pub struct UnhealthySet {
+    previous: HashSet<Pubkey>,
+}
+
+/// How this epoch's unhealthy obligations differ from the previous epoch's
+#[derive(Debug, Default, PartialEq)]
+pub struct UnhealthyDiff {
+    pub new: Vec<Pubkey>,
+    pub recovered: Vec<Pubkey>,
+    pub unchanged: Vec<Pubkey>,
+}
+
+impl UnhealthySet {
+    /// Replace the remembered set with `current`, returning what changed (sorted)
+    pub fn update(&mut self, current: impl IntoIterator<Item = Pubkey>) -> UnhealthyDiff {
+        let current: HashSet<Pubkey> = current.into_iter().collect();
+        
+        let mut diff = UnhealthyDiff {
+            new: current.difference(&self.previous).copied().collect(),
+            recovered: self.previous.difference(&current).copied().collect(),
+            unchanged: current.intersection(&self.previous).copied().collect(),
+        };
+        diff.new.sort();
+        diff.recovered.sort();
+        diff.unchanged.sort();
+        
+        self.previous = current;
+        diff
+    }
+}
+
+/// What an epoch would have liquidated, collected in dry-run mode
+#[derive(Debug, Clone, Default)]
+pub struct DryRunReport {
+    pub liquidations: Vec<DryRunLiquidation>,
+    pub insufficient_balance: Vec<DryRunSkip>,
+}
+
+impl DryRunReport {
+    pub fn is_empty(&self) -> bool {
+        self.liquidations.is_empty() && self.insufficient_balance.is_empty()
+    }
+
+    /// Fold another market's report into this one
+    pub fn merge(&mut self, other: DryRunReport) {
+        self.liquidations.extend(other.liquidations);
+        self.insufficient_balance.extend(other.insufficient_balance);
+    }
+
+    pub fn total_profit_usd(&self) -> Decimal {
+        self.liquidations.iter().map(|l| l.profit_usd).sum()
+    }
+
+    /// Table of would-be liquidations followed by balance shortfalls
+    pub fn render(&self) -> String {
+        let mut lines = vec![format!(
+            "DRY-RUN REPORT: {} would liquidate (est. profit ${:.2}), {} skipped for balance",
+            self.liquidations.len(),
+            self.total_profit_usd().round_dp(2),
+            self.insufficient_balance.len()
+        )];
+
+        if !self.liquidations.is_empty() {
+            lines.push(format!(
+                "  {:<12} {:<44} {:>18} {:<8} {:<8} {:>12}",
+                "Market", "Obligation", "Repay", "", "Withdraw", "Est. profit"
+            ));
+            for l in &self.liquidations {
+                lines.push(format!(
+                    "  {:<12} {:<44} {:>18} {:<8} {:<8} {:>12}",
+                    l.market,
+                    l.obligation,
+                    l.repay_amount.normalize(),
+                    l.repay_symbol,
+                    l.withdraw_symbol,
+                    format!("${:.2}", l.profit_usd.round_dp(2))
+                ));
+            }
+        }
+
+        if !self.insufficient_balance.is_empty() {
+            lines.push("  Skipped for insufficient balance:".to_string());
+            lines.push(format!(
+                "  {:<12} {:<44} {:>18} {:>18} {:<8}",
+                "Market", "Obligation", "Needed", "Held", ""
+            ));
+            for s in &self.insufficient_balance {
+                lines.push(format!(
+                    "  {:<12} {:<44} {:>18} {:>18} {:<8}",
+                    s.market,
+                    s.obligation,
+                    s.required.normalize(),
+                    s.available.normalize(),
+                    s.repay_symbol
+                ));
+            }
+        }
+
+        lines.join("\n")
+    }
+
+    pub fn log(&self) {
+        for line in self.render().lines() {
+            info!("{}", line);
+        }
+    }
+}
+
+/// Hypothetical liquidations found in shadow mode, with their estimated profit
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ShadowTotals {
+    /// Liquidations the wallet could have funded
+    pub captured: usize,
+    pub captured_profit_usd: Decimal,
+    /// Profitable liquidations the wallet held none of the repay token for
+    pub missed: usize,
+    pub missed_profit_usd: Decimal,
+}
+
+impl ShadowTotals {
+    pub fn is_empty(&self) -> bool {
+        self.captured == 0 && self.missed == 0
+    }
+
+    pub fn record(&mut self, profit_usd: Decimal, funded: bool) {
+        if funded {
+            self.captured += 1;
+            self.captured_profit_usd += profit_usd;
+        } else {
+            self.missed += 1;
+            self.missed_profit_usd += profit_usd;
+        }
+    }
+
+    pub fn merge(&mut self, other: &ShadowTotals) {
+        self.captured += other.captured;
+        self.captured_profit_usd += other.captured_profit_usd;
+        self.missed += other.missed;
+        self.missed_profit_usd += other.missed_profit_usd;
+    }
+
+    pub fn render(&self) -> String {
+        format!(
+            "captured {} (est. ${:.2}), missed {} (est. ${:.2})",
+            self.captured,
+            self.captured_profit_usd.round_dp(2),
+            self.missed,
+            self.missed_profit_usd.round_dp(2)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shadow_totals_accumulate() {
+        let mut epoch = ShadowTotals::default();
+        assert!(epoch.is_empty());
+        epoch.record(Decimal::new(1_250, 2), true);
+        epoch.record(Decimal::new(400, 2), false);
+        epoch.record(Decimal::new(75, 2), true);
+
+        let mut running = ShadowTotals::default();
+        running.merge(&epoch);
+        running.merge(&epoch);
+        assert_eq!((running.captured, running.missed), (4, 2));
+        assert_eq!(running.render(), "captured 4 (est. $26.50), missed 2 (est. $8.00)");
+    }
+
+    #[test]
+    fn test_render_dry_run_report() {
+        let mut report = DryRunReport::default();
+        report.liquidations.push(DryRunLiquidation {
+            market: "main".to_string(),
+            obligation: "Obl1".to_string(),
+            repay_symbol: "USDC".to_string(),
+            repay_amount: Decimal::new(12_500, 2),
+            withdraw_symbol: "SOL".to_string(),
+            profit_usd: Decimal::new(3_215, 3),
+        });
+
+        let mut other = DryRunReport::default();
+        other.liquidations.push(DryRunLiquidation {
+            market: "TURBO".to_string(),
+            obligation: "Obl2".to_string(),
+            repay_symbol: "USDT".to_string(),
+            repay_amount: Decimal::from(10),
+            withdraw_symbol: "mSOL".to_string(),
+            profit_usd: Decimal::ONE,
+        });
+        other.insufficient_balance.push(DryRunSkip {
+            market: "TURBO".to_string(),
+            obligation: "Obl3".to_string(),
+            repay_symbol: "ETH".to_string(),
+            required: Decimal::new(5, 1),
+            available: Decimal::ZERO,
+        });
+        report.merge(other);
+
+        let rendered = report.render();
+        assert!(rendered.starts_with("DRY-RUN REPORT: 2 would liquidate (est. profit $4.22), 1 skipped"));
+        assert!(rendered.contains("125 USDC"));
+        assert!(rendered.contains("$3.22"));
+        assert!(rendered.contains("Skipped for insufficient balance"));
+        assert!(rendered.lines().last().unwrap().contains("Obl3"));
+    }
+
+    #[test]
+    fn test_unhealthy_set_diffs_epochs() {
+        let (a, b, c) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut set = UnhealthySet::default();
+        
+        let first = set.update([a, b]);
+        assert_eq!(first.new.len(), 2);
+        assert!(first.recovered.is_empty() && first.unchanged.is_empty());
+        
+        let second = set.update([b, c]);
+        assert_eq!(second, UnhealthyDiff { new: vec![c], recovered: vec![a], unchanged: vec![b] });
+        
+        let third = set.update([]);
+        assert_eq!(third.recovered.len(), 2);
+    }
+
+    #[test]
+    fn test_render_scan_sorts_by_bonus_value() {
+        let row = |obligation: &str, bonus_value: i64, pair: Option<(&str, &str)>| ScanRow {
+            obligation: obligation.to_string(),
+            market: "main".to_string(),
+            borrowed_value: Decimal::from(150),
+            unhealthy_borrow_value: Decimal::from(120),
+            health_factor: Decimal::new(125, 2),
+            pair: pair.map(|(r, w)| (r.to_string(), w.to_string())),
+            bonus_value: Decimal::from(bonus_value),
+        };
+
+        let rendered = render_scan(&[
+            row("Small", 1, Some(("USDC", "SOL"))),
+            row("Large", 9, Some(("USDT", "mSOL"))),
+            row("Unknown", 0, None),
+        ]);
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        assert_eq!(lines[0], "3 unhealthy obligations");
+        assert!(lines[2].starts_with("Large") && lines[2].contains("USDT/mSOL") && lines[2].contains("$9.00"));
+        assert!(lines[3].starts_with("Small") && lines[3].contains("1.2500"));
+        assert!(lines[4].starts_with("Unknown") && lines[4].contains(" - "));
+    }
+}
@@ -0,0 +1,138 @@
+use anyhow::{anyhow, Result};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::clock::{DEFAULT_MS_PER_SLOT, MAX_PROCESSING_AGE};
+use solana_sdk::hash::Hash;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Default blocks of validity a cached blockhash must have left to be reused
+pub const DEFAULT_REFRESH_MARGIN_BLOCKS: u64 = 100;
+
+/// A transaction's blockhash expired before it landed, so it never will
+/// Rebuilding it with a fresh blockhash is safe
+#[derive(Debug, thiserror::Error)]
+#[error("blockhash {0} expired before the transaction landed")]
+pub struct BlockhashExpired(pub Hash);
+
+#[derive(Debug, Clone, Copy)]
+struct CachedBlockhash {
+    hash: Hash,
+    last_valid_block_height: u64,
+    fetched_at: Instant,
+}
+
+impl CachedBlockhash {
+    /// Blocks of validity left, estimated from the slots since it was fetched
+    /// A blockhash is valid for `MAX_PROCESSING_AGE` blocks after the one it was fetched at
+    fn blocks_left(&self, now: Instant) -> u64 {
+        let fetched_at_height = self.last_valid_block_height.saturating_sub(MAX_PROCESSING_AGE as u64);
+        let elapsed_blocks = now.saturating_duration_since(self.fetched_at).as_millis() as u64 / DEFAULT_MS_PER_SLOT;
+        self.last_valid_block_height.saturating_sub(fetched_at_height + elapsed_blocks)
+    }
+}
+
+/// Latest blockhash shared by every send, refetched when it gets close to expiry
+pub struct BlockhashManager {
+    refresh_margin_blocks: u64,
+    latest: Mutex<Option<CachedBlockhash>>,
+}
+
+impl BlockhashManager {
+    /// Cached blockhashes are reused while more than `refresh_margin_blocks` blocks are left;
+    /// `MAX_PROCESSING_AGE` (150) or more fetches one for every call
+    pub fn new(refresh_margin_blocks: u64) -> Self {
+        Self {
+            refresh_margin_blocks,
+            latest: Mutex::new(None),
+        }
+    }
+
+    /// A blockhash with more than the refresh margin of blocks left, fetched only when needed
+    pub fn get_fresh_blockhash(&self, client: &RpcClient) -> Result<Hash> {
+        let mut latest = self.latest.lock().map_err(|_| anyhow!("Blockhash cache poisoned"))?;
+
+        if let Some(cached) = *latest {
+            if cached.blocks_left(Instant::now()) > self.refresh_margin_blocks {
+                return Ok(cached.hash);
+            }
+        }
+
+        let (hash, last_valid_block_height) = client
+            .get_latest_blockhash_with_commitment(client.commitment())
+            .map_err(|e| anyhow!("Failed to get latest blockhash: {}", e))?;
+        *latest = Some(CachedBlockhash {
+            hash,
+            last_valid_block_height,
+            fetched_at: Instant::now(),
+        });
+
+        Ok(hash)
+    }
+
+    /// Drop the cached blockhash, e.g. after a send reported it expired
+    pub fn invalidate(&self) {
+        if let Ok(mut latest) = self.latest.lock() {
+            *latest = None;
+        }
+    }
+}
+
+impl Default for BlockhashManager {
+    fn default() -> Self {
+        Self::new(DEFAULT_REFRESH_MARGIN_BLOCKS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_client::rpc_request::RpcRequest;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    /// Mock client answering the first blockhash request with `hash`, later ones with the mock default
+    fn client_returning(hash: Hash) -> RpcClient {
+        let mut mocks = HashMap::new();
+        mocks.insert(
+            RpcRequest::GetLatestBlockhash,
+            serde_json::json!({
+                "context": { "slot": 1 },
+                "value": { "blockhash": hash.to_string(), "lastValidBlockHeight": 1_150 },
+            }),
+        );
+        RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks)
+    }
+
+    #[test]
+    fn test_blocks_left_counts_down_with_time() {
+        let now = Instant::now();
+        let cached = CachedBlockhash { hash: Hash::new_unique(), last_valid_block_height: 1_150, fetched_at: now };
+
+        assert_eq!(cached.blocks_left(now), 150);
+        assert_eq!(cached.blocks_left(now + Duration::from_millis(DEFAULT_MS_PER_SLOT * 50)), 100);
+        assert_eq!(cached.blocks_left(now + Duration::from_secs(600)), 0);
+    }
+
+    #[test]
+    fn test_blockhash_is_reused_until_invalidated() {
+        let hash = Hash::new_unique();
+        let client = client_returning(hash);
+        let blockhashes = BlockhashManager::default();
+
+        assert_eq!(blockhashes.get_fresh_blockhash(&client).unwrap(), hash);
+        assert_eq!(blockhashes.get_fresh_blockhash(&client).unwrap(), hash);
+
+        blockhashes.invalidate();
+        assert_ne!(blockhashes.get_fresh_blockhash(&client).unwrap(), hash);
+    }
+
+    #[test]
+    fn test_full_margin_fetches_every_time() {
+        let hash = Hash::new_unique();
+        let client = client_returning(hash);
+        let blockhashes = BlockhashManager::new(MAX_PROCESSING_AGE as u64);
+
+        assert_eq!(blockhashes.get_fresh_blockhash(&client).unwrap(), hash);
+        assert_ne!(blockhashes.get_fresh_blockhash(&client).unwrap(), hash);
+    }
+}
@@ -0,0 +1,216 @@
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use serde::Deserialize;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    system_instruction,
+    transaction::{TransactionError, VersionedTransaction},
+};
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Mainnet block engine
+pub const DEFAULT_BLOCK_ENGINE_URL: &str = "https://mainnet.block-engine.jito.wtf";
+
+/// Accounts the block engine accepts tips on; one is picked per transaction
+const TIP_ACCOUNTS: [&str; 8] = [
+    "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5",
+    "HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe",
+    "Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY",
+    "ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49",
+    "DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh",
+    "ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt",
+    "DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL",
+    "3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT",
+];
+
+const CONFIRM_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcResponse {
+    result: Option<String>,
+    error: Option<serde_json::Value>,
+}
+
+/// Submits transactions as single-transaction bundles to a Jito block engine
+pub struct JitoClient {
+    client: reqwest::Client,
+    block_engine_url: String,
+    tip_lamports: u64,
+}
+
+impl JitoClient {
+    pub fn new(block_engine_url: &str, tip_lamports: u64) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            block_engine_url: block_engine_url.trim_end_matches('/').to_string(),
+            tip_lamports,
+        }
+    }
+
+    /// Transfer paying the block engine tip; must be part of the bundled transaction
+    /// Tip accounts are rotated to spread write locks across them
+    pub fn tip_instruction(&self, payer: &Pubkey) -> Instruction {
+        let index = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos() as usize)
+            .unwrap_or(0)
+            % TIP_ACCOUNTS.len();
+        let tip_account = Pubkey::from_str(TIP_ACCOUNTS[index]).expect("valid tip account");
+
+        system_instruction::transfer(payer, &tip_account, self.tip_lamports)
+    }
+
+    /// Submit a signed transaction as a bundle, returning the bundle id
+    pub async fn send_bundle(&self, transaction: &VersionedTransaction) -> Result<String> {
+        let url = format!("{}/api/v1/bundles", self.block_engine_url);
+
+        let response: JsonRpcResponse = self
+            .client
+            .post(&url)
+            .json(&bundle_request(transaction)?)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        match response {
+            JsonRpcResponse { result: Some(bundle_id), .. } => Ok(bundle_id),
+            JsonRpcResponse { error: Some(error), .. } => Err(anyhow!("Jito sendBundle error: {}", error)),
+            _ => Err(anyhow!("Jito sendBundle returned no bundle id")),
+        }
+    }
+
+    /// Wait for a bundled transaction to land at the client's commitment, re-submitting
+    /// the bundle every `resend_interval` until it lands, `timeout` passes or its blockhash expires
+    pub async fn confirm_bundle(
+        &self,
+        client: &RpcClient,
+        transaction: &VersionedTransaction,
+        timeout: Duration,
+        resend_interval: Duration,
+    ) -> Result<BundleOutcome> {
+        let signature = transaction.signatures[0];
+        let blockhash = *transaction.message.recent_blockhash();
+        let start = Instant::now();
+        let mut last_send = start;
+
+        loop {
+            if let Some(status) = client.get_signature_status_with_commitment(&signature, client.commitment())? {
+                return Ok(BundleOutcome::Landed(status));
+            }
+
+            let timed_out = start.elapsed() >= timeout;
+            if timed_out || last_send.elapsed() >= resend_interval {
+                let blockhash_expired = !client.is_blockhash_valid(&blockhash, CommitmentConfig::processed())?;
+                if timed_out || blockhash_expired {
+                    return Ok(BundleOutcome::TimedOut {
+                        elapsed_secs: start.elapsed().as_secs(),
+                        blockhash_expired,
+                    });
+                }
+
+                if let Err(e) = self.send_bundle(transaction).await {
+                    log::debug!("Re-sending bundle for {} failed: {}", signature, e);
+                }
+                last_send = Instant::now();
+            }
+
+            tokio::time::sleep(CONFIRM_POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// How waiting for a bundled transaction ended
+#[derive(Debug, PartialEq)]
+pub enum BundleOutcome {
+    /// Seen at the client's commitment, with its on-chain result
+    Landed(std::result::Result<(), TransactionError>),
+    /// Not seen in time; it can't land any more once its blockhash expired
+    TimedOut { elapsed_secs: u64, blockhash_expired: bool },
+}
+
+/// JSON-RPC `sendBundle` body for a single base64-encoded transaction
+fn bundle_request(transaction: &VersionedTransaction) -> Result<serde_json::Value> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(bincode::serialize(transaction)?);
+
+    Ok(serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "sendBundle",
+        "params": [[encoded], { "encoding": "base64" }],
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{
+        hash::Hash,
+        signature::{Keypair, Signer},
+        transaction::Transaction,
+    };
+
+    #[test]
+    fn test_tip_instruction_pays_a_tip_account() {
+        let jito = JitoClient::new("https://example.com/", 10_000);
+        assert_eq!(jito.block_engine_url, "https://example.com");
+
+        let payer = Pubkey::new_unique();
+        let ix = jito.tip_instruction(&payer);
+
+        assert_eq!(ix.program_id, solana_sdk::system_program::id());
+        assert_eq!(ix.accounts[0].pubkey, payer);
+        assert!(TIP_ACCOUNTS.contains(&ix.accounts[1].pubkey.to_string().as_str()));
+    }
+
+    #[test]
+    fn test_bundle_request_round_trips() {
+        let payer = Keypair::new();
+        let ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+        let transaction: VersionedTransaction =
+            Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], Hash::default()).into();
+
+        let request = bundle_request(&transaction).unwrap();
+        assert_eq!(request["method"], "sendBundle");
+        assert_eq!(request["params"][1]["encoding"], "base64");
+
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(request["params"][0][0].as_str().unwrap())
+            .unwrap();
+        let decoded: VersionedTransaction = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(decoded.signatures, transaction.signatures);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_confirm_bundle_times_out_once_the_blockhash_expires() {
+        let payer = Keypair::new();
+        let ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+        let transaction: VersionedTransaction =
+            Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], Hash::default()).into();
+        let jito = JitoClient::new("http://127.0.0.1:1", 10_000);
+
+        let client = RpcClient::new_mock("succeeds".to_string());
+        let outcome = jito
+            .confirm_bundle(&client, &transaction, Duration::from_secs(5), Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(outcome, BundleOutcome::Landed(Ok(())));
+
+        // Never seen on-chain and the blockhash has expired
+        let mut mocks = std::collections::HashMap::new();
+        mocks.insert(
+            solana_client::rpc_request::RpcRequest::IsBlockhashValid,
+            serde_json::json!({ "context": { "slot": 1 }, "value": false }),
+        );
+        let client = RpcClient::new_mock_with_mocks("sig_not_found".to_string(), mocks);
+        let outcome = jito
+            .confirm_bundle(&client, &transaction, Duration::from_secs(5), Duration::ZERO)
+            .await
+            .unwrap();
+        assert!(matches!(outcome, BundleOutcome::TimedOut { blockhash_expired: true, .. }));
+    }
+}
@@ -0,0 +1,1102 @@
+pub mod blockhash;
+pub mod jito;
+
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::rpc_client::{RpcClient, RpcClientConfig};
+use solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+use solana_rpc_client::http_sender::HttpSender;
+use solana_client::rpc_request::{RpcError, RpcRequest};
+use solana_client::rpc_response::{Response, RpcKeyedAccount};
+use solana_client::rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType};
+use solana_account_decoder::UiDataSliceConfig;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+    account::Account,
+    program_pack::Pack,
+};
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroU32;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+
+use crate::cache::Cache;
+use crate::liquidation::{calculate_refreshed_obligation, RefreshedObligation};
+use crate::models::{MarketConfig, Obligation, ObligationHealthView, Reserve};
+use crate::models::{obligation, reserve};
+use crate::utils::get_program_id;
+
+pub use blockhash::{BlockhashExpired, BlockhashManager};
+
+/// Reserves per lending market address
+pub type ReserveCache = Cache<String, Vec<(Pubkey, Reserve)>>;
+
+/// Delay before re-opening a dropped WebSocket subscription
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+/// Solana's own default per-request timeout
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default retry policy for RPC reads
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+const DEFAULT_RETRY_BASE_DELAY: Duration = Duration::from_millis(200);
+
+/// Whether an RPC error is worth retrying (rate limits, 5xx, transport failures)
+fn is_transient(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::Io(_) => true,
+        ClientErrorKind::Reqwest(e) => {
+            e.is_timeout()
+                || e.is_connect()
+                || e.status().is_some_and(|s| s.as_u16() == 429 || s.is_server_error())
+        }
+        ClientErrorKind::RpcError(RpcError::RpcRequestError(_)) => true,
+        // Node behind / rate limited responses
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+            matches!(code, -32005 | 429)
+        }
+        _ => false,
+    }
+}
+
+/// `tokio::task::block_in_place` on a multi-threaded runtime, so a blocking call doesn't
+/// stall the worker's other tasks; current-thread runtimes and plain threads just run `f`
+fn block_in_place<R>(f: impl FnOnce() -> R) -> R {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(f)
+        }
+        _ => f(),
+    }
+}
+
+/// Filters matching accounts of `data_size` bytes in a lending market
+/// `lending_market_offset` comes from the account layout, so it moves with the parser
+fn market_account_filters(
+    lending_market_addr: &str,
+    data_size: usize,
+    lending_market_offset: usize,
+) -> Vec<RpcFilterType> {
+    vec![
+        // Filter by lending market address
+        RpcFilterType::Memcmp(Memcmp::new(
+            lending_market_offset,
+            MemcmpEncodedBytes::Base58(lending_market_addr.to_string()),
+        )),
+        // Filter by data size
+        RpcFilterType::DataSize(data_size as u64),
+    ]
+}
+
+/// Obligation updates pushed by a program account subscription
+pub struct ObligationWatcher {
+    receiver: mpsc::UnboundedReceiver<(Pubkey, Obligation)>,
+    /// Set once a full fetch has been taken; updates before that are redundant
+    pub seeded: bool,
+}
+
+impl ObligationWatcher {
+    /// Drain pending updates, keeping the latest version of each obligation
+    pub fn drain_changed(&mut self) -> Vec<(Pubkey, Obligation)> {
+        let mut changed = HashMap::new();
+        while let Ok((pubkey, obligation)) = self.receiver.try_recv() {
+            changed.insert(pubkey, obligation);
+        }
+        changed.into_iter().collect()
+    }
+}
+
+/// Obligation accounts kept between epochs so healthy ones aren't downloaded every time
+/// Health is still recomputed from fresh prices each epoch; only the account data is reused
+pub struct KnownObligations {
+    obligations: HashMap<Pubkey, Obligation>,
+    /// Obligations that weren't near liquidation when last checked
+    healthy: HashSet<Pubkey>,
+    /// Everything is re-downloaded this often, catching changes to healthy accounts
+    resync_interval: Duration,
+    last_resync: Option<Instant>,
+}
+
+impl KnownObligations {
+    pub fn new(resync_secs: u64) -> Self {
+        Self {
+            obligations: HashMap::new(),
+            healthy: HashSet::new(),
+            resync_interval: Duration::from_secs(resync_secs),
+            last_resync: None,
+        }
+    }
+    
+    /// Replace the set of obligations whose cached data can be trusted next epoch
+    pub fn set_healthy(&mut self, healthy: impl IntoIterator<Item = Pubkey>) {
+        self.healthy = healthy
+            .into_iter()
+            .filter(|pubkey| self.obligations.contains_key(pubkey))
+            .collect();
+    }
+    
+    pub fn obligations(&self) -> Vec<(Pubkey, Obligation)> {
+        self.obligations.iter().map(|(pubkey, obligation)| (*pubkey, obligation.clone())).collect()
+    }
+    
+    /// Accounts that need downloading: new or not known healthy, or all of them when a resync is due
+    fn pubkeys_to_fetch(&mut self, pubkeys: &[Pubkey], now: Instant) -> Vec<Pubkey> {
+        let resync_due = self
+            .last_resync
+            .is_none_or(|last| now.saturating_duration_since(last) >= self.resync_interval);
+        if resync_due {
+            self.healthy.clear();
+            self.last_resync = Some(now);
+        }
+        
+        pubkeys.iter().filter(|pubkey| !self.healthy.contains(pubkey)).copied().collect()
+    }
+    
+    /// Drop closed accounts and store freshly downloaded ones
+    fn update(&mut self, pubkeys: &[Pubkey], fetched: Vec<(Pubkey, Option<Obligation>)>) {
+        let live: HashSet<&Pubkey> = pubkeys.iter().collect();
+        self.obligations.retain(|pubkey, _| live.contains(pubkey));
+        self.healthy.retain(|pubkey| live.contains(pubkey));
+        
+        for (pubkey, obligation) in fetched {
+            match obligation {
+                Some(obligation) => {
+                    self.obligations.insert(pubkey, obligation);
+                }
+                None => {
+                    self.obligations.remove(&pubkey);
+                    self.healthy.remove(&pubkey);
+                }
+            }
+        }
+    }
+}
+
+/// RPC transport that waits on a shared token bucket before every request
+/// Sits beneath `RpcClient`, so calls made through `client()` are limited too
+struct RateLimitedSender<S> {
+    inner: S,
+    limiter: Arc<governor::DefaultDirectRateLimiter>,
+}
+
+#[async_trait::async_trait]
+impl<S: RpcSender + Send + Sync> RpcSender for RateLimitedSender<S> {
+    async fn send(
+        &self,
+        request: RpcRequest,
+        params: serde_json::Value,
+    ) -> solana_client::client_error::Result<serde_json::Value> {
+        self.limiter.until_ready().await;
+        self.inner.send(request, params).await
+    }
+    
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.inner.get_transport_stats()
+    }
+    
+    fn url(&self) -> String {
+        self.inner.url()
+    }
+}
+
+/// RPC client wrapper with convenience methods
+pub struct SolendRpcClient {
+    /// Uses the confirm commitment, so transactions sent through it confirm at that level
+    client: RpcClient,
+    program_id: Pubkey,
+    /// Commitment for obligation, reserve and account reads
+    read_commitment: CommitmentConfig,
+    max_attempts: u32,
+    retry_base_delay: Duration,
+    /// Per-request timeout and shared rate limit, kept whenever the transport is rebuilt
+    timeout: Duration,
+    limiter: Option<Arc<governor::DefaultDirectRateLimiter>>,
+    /// Individual reserves, for lookups that don't need a whole market
+    reserves: Mutex<Cache<Pubkey, Reserve>>,
+}
+
+// solana_client's ClientError is large; retried calls pass it through unboxed
+#[allow(clippy::result_large_err)]
+impl SolendRpcClient {
+    /// Create new RPC client
+    pub fn new(rpc_endpoint: &str, env: &str) -> Result<Self> {
+        Self::new_with_commitment(
+            rpc_endpoint,
+            env,
+            CommitmentConfig::confirmed(),
+            CommitmentConfig::confirmed(),
+        )
+    }
+    
+    /// Create new RPC client reading at `read_commitment` and confirming
+    /// transactions at `confirm_commitment`
+    pub fn new_with_commitment(
+        rpc_endpoint: &str,
+        env: &str,
+        read_commitment: CommitmentConfig,
+        confirm_commitment: CommitmentConfig,
+    ) -> Result<Self> {
+        let client = RpcClient::new_with_commitment(
+            rpc_endpoint.to_string(),
+            confirm_commitment,
+        );
+        
+        let program_id = get_program_id(env)?;
+        
+        Ok(Self {
+            client,
+            program_id,
+            read_commitment,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            retry_base_delay: DEFAULT_RETRY_BASE_DELAY,
+            timeout: DEFAULT_TIMEOUT,
+            limiter: None,
+            reserves: Mutex::new(Cache::new(0)),
+        })
+    }
+    
+    /// Fail RPC requests that take longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self.rebuild_transport();
+        self
+    }
+    
+    /// Reuse individually fetched reserves for `ttl_secs` (0, the default, disables)
+    pub fn with_reserve_cache_ttl(mut self, ttl_secs: u64) -> Self {
+        self.reserves = Mutex::new(Cache::new(ttl_secs));
+        self
+    }
+    
+    /// Override the retry policy for RPC reads
+    pub fn with_retry_policy(mut self, max_attempts: u32, base_delay_ms: u64) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self.retry_base_delay = Duration::from_millis(base_delay_ms);
+        self
+    }
+    
+    /// Limit outbound RPC requests to `requests_per_second` (0 disables)
+    /// Every clone of the surrounding `Arc` shares the same bucket
+    pub fn with_rate_limit(mut self, requests_per_second: u32) -> Self {
+        let Some(rate) = NonZeroU32::new(requests_per_second) else {
+            return self;
+        };
+        
+        self.limiter = Some(Arc::new(governor::RateLimiter::direct(governor::Quota::per_second(rate))));
+        self.rebuild_transport();
+        self
+    }
+    
+    /// Recreate the client for the current timeout and rate limit
+    fn rebuild_transport(&mut self) {
+        let (url, commitment) = (self.client.url(), self.client.commitment());
+        
+        self.client = match &self.limiter {
+            Some(limiter) => RpcClient::new_sender(
+                RateLimitedSender {
+                    inner: HttpSender::new_with_timeout(url, self.timeout),
+                    limiter: limiter.clone(),
+                },
+                RpcClientConfig::with_commitment(commitment),
+            ),
+            None => RpcClient::new_with_timeout_and_commitment(url, self.timeout, commitment),
+        };
+    }
+    
+    /// Run an RPC call, retrying transient failures with exponential backoff and jitter
+    /// The calls and backoff block, so on a tokio worker the other tasks are handed off first
+    fn with_retry<T>(&self, op: impl Fn() -> Result<T, ClientError>) -> Result<T, ClientError> {
+        block_in_place(|| self.retry_blocking(op))
+    }
+    
+    fn retry_blocking<T>(&self, op: impl Fn() -> Result<T, ClientError>) -> Result<T, ClientError> {
+        let mut attempt = 1;
+        
+        loop {
+            match op() {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < self.max_attempts && is_transient(&e) => {
+                    let backoff = self.retry_base_delay * 2u32.pow(attempt - 1);
+                    // Up to 50% jitter so parallel market tasks don't retry in lockstep
+                    let jitter_ms = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|d| d.subsec_nanos() as u64)
+                        .unwrap_or(0)
+                        % (backoff.as_millis() as u64 / 2 + 1);
+                    let delay = backoff + Duration::from_millis(jitter_ms);
+                    
+                    log::warn!(
+                        "RPC call failed (attempt {}/{}), retrying in {:?}: {}",
+                        attempt, self.max_attempts, delay, e
+                    );
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    
+    /// Fetch the raw obligation accounts of a lending market
+    /// With `data_slice`, each account holds only that byte range of its data
+    pub fn get_obligation_accounts(
+        &self,
+        lending_market_addr: &str,
+        data_slice: Option<UiDataSliceConfig>,
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        self.get_market_accounts(
+            lending_market_addr,
+            obligation::OBLIGATION_SIZE,
+            obligation::LENDING_MARKET_OFFSET,
+            data_slice,
+        )
+    }
+    
+    /// Fetch the raw reserve accounts of a lending market
+    /// With `data_slice`, each account holds only that byte range of its data
+    pub fn get_reserve_accounts(
+        &self,
+        lending_market_addr: &str,
+        data_slice: Option<UiDataSliceConfig>,
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        self.get_market_accounts(
+            lending_market_addr,
+            reserve::RESERVE_SIZE,
+            reserve::LENDING_MARKET_OFFSET,
+            data_slice,
+        )
+    }
+    
+    fn get_market_accounts(
+        &self,
+        lending_market_addr: &str,
+        data_size: usize,
+        lending_market_offset: usize,
+        data_slice: Option<UiDataSliceConfig>,
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        // Filters apply to the full account, so slicing doesn't affect which accounts match
+        let filters = market_account_filters(lending_market_addr, data_size, lending_market_offset);
+        
+        let config = solana_client::rpc_config::RpcProgramAccountsConfig {
+            filters: Some(filters),
+            account_config: solana_client::rpc_config::RpcAccountInfoConfig {
+                encoding: Some(solana_account_decoder::UiAccountEncoding::Base64),
+                data_slice,
+                commitment: Some(self.read_commitment),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        
+        Ok(self.with_retry(|| {
+            self.client.get_program_accounts_with_config(&self.program_id, config.clone())
+        })?)
+    }
+    
+    /// Fetch the on-chain health fields of every obligation in a lending market
+    /// Downloads a small slice per account; values are as of each obligation's last refresh
+    pub fn get_obligation_health_views(
+        &self,
+        lending_market_addr: &str,
+    ) -> Result<Vec<(Pubkey, ObligationHealthView)>> {
+        let accounts = self.get_obligation_accounts(
+            lending_market_addr,
+            Some(UiDataSliceConfig {
+                offset: obligation::HEALTH_VIEW_OFFSET,
+                length: obligation::HEALTH_VIEW_LEN,
+            }),
+        )?;
+        
+        let mut views = Vec::with_capacity(accounts.len());
+        for (pubkey, account) in accounts {
+            match ObligationHealthView::parse(&account.data) {
+                Ok(view) => views.push((pubkey, view)),
+                Err(e) => log::warn!("Failed to parse obligation {}: {}", pubkey, e),
+            }
+        }
+        
+        Ok(views)
+    }
+    
+    /// Fetch all obligations for a lending market
+    pub fn get_obligations(&self, lending_market_addr: &str) -> Result<Vec<(Pubkey, Obligation)>> {
+        let _market_pubkey = Pubkey::from_str(lending_market_addr)?;
+        let accounts = self.get_obligation_accounts(lending_market_addr, None)?;
+        
+        let mut obligations = Vec::new();
+        
+        for (pubkey, account) in accounts {
+            match Obligation::parse_detailed(&account.data) {
+                Ok(obligation) => {
+                    if !obligation.last_update.is_zero() {
+                        obligations.push((pubkey, obligation));
+                    }
+                }
+                Err(e) => {
+                    log::warn!("Failed to parse obligation {}: {}", pubkey, e);
+                }
+            }
+        }
+        
+        log::info!("Fetched {} obligations for market {}", obligations.len(), lending_market_addr);
+        Ok(obligations)
+    }
+    
+    /// Fetch the addresses of all obligations in a lending market without their data
+    pub fn get_obligation_pubkeys(&self, lending_market_addr: &str) -> Result<Vec<Pubkey>> {
+        // Zero-length slice: only the pubkeys are needed
+        let accounts = self.get_obligation_accounts(
+            lending_market_addr,
+            Some(UiDataSliceConfig { offset: 0, length: 0 }),
+        )?;
+        
+        Ok(accounts.into_iter().map(|(pubkey, _)| pubkey).collect())
+    }
+    
+    /// Fetch all obligations for a lending market, downloading only accounts not known healthy
+    pub async fn get_obligations_incremental(
+        &self,
+        known: &mut KnownObligations,
+        lending_market_addr: &str,
+    ) -> Result<Vec<(Pubkey, Obligation)>> {
+        let pubkeys = self.get_obligation_pubkeys(lending_market_addr)?;
+        let to_fetch = known.pubkeys_to_fetch(&pubkeys, Instant::now());
+        let accounts = self.get_multiple_accounts_batched(&to_fetch, 100).await?;
+        
+        let fetched = to_fetch
+            .iter()
+            .zip(accounts)
+            .map(|(pubkey, account)| {
+                let obligation = account.and_then(|account| match Obligation::parse_detailed(&account.data) {
+                    Ok(obligation) => Some(obligation).filter(|o| !o.last_update.is_zero()),
+                    Err(e) => {
+                        log::warn!("Failed to parse obligation {}: {}", pubkey, e);
+                        None
+                    }
+                });
+                (*pubkey, obligation)
+            })
+            .collect();
+        known.update(&pubkeys, fetched);
+        
+        log::info!(
+            "Fetched {} of {} obligations for market {}",
+            to_fetch.len(),
+            pubkeys.len(),
+            lending_market_addr
+        );
+        Ok(known.obligations())
+    }
+    
+    /// Fetch reserves for a lending market, reusing a cached copy within its TTL
+    /// The lock is not held across the fetch so markets don't serialize on it
+    pub fn get_reserves_cached(
+        &self,
+        cache: &Mutex<ReserveCache>,
+        lending_market_addr: &str,
+    ) -> Result<Vec<(Pubkey, Reserve)>> {
+        let key = lending_market_addr.to_string();
+        
+        if let Some(reserves) = cache.lock().map_err(|_| anyhow!("Reserve cache poisoned"))?.get(&key) {
+            return Ok(reserves);
+        }
+        
+        let reserves = self.get_reserves(lending_market_addr)?;
+        cache
+            .lock()
+            .map_err(|_| anyhow!("Reserve cache poisoned"))?
+            .insert(key, reserves.clone());
+        
+        Ok(reserves)
+    }
+    
+    /// Fetch one reserve, reusing a cached copy within its TTL
+    pub fn get_reserve_cached(&self, pubkey: &Pubkey) -> Result<Reserve> {
+        if let Some(reserve) = self.reserve_cache()?.get(pubkey) {
+            return Ok(reserve);
+        }
+        
+        let account = self.get_account(pubkey)?;
+        let reserve = Reserve::parse(&account.data)
+            .map_err(|e| anyhow!("Failed to parse reserve {}: {}", pubkey, e))?;
+        self.reserve_cache()?.insert(*pubkey, reserve.clone());
+        
+        Ok(reserve)
+    }
+    
+    /// Drop a cached reserve so the next lookup re-fetches it
+    /// For callers that know it changed, e.g. after liquidating against it
+    pub fn invalidate_reserve(&self, pubkey: &Pubkey) {
+        if let Ok(mut cache) = self.reserves.lock() {
+            cache.remove(pubkey);
+        }
+    }
+    
+    fn reserve_cache(&self) -> Result<std::sync::MutexGuard<'_, Cache<Pubkey, Reserve>>> {
+        self.reserves.lock().map_err(|_| anyhow!("Reserve cache poisoned"))
+    }
+    
+    /// Fetch all reserves for a lending market
+    /// Each one also refreshes its entry in the per-reserve cache
+    pub fn get_reserves(&self, lending_market_addr: &str) -> Result<Vec<(Pubkey, Reserve)>> {
+        let accounts = self.get_reserve_accounts(lending_market_addr, None)?;
+        
+        let mut reserves = Vec::new();
+        
+        for (pubkey, account) in accounts {
+            match Reserve::parse(&account.data) {
+                Ok(reserve) => {
+                    if !reserve.last_update.is_zero() {
+                        reserves.push((pubkey, reserve));
+                    }
+                }
+                Err(e) => {
+                    log::warn!("Failed to parse reserve {}: {}", pubkey, e);
+                }
+            }
+        }
+        
+        if let Ok(mut cache) = self.reserves.lock() {
+            for (pubkey, reserve) in &reserves {
+                cache.insert(*pubkey, reserve.clone());
+            }
+        }
+        
+        log::info!("Fetched {} reserves for market {}", reserves.len(), lending_market_addr);
+        Ok(reserves)
+    }
+    
+    /// Get account info
+    pub fn get_account(&self, pubkey: &Pubkey) -> Result<Account> {
+        self.with_retry(|| self.client.get_account_with_commitment(pubkey, self.read_commitment))
+            .map_err(|e| anyhow!("Failed to get account {}: {}", pubkey, e))?
+            .value
+            .ok_or_else(|| anyhow!("Failed to get account {}: account not found", pubkey))
+    }
+    
+    /// Get the current slot
+    pub fn get_slot(&self) -> Result<u64> {
+        self.with_retry(|| self.client.get_slot_with_commitment(self.read_commitment))
+            .map_err(|e| anyhow!("Failed to get slot: {}", e))
+    }
+    
+    /// List every SPL token account held by `owner`
+    /// Accounts that fail to decode or unpack are skipped with a warning
+    pub fn get_token_accounts_by_owner(&self, owner: &Pubkey) -> Result<Vec<(Pubkey, spl_token::state::Account)>> {
+        // Raw base64 data rather than RpcClient's jsonParsed default, so accounts can be unpacked
+        let params = serde_json::json!([
+            owner.to_string(),
+            { "programId": spl_token::id().to_string() },
+            {
+                "encoding": solana_account_decoder::UiAccountEncoding::Base64,
+                "commitment": self.read_commitment.commitment,
+            },
+        ]);
+        
+        let response: Response<Vec<RpcKeyedAccount>> = self
+            .with_retry(|| self.client.send(RpcRequest::GetTokenAccountsByOwner, params.clone()))
+            .map_err(|e| anyhow!("Failed to get token accounts for {}: {}", owner, e))?;
+        
+        let mut accounts = Vec::with_capacity(response.value.len());
+        
+        for keyed in response.value {
+            let parsed = Pubkey::from_str(&keyed.pubkey)
+                .map_err(|e| anyhow!("invalid pubkey: {}", e))
+                .and_then(|pubkey| {
+                    let account: Account = keyed.account.decode().ok_or_else(|| anyhow!("undecodable data"))?;
+                    Ok((pubkey, spl_token::state::Account::unpack(&account.data)?))
+                });
+            
+            match parsed {
+                Ok(entry) => accounts.push(entry),
+                Err(e) => log::warn!("Failed to parse token account {}: {}", keyed.pubkey, e),
+            }
+        }
+        
+        Ok(accounts)
+    }
+    
+    /// Get multiple accounts
+    pub fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+        self.with_retry(|| self.client.get_multiple_accounts_with_commitment(pubkeys, self.read_commitment))
+            .map(|response| response.value)
+            .map_err(|e| anyhow!("Failed to get multiple accounts: {}", e))
+    }
+
+    /// Get multiple accounts in batches to avoid RPC limits (max 100 per request)
+    pub async fn get_multiple_accounts_batched(
+        &self,
+        pubkeys: &[Pubkey],
+        batch_size: usize,
+    ) -> Result<Vec<Option<Account>>> {
+        let mut all_accounts = Vec::with_capacity(pubkeys.len());
+        
+        // Use default batch size of 100 if 0 or larger than limit
+        let actual_batch_size = if batch_size == 0 || batch_size > 100 { 
+            100 
+        } else { 
+            batch_size 
+        };
+
+        for chunk in pubkeys.chunks(actual_batch_size) {
+            // Note: get_multiple_accounts is synchronous in solana-client but we wrap it here
+            // In a real async client we'd await. Here we just call the sync method.
+            // If we want true parallelism we might need spawn_blocking or similar if using sync client
+            // But for now, just batching to avoid errors is the goal.
+            let chunk_accounts = self.get_multiple_accounts(chunk)?;
+            all_accounts.extend(chunk_accounts);
+        }
+
+        Ok(all_accounts)
+    }
+    
+    /// Fetch a single obligation with its reserves and oracle prices and
+    /// compute its current health
+    /// Oracles are held to the same confidence, agreement and staleness limits as an epoch
+    pub async fn check_obligation_health(
+        &self,
+        obligation: &Pubkey,
+        market: &MarketConfig,
+        max_conf_ratio: f64,
+        tolerance_percent: f64,
+        staleness: &crate::oracle::StalenessConfig,
+        use_ema_for_health: bool,
+    ) -> Result<RefreshedObligation> {
+        let account = self.get_account(obligation)?;
+        let parsed = Obligation::parse(&account.data)
+            .map_err(|e| anyhow!("Failed to parse obligation {}: {}", obligation, e))?;
+        
+        // Only the reserves this obligation touches
+        let mut reserve_pubkeys: Vec<Pubkey> = parsed.deposits.iter().map(|d| d.deposit_reserve)
+            .chain(parsed.borrows.iter().map(|b| b.borrow_reserve))
+            .collect();
+        reserve_pubkeys.sort();
+        reserve_pubkeys.dedup();
+        
+        let mut reserves = HashMap::new();
+        for pubkey in &reserve_pubkeys {
+            reserves.insert(*pubkey, self.get_reserve_cached(pubkey)?);
+        }
+        
+        let oracle_data = crate::oracle::get_tokens_oracle_data(
+            self,
+            market,
+            max_conf_ratio,
+            tolerance_percent,
+            staleness,
+        ).await?;
+        
+        calculate_refreshed_obligation(&parsed, &reserves, &oracle_data, use_ema_for_health)
+    }
+    
+    /// Subscribe to obligation account changes for a lending market
+    /// Updates are parsed and forwarded from a background task that
+    /// re-subscribes whenever the WebSocket drops
+    pub fn subscribe_obligations(&self, ws_endpoint: &str, lending_market_addr: &str) -> Result<ObligationWatcher> {
+        let _market_pubkey = Pubkey::from_str(lending_market_addr)?;
+        
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let ws_endpoint = ws_endpoint.to_string();
+        let market = lending_market_addr.to_string();
+        let program_id = self.program_id;
+        let commitment = self.read_commitment;
+        
+        tokio::spawn(async move {
+            while !sender.is_closed() {
+                if let Err(e) = forward_obligation_updates(&ws_endpoint, &program_id, &market, commitment, &sender).await {
+                    log::warn!("Obligation subscription for market {} dropped: {}", market, e);
+                }
+                tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+            }
+        });
+        
+        Ok(ObligationWatcher { receiver, seeded: false })
+    }
+    
+    /// Get inner client reference
+    pub fn client(&self) -> &RpcClient {
+        &self.client
+    }
+}
+
+/// Stream obligation changes into `sender` until the subscription ends
+async fn forward_obligation_updates(
+    ws_endpoint: &str,
+    program_id: &Pubkey,
+    lending_market_addr: &str,
+    commitment: CommitmentConfig,
+    sender: &mpsc::UnboundedSender<(Pubkey, Obligation)>,
+) -> Result<()> {
+    let pubsub = PubsubClient::new(ws_endpoint).await?;
+    
+    let config = solana_client::rpc_config::RpcProgramAccountsConfig {
+        filters: Some(market_account_filters(
+            lending_market_addr,
+            obligation::OBLIGATION_SIZE,
+            obligation::LENDING_MARKET_OFFSET,
+        )),
+        account_config: solana_client::rpc_config::RpcAccountInfoConfig {
+            encoding: Some(solana_account_decoder::UiAccountEncoding::Base64),
+            commitment: Some(commitment),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    
+    let (mut stream, unsubscribe) = pubsub.program_subscribe(program_id, Some(config)).await?;
+    log::info!("Subscribed to obligation updates for market {}", lending_market_addr);
+    
+    while let Some(update) = stream.next().await {
+        let keyed = update.value;
+        
+        let Ok(pubkey) = Pubkey::from_str(&keyed.pubkey) else {
+            continue;
+        };
+        let Some(account) = keyed.account.decode::<Account>() else {
+            log::warn!("Failed to decode obligation update {}", pubkey);
+            continue;
+        };
+        
+        match Obligation::parse(&account.data) {
+            Ok(obligation) => {
+                if !obligation.last_update.is_zero() && sender.send((pubkey, obligation)).is_err() {
+                    // Watcher dropped
+                    break;
+                }
+            }
+            Err(e) => {
+                log::warn!("Failed to parse obligation {}: {}", pubkey, e);
+            }
+        }
+    }
+    
+    unsubscribe().await;
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::result_large_err)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_is_transient() {
+        let io = ClientError::from(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset"));
+        assert!(is_transient(&io));
+        
+        let rate_limited = ClientError::from(RpcError::RpcResponseError {
+            code: 429,
+            message: "Too many requests".to_string(),
+            data: solana_client::rpc_request::RpcResponseErrorData::Empty,
+        });
+        assert!(is_transient(&rate_limited));
+        
+        let parse = ClientError::from(RpcError::ParseError("account".to_string()));
+        assert!(!is_transient(&parse));
+    }
+    
+    #[test]
+    fn test_with_retry_stops_on_non_transient() {
+        let client = SolendRpcClient::new("http://localhost:8899", "production")
+            .unwrap()
+            .with_retry_policy(3, 0);
+        let calls = std::cell::Cell::new(0);
+        
+        let result: Result<(), ClientError> = client.with_retry(|| {
+            calls.set(calls.get() + 1);
+            Err(ClientError::from(RpcError::ParseError("account".to_string())))
+        });
+        
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 1);
+    }
+    
+    #[test]
+    fn test_with_retry_retries_transient() {
+        let client = SolendRpcClient::new("http://localhost:8899", "production")
+            .unwrap()
+            .with_retry_policy(3, 0);
+        let calls = std::cell::Cell::new(0);
+        
+        let result: Result<(), ClientError> = client.with_retry(|| {
+            calls.set(calls.get() + 1);
+            Err(ClientError::from(std::io::Error::new(std::io::ErrorKind::TimedOut, "timeout")))
+        });
+        
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 3);
+    }
+    
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_with_retry_backoff_leaves_the_worker_to_other_tasks() {
+        // Runs on the only worker, so the task it spawns is queued behind it
+        let result = tokio::spawn(async {
+            let client = SolendRpcClient::new("http://localhost:8899", "production")
+                .unwrap()
+                .with_retry_policy(5, 20);
+            let other_task_ran = Arc::new(std::sync::atomic::AtomicBool::new(false));
+            let flag = other_task_ran.clone();
+            tokio::spawn(async move { flag.store(true, std::sync::atomic::Ordering::SeqCst) });
+            
+            // Only succeeds once the spawned task got to run during a backoff
+            client.with_retry(|| {
+                if other_task_ran.load(std::sync::atomic::Ordering::SeqCst) {
+                    Ok(())
+                } else {
+                    Err(ClientError::from(std::io::Error::new(std::io::ErrorKind::TimedOut, "timeout")))
+                }
+            })
+        })
+        .await
+        .unwrap();
+        
+        assert!(result.is_ok());
+    }
+    
+    #[test]
+    fn test_get_reserves_cached_hit_skips_fetch() {
+        // Nothing listens on this port, so any fetch would fail
+        let client = SolendRpcClient::new("http://127.0.0.1:1", "production")
+            .unwrap()
+            .with_retry_policy(1, 0);
+        let market = Pubkey::new_unique().to_string();
+        let cache = Mutex::new(ReserveCache::new(60));
+        
+        assert!(client.get_reserves_cached(&cache, &market).is_err());
+        
+        cache.lock().unwrap().insert(market.clone(), Vec::new());
+        let reserves = client.get_reserves_cached(&cache, &market).unwrap();
+        assert!(reserves.is_empty());
+    }
+    
+    #[test]
+    fn test_get_reserve_cached_until_invalidated() {
+        // Nothing listens on this port, so any fetch would fail
+        let client = SolendRpcClient::new("http://127.0.0.1:1", "production")
+            .unwrap()
+            .with_retry_policy(1, 0)
+            .with_reserve_cache_ttl(60);
+        let pubkey = Pubkey::new_unique();
+        
+        assert!(client.get_reserve_cached(&pubkey).is_err());
+        
+        let reserve = Reserve::parse(&vec![0u8; reserve::RESERVE_SIZE]).unwrap();
+        client.reserves.lock().unwrap().insert(pubkey, reserve);
+        assert!(client.get_reserve_cached(&pubkey).is_ok());
+        
+        client.invalidate_reserve(&pubkey);
+        assert!(client.get_reserve_cached(&pubkey).is_err());
+    }
+    
+    #[test]
+    fn test_get_token_accounts_by_owner_unpacks_accounts() {
+        use base64::Engine;
+        
+        let (owner, mint, token_account) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut data = vec![0u8; spl_token::state::Account::LEN];
+        spl_token::state::Account::pack(
+            spl_token::state::Account {
+                mint,
+                owner,
+                amount: 42,
+                state: spl_token::state::AccountState::Initialized,
+                ..Default::default()
+            },
+            &mut data,
+        )
+        .unwrap();
+        
+        let keyed_account = |pubkey: String, data: &[u8]| {
+            serde_json::json!({
+                "pubkey": pubkey,
+                "account": {
+                    "lamports": 2_039_280,
+                    "data": [base64::engine::general_purpose::STANDARD.encode(data), "base64"],
+                    "owner": spl_token::id().to_string(),
+                    "executable": false,
+                    "rentEpoch": 0,
+                    "space": data.len(),
+                },
+            })
+        };
+        let mut mocks = HashMap::new();
+        mocks.insert(
+            RpcRequest::GetTokenAccountsByOwner,
+            serde_json::json!({
+                "context": { "slot": 1 },
+                "value": [
+                    keyed_account(token_account.to_string(), &data),
+                    // Truncated data is skipped
+                    keyed_account(Pubkey::new_unique().to_string(), &data[..10]),
+                ],
+            }),
+        );
+        let client = SolendRpcClient {
+            client: RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks),
+            program_id: Pubkey::new_unique(),
+            read_commitment: CommitmentConfig::confirmed(),
+            max_attempts: 1,
+            retry_base_delay: Duration::ZERO,
+            timeout: DEFAULT_TIMEOUT,
+            limiter: None,
+            reserves: Mutex::new(Cache::new(0)),
+        };
+        
+        let accounts = client.get_token_accounts_by_owner(&owner).unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].0, token_account);
+        assert_eq!(accounts[0].1.mint, mint);
+        assert_eq!(accounts[0].1.amount, 42);
+    }
+    
+    #[test]
+    fn test_commitments_survive_rate_limit() {
+        let client = SolendRpcClient::new_with_commitment(
+            "http://localhost:8899",
+            "production",
+            CommitmentConfig::processed(),
+            CommitmentConfig::finalized(),
+        )
+        .unwrap()
+        .with_rate_limit(10);
+        
+        assert_eq!(client.read_commitment, CommitmentConfig::processed());
+        assert_eq!(client.client().commitment(), CommitmentConfig::finalized());
+    }
+    
+    #[test]
+    fn test_timeout_applies_with_or_without_rate_limit() {
+        // Accepts connections but never answers
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        
+        let clients = [
+            SolendRpcClient::new(&url, "production").unwrap().with_timeout(Duration::from_millis(200)),
+            SolendRpcClient::new(&url, "production")
+                .unwrap()
+                .with_timeout(Duration::from_millis(200))
+                .with_rate_limit(10),
+            SolendRpcClient::new(&url, "production")
+                .unwrap()
+                .with_rate_limit(10)
+                .with_timeout(Duration::from_millis(200)),
+        ];
+        assert!(clients[1].limiter.is_some() && clients[2].limiter.is_some());
+        
+        for client in clients {
+            let start = std::time::Instant::now();
+            assert!(client.client().get_slot().is_err());
+            assert!(start.elapsed() < Duration::from_secs(5));
+        }
+    }
+    
+    #[test]
+    fn test_rate_limited_sender_throttles_bursts() {
+        let sender = RateLimitedSender {
+            inner: solana_rpc_client::mock_sender::MockSender::new("succeeds"),
+            limiter: Arc::new(governor::RateLimiter::direct(
+                governor::Quota::per_second(NonZeroU32::new(10).unwrap()),
+            )),
+        };
+        let client = RpcClient::new_sender(sender, RpcClientConfig::default());
+        
+        // The first 10 calls fit the burst; the 11th waits ~100ms for a token
+        let start = std::time::Instant::now();
+        for _ in 0..11 {
+            client.get_slot().unwrap();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(80));
+    }
+    
+    #[test]
+    fn test_known_obligations_skip_healthy_until_resync() {
+        let sample = |slot: u64| Obligation {
+            version: 1,
+            last_update: crate::models::LastUpdate { slot, stale: false },
+            lending_market: Pubkey::default(),
+            owner: Pubkey::default(),
+            deposited_value: 0,
+            borrowed_value: 0,
+            allowed_borrow_value: 0,
+            unhealthy_borrow_value: 0,
+            deposits: Vec::new(),
+            borrows: Vec::new(),
+        };
+        let (a, b, c) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut known = KnownObligations::new(60);
+        let start = Instant::now();
+        
+        // The first epoch downloads everything
+        let pubkeys = vec![a, b];
+        assert_eq!(known.pubkeys_to_fetch(&pubkeys, start), pubkeys);
+        known.update(&pubkeys, vec![(a, Some(sample(1))), (b, Some(sample(1)))]);
+        known.set_healthy([a, c]);
+        
+        // `a` is reused, `b` is refetched and `c` is new; `a` and `b` are gone next
+        let pubkeys = vec![a, b, c];
+        assert_eq!(known.pubkeys_to_fetch(&pubkeys, start + Duration::from_secs(1)), vec![b, c]);
+        known.update(&pubkeys, vec![(b, Some(sample(2))), (c, None)]);
+        assert_eq!(known.obligations.len(), 2);
+        assert_eq!(known.obligations[&b].last_update.slot, 2);
+        
+        known.update(&[b], Vec::new());
+        assert_eq!(known.obligations().len(), 1);
+        assert!(known.healthy.is_empty());
+        
+        // A due resync refetches healthy obligations too
+        known.set_healthy([b]);
+        assert!(known.pubkeys_to_fetch(&[b], start + Duration::from_secs(1)).is_empty());
+        assert_eq!(known.pubkeys_to_fetch(&[b], start + Duration::from_secs(60)), vec![b]);
+    }
+    
+    #[test]
+    fn test_get_obligation_health_views_decodes_slices() {
+        use base64::Engine;
+        
+        let mut slice = vec![0u8; obligation::HEALTH_VIEW_LEN];
+        slice[..8].copy_from_slice(&123u64.to_le_bytes());
+        // borrowed_value sits 89 bytes into the slice (offset 90 in the account)
+        slice[89..105].copy_from_slice(&500u128.to_le_bytes());
+        let pubkey = Pubkey::new_unique();
+        
+        let mut mocks = HashMap::new();
+        mocks.insert(
+            RpcRequest::GetProgramAccounts,
+            serde_json::json!([
+                {
+                    "pubkey": pubkey.to_string(),
+                    "account": {
+                        "lamports": 10_000_000,
+                        "data": [base64::engine::general_purpose::STANDARD.encode(&slice), "base64"],
+                        "owner": Pubkey::new_unique().to_string(),
+                        "executable": false,
+                        "rentEpoch": 0,
+                        "space": obligation::OBLIGATION_SIZE,
+                    },
+                },
+            ]),
+        );
+        let client = SolendRpcClient {
+            client: RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks),
+            program_id: Pubkey::new_unique(),
+            read_commitment: CommitmentConfig::confirmed(),
+            max_attempts: 1,
+            retry_base_delay: Duration::ZERO,
+            timeout: DEFAULT_TIMEOUT,
+            limiter: None,
+            reserves: Mutex::new(Cache::new(0)),
+        };
+        
+        let views = client.get_obligation_health_views(&Pubkey::new_unique().to_string()).unwrap();
+        assert_eq!(views.len(), 1);
+        assert_eq!(views[0].0, pubkey);
+        assert_eq!(views[0].1.last_update.slot, 123);
+        assert_eq!(views[0].1.borrowed_value, 500);
+        assert!(!views[0].1.is_healthy());
+    }
+}
@@ -29,6 +29,38 @@ pub fn get_program_id(env: &str) -> Result<Pubkey> {
         .map_err(|e| anyhow!("Failed to parse program ID: {}", e))
 }
 
+/// Timeouts and connection pooling for the HTTP API clients (Solend markets, Jupiter)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HttpClientConfig {
+    pub connect_timeout: Duration,
+    /// Whole request, from connecting until the response body is read
+    pub timeout: Duration,
+    pub pool_max_idle_per_host: usize,
+}
+
+impl Default for HttpClientConfig {
+    /// Low enough that a flaky endpoint fails fast instead of stalling a market
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_millis(2_000),
+            timeout: Duration::from_millis(10_000),
+            pool_max_idle_per_host: 8,
+        }
+    }
+}
+
+/// Client builder for HTTP APIs; with the `compression` feature, responses may be
+/// gzip, brotli or deflate encoded and `Accept-Encoding` advertises all three
+pub fn http_client_builder(http: &HttpClientConfig) -> reqwest::ClientBuilder {
+    let builder = reqwest::Client::builder()
+        .connect_timeout(http.connect_timeout)
+        .timeout(http.timeout)
+        .pool_max_idle_per_host(http.pool_max_idle_per_host);
+    #[cfg(feature = "compression")]
+    let builder = builder.gzip(true).brotli(true).deflate(true);
+    builder
+}
+
 /// Convert base unit amount to human-readable format with decimals
 pub fn to_human(amount: u64, decimals: u8) -> Decimal {
     let amount_decimal = Decimal::from(amount);
@@ -36,6 +68,11 @@ pub fn to_human(amount: u64, decimals: u8) -> Decimal {
     amount_decimal / divisor
 }
 
+/// Base unit amount with its symbol for logs, e.g. "1.5 SOL"
+pub fn format_amount(amount: u64, decimals: u8, symbol: &str) -> String {
+    format!("{} {}", to_human(amount, decimals).normalize(), symbol)
+}
+
 /// Convert human-readable amount to base units
 pub fn to_base_unit(amount: &str, decimals: u8) -> Result<u64> {
     if amount == U64_MAX {
@@ -76,6 +113,13 @@ mod tests {
         assert_eq!(result, Decimal::new(5, 1));
     }
 
+    #[test]
+    fn test_format_amount() {
+        assert_eq!(format_amount(1_500_000, 6, "USDC"), "1.5 USDC");
+        assert_eq!(format_amount(2_000_000_000, 9, "SOL"), "2 SOL");
+        assert_eq!(format_amount(1, 9, "SOL"), "0.000000001 SOL");
+    }
+
     #[test]
     fn test_to_base_unit() {
         // 1 SOL to lamports
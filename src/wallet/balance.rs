@@ -1,32 +1,141 @@
 use anyhow::{anyhow, Result};
 use solana_client::rpc_client::RpcClient;
-use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{account::Account, program_pack::Pack, pubkey::Pubkey};
+use spl_token_2022::extension::StateWithExtensions;
 use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::sync::Mutex;
 
 use crate::utils::to_human;
 
+/// Token programs a wallet's token accounts may belong to
+pub const TOKEN_PROGRAMS: [Pubkey; 2] = [spl_token::ID, spl_token_2022::ID];
+
 /// Get associated token address for a mint and wallet
+/// `token_program` must be the program owning the mint (SPL Token or Token-2022)
 pub fn find_associated_token_address(
     wallet_address: &Pubkey,
     token_mint_address: &Pubkey,
+    token_program: &Pubkey,
 ) -> Pubkey {
-    spl_associated_token_account::get_associated_token_address(
+    spl_associated_token_account::get_associated_token_address_with_program_id(
         wallet_address,
         token_mint_address,
+        token_program,
     )
 }
 
+/// Token program owning each of `mints` (SPL Token or Token-2022), in order
+/// ATAs must be derived and created under this program, or they won't match the mint
+pub fn mint_token_programs(client: &RpcClient, mints: &[Pubkey]) -> Result<Vec<Pubkey>> {
+    let accounts = client
+        .get_multiple_accounts(mints)
+        .map_err(|e| anyhow!("Failed to get mint accounts: {}", e))?;
+    
+    mints
+        .iter()
+        .zip(accounts)
+        .map(|(mint, account)| match account {
+            Some(account) if TOKEN_PROGRAMS.contains(&account.owner) => Ok(account.owner),
+            Some(account) => Err(anyhow!("Mint {} is owned by {}, not a token program", mint, account.owner)),
+            None => Err(anyhow!("Mint {} not found", mint)),
+        })
+        .collect()
+}
+
+/// Token amount held by an SPL Token or Token-2022 account
+/// Token-2022 accounts start with the SPL Token layout, followed by extensions
+fn unpack_token_amount(account: &Account) -> Option<u64> {
+    if account.owner == spl_token_2022::ID {
+        StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data)
+            .ok()
+            .map(|state| state.base.amount)
+    } else {
+        spl_token::state::Account::unpack(&account.data)
+            .ok()
+            .map(|token_account| token_account.amount)
+    }
+}
+
+/// Decimals of an SPL Token or Token-2022 mint account
+fn unpack_mint_decimals(account: &Account) -> Option<u8> {
+    if account.owner == spl_token_2022::ID {
+        StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&account.data)
+            .ok()
+            .map(|state| state.base.decimals)
+    } else {
+        spl_token::state::Mint::unpack(&account.data)
+            .ok()
+            .map(|mint| mint.decimals)
+    }
+}
+
+/// Token decimals per mint, read from the mint account when market configs don't list it
+/// Decimals are fixed at mint creation, so entries never expire
+#[derive(Default)]
+pub struct MintDecimalsCache {
+    decimals: Mutex<HashMap<Pubkey, u8>>,
+}
+
+impl MintDecimalsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Record decimals already known from a market config
+    pub fn insert(&self, mint: Pubkey, decimals: u8) {
+        if let Ok(mut cache) = self.decimals.lock() {
+            cache.insert(mint, decimals);
+        }
+    }
+    
+    pub async fn get(&self, client: &crate::rpc::SolendRpcClient, mint: &Pubkey) -> Result<u8> {
+        Ok(self.get_many(client, std::slice::from_ref(mint)).await?[mint])
+    }
+    
+    /// Decimals for every mint, fetching unknown mint accounts in one batch
+    /// The lock is not held across the fetch so markets don't serialize on it
+    pub async fn get_many(
+        &self,
+        client: &crate::rpc::SolendRpcClient,
+        mints: &[Pubkey],
+    ) -> Result<HashMap<Pubkey, u8>> {
+        let missing: Vec<Pubkey> = {
+            let cache = self.decimals.lock().map_err(|_| anyhow!("Mint decimals cache poisoned"))?;
+            mints.iter().filter(|mint| !cache.contains_key(mint)).copied().collect()
+        };
+        
+        if !missing.is_empty() {
+            let accounts = client.get_multiple_accounts_batched(&missing, 100).await?;
+            let mut cache = self.decimals.lock().map_err(|_| anyhow!("Mint decimals cache poisoned"))?;
+            for (mint, account) in missing.iter().zip(accounts) {
+                let decimals = account
+                    .as_ref()
+                    .and_then(unpack_mint_decimals)
+                    .ok_or_else(|| anyhow!("Failed to read decimals of mint {}", mint))?;
+                log::debug!("Mint {} has {} decimals", mint, decimals);
+                cache.insert(*mint, decimals);
+            }
+        }
+        
+        let cache = self.decimals.lock().map_err(|_| anyhow!("Mint decimals cache poisoned"))?;
+        Ok(mints.iter().map(|mint| (*mint, cache[mint])).collect())
+    }
+}
+
 /// Get wallet token balance
+/// The mint's token program isn't known here, so both ATAs are tried
 pub fn get_wallet_token_balance(
     client: &RpcClient,
     mint: &Pubkey,
     wallet_address: &Pubkey,
     decimals: u8,
 ) -> Result<(u64, Decimal)> {
-    let ata = find_associated_token_address(wallet_address, mint);
-    
-    match client.get_token_account_balance(&ata) {
-        Ok(token_amount) => {
+    for token_program in &TOKEN_PROGRAMS {
+        let ata = find_associated_token_address(wallet_address, mint, token_program);
+        
+        // Account doesn't exist or error fetching; try the other program
+        if let Ok(token_amount) = client.get_token_account_balance(&ata) {
             let balance_base = token_amount
                 .amount
                 .parse::<u64>()
@@ -34,13 +143,11 @@ pub fn get_wallet_token_balance(
             
             let balance_human = to_human(balance_base, decimals);
             
-            Ok((balance_base, balance_human))
-        }
-        Err(_) => {
-            // Account doesn't exist or error fetching
-            Ok((0, Decimal::ZERO))
+            return Ok((balance_base, balance_human));
         }
     }
+    
+    Ok((0, Decimal::ZERO))
 }
 
 /// Get multiple wallet token balances in batches
@@ -49,47 +156,95 @@ pub async fn get_wallet_token_balances_batched(
     client: &crate::rpc::SolendRpcClient,
     wallet_address: &Pubkey,
     mints: &[Pubkey],
-    decimals_map: &std::collections::HashMap<Pubkey, u8>,
-) -> Result<std::collections::HashMap<Pubkey, (u64, Decimal)>> {
-    use solana_sdk::program_pack::Pack;
-    use spl_token::state::Account as TokenAccount;
+    mint_decimals: &MintDecimalsCache,
+) -> Result<HashMap<Pubkey, (u64, Decimal)>> {
+    let decimals_map = mint_decimals.get_many(client, mints).await?;
     
-    // 1. Derive ATAs for all mints
+    // 1. Derive the SPL Token and Token-2022 ATAs for all mints
+    // Only the one under the mint's owning program can exist
     let atas: Vec<Pubkey> = mints
         .iter()
-        .map(|mint| find_associated_token_address(wallet_address, mint))
+        .flat_map(|mint| {
+            TOKEN_PROGRAMS
+                .iter()
+                .map(move |token_program| find_associated_token_address(wallet_address, mint, token_program))
+        })
         .collect();
         
     // 2. Batch fetch all ATAs
     // Use the batched method we added to RpcClient
     let accounts = client.get_multiple_accounts_batched(&atas, 100).await?;
     
-    let mut results = std::collections::HashMap::new();
+    let mut results = HashMap::new();
     
     // 3. Parse accounts
-    for (i, account_opt) in accounts.iter().enumerate() {
-        let mint = &mints[i];
-        let decimals = *decimals_map.get(mint).unwrap_or(&9); // Default to 9 if not found
-        
-        let (balance_base, balance_human) = if let Some(account) = account_opt {
-            if let Ok(token_account) = TokenAccount::unpack(&account.data) {
-                let amount = token_account.amount;
-                (amount, to_human(amount, decimals))
-            } else {
-                // Failed to unpack
-                (0, Decimal::ZERO)
-            }
-        } else {
-            // Account doesn't exist (balance 0)
-            (0, Decimal::ZERO)
-        };
+    for (mint, candidates) in mints.iter().zip(accounts.chunks(TOKEN_PROGRAMS.len())) {
+        let decimals = decimals_map[mint];
         
-        results.insert(*mint, (balance_base, balance_human));
+        // Missing or unparseable accounts count as a zero balance
+        let balance_base = candidates
+            .iter()
+            .flatten()
+            .find_map(unpack_token_amount)
+            .unwrap_or(0);
+        
+        results.insert(*mint, (balance_base, to_human(balance_base, decimals)));
     }
     
     Ok(results)
 }
 
+/// Wallet token balances for one epoch, read at most once per mint
+/// Mints a liquidation touched are invalidated so only they get re-read
+pub struct WalletBalances {
+    owner: Pubkey,
+    balances: HashMap<Pubkey, (u64, Decimal)>,
+}
+
+impl WalletBalances {
+    pub fn new(owner: Pubkey) -> Self {
+        Self {
+            owner,
+            balances: HashMap::new(),
+        }
+    }
+    
+    /// Batch-read every mint not cached yet
+    /// A failed batch leaves those mints to per-mint reads in `get`
+    pub async fn prefetch(
+        &mut self,
+        client: &crate::rpc::SolendRpcClient,
+        mints: &[Pubkey],
+        mint_decimals: &MintDecimalsCache,
+    ) {
+        let missing: Vec<Pubkey> = mints.iter().filter(|mint| !self.balances.contains_key(mint)).copied().collect();
+        if missing.is_empty() {
+            return;
+        }
+        
+        match get_wallet_token_balances_batched(client, &self.owner, &missing, mint_decimals).await {
+            Ok(balances) => self.balances.extend(balances),
+            Err(e) => log::warn!("Batched wallet balance fetch failed: {}", e),
+        }
+    }
+    
+    /// Balance of `mint` as (base units, human units), read on a miss
+    pub fn get(&mut self, client: &RpcClient, mint: &Pubkey, decimals: u8) -> Result<(u64, Decimal)> {
+        if let Some(balance) = self.balances.get(mint) {
+            return Ok(*balance);
+        }
+        
+        let balance = get_wallet_token_balance(client, mint, &self.owner, decimals)?;
+        self.balances.insert(*mint, balance);
+        Ok(balance)
+    }
+    
+    /// Forget `mint` so the next `get` re-reads it
+    pub fn invalidate(&mut self, mint: &Pubkey) {
+        self.balances.remove(mint);
+    }
+}
+
 /// Wallet balance data
 #[derive(Debug, Clone)]
 pub struct WalletTokenData {
@@ -108,9 +263,108 @@ mod tests {
         let wallet = Pubkey::from_str("11111111111111111111111111111111").unwrap();
         let mint = Pubkey::from_str("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v").unwrap(); // USDC
         
-        let ata = find_associated_token_address(&wallet, &mint);
+        let ata = find_associated_token_address(&wallet, &mint, &spl_token::ID);
         
         // ATA should be derived deterministically
         assert_ne!(ata, Pubkey::default());
+        assert_eq!(ata, spl_associated_token_account::get_associated_token_address(&wallet, &mint));
+        assert_ne!(ata, find_associated_token_address(&wallet, &mint, &spl_token_2022::ID));
+    }
+    
+    #[test]
+    fn test_unpack_token_2022_account_with_extensions() {
+        use spl_token_2022::extension::{ExtensionType, StateWithExtensionsMut};
+        
+        let mint = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let len = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Account>(&[
+            ExtensionType::ImmutableOwner,
+        ])
+        .unwrap();
+        let mut data = vec![0u8; len];
+        
+        let mut state =
+            StateWithExtensionsMut::<spl_token_2022::state::Account>::unpack_uninitialized(&mut data).unwrap();
+        state.init_extension::<spl_token_2022::extension::immutable_owner::ImmutableOwner>(true).unwrap();
+        state.base = spl_token_2022::state::Account {
+            mint,
+            owner,
+            amount: 1_234,
+            state: spl_token_2022::state::AccountState::Initialized,
+            ..Default::default()
+        };
+        state.pack_base();
+        state.init_account_type().unwrap();
+        
+        let account = Account {
+            lamports: 1,
+            data,
+            owner: spl_token_2022::ID,
+            executable: false,
+            rent_epoch: 0,
+        };
+        assert!(account.data.len() > spl_token::state::Account::LEN);
+        assert_eq!(unpack_token_amount(&account), Some(1_234));
+        
+        // The same buffer under the SPL Token program is rejected by its strict length check
+        let classic = Account { owner: spl_token::ID, ..account };
+        assert_eq!(unpack_token_amount(&classic), None);
+    }
+    
+    #[test]
+    fn test_unpack_mint_decimals() {
+        let mut data = vec![0u8; spl_token::state::Mint::LEN];
+        spl_token::state::Mint::pack(
+            spl_token::state::Mint { decimals: 6, is_initialized: true, ..Default::default() },
+            &mut data,
+        )
+        .unwrap();
+        let classic = Account { lamports: 1, data, owner: spl_token::ID, executable: false, rent_epoch: 0 };
+        assert_eq!(unpack_mint_decimals(&classic), Some(6));
+        
+        // A Token-2022 mint without extensions shares the base layout
+        let token_2022 = Account { owner: spl_token_2022::ID, ..classic.clone() };
+        assert_eq!(unpack_mint_decimals(&token_2022), Some(6));
+        
+        let uninitialized = Account { data: vec![0u8; spl_token::state::Mint::LEN], ..classic };
+        assert_eq!(unpack_mint_decimals(&uninitialized), None);
+    }
+    
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_mint_decimals_cache_hit_skips_fetch() {
+        // Nothing listens on this port, so any fetch would fail
+        let client = crate::rpc::SolendRpcClient::new("http://127.0.0.1:1", "production")
+            .unwrap()
+            .with_retry_policy(1, 0);
+        let (known, unknown) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let cache = MintDecimalsCache::new();
+        cache.insert(known, 6);
+        
+        assert_eq!(cache.get(&client, &known).await.unwrap(), 6);
+        assert!(cache.get_many(&client, &[known, unknown]).await.is_err());
+    }
+    
+    #[test]
+    fn test_wallet_balances_read_once_until_invalidated() {
+        use solana_client::rpc_request::RpcRequest;
+        
+        // Mocks answer once, later reads find no token account
+        let mut mocks = HashMap::new();
+        mocks.insert(
+            RpcRequest::GetTokenAccountBalance,
+            serde_json::json!({
+                "context": { "slot": 1 },
+                "value": { "amount": "1500000", "decimals": 6, "uiAmount": 1.5, "uiAmountString": "1.5" },
+            }),
+        );
+        let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+        let mint = Pubkey::new_unique();
+        let mut balances = WalletBalances::new(Pubkey::new_unique());
+        
+        assert_eq!(balances.get(&client, &mint, 6).unwrap(), (1_500_000, Decimal::new(15, 1)));
+        assert_eq!(balances.get(&client, &mint, 6).unwrap(), (1_500_000, Decimal::new(15, 1)));
+        
+        balances.invalidate(&mint);
+        assert_eq!(balances.get(&client, &mint, 6).unwrap(), (0, Decimal::ZERO));
     }
 }
@@ -1,9 +1,16 @@
 pub mod balance;
 pub mod swap;
 pub mod rebalance;
+pub mod sweep;
 pub mod unwrap;
+pub mod wsol;
 
-pub use balance::{get_wallet_token_balance, get_wallet_token_balances_batched, find_associated_token_address};
+pub use balance::{
+    get_wallet_token_balance, get_wallet_token_balances_batched, find_associated_token_address, mint_token_programs,
+    MintDecimalsCache, WalletBalances,
+};
 pub use swap::JupiterClient;
 pub use rebalance::rebalance_wallet;
+pub use sweep::sweep_dust;
 pub use unwrap::unwrap_all_wrapped_tokens;
+pub use wsol::{close_wsol_account, wrap_sol_for_repay};
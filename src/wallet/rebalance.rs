@@ -3,40 +3,48 @@ use log::{info, warn};
 use rust_decimal::prelude::ToPrimitive;
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::{pubkey::Pubkey, signature::{Keypair, Signer}};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use crate::models::market::TokenCount;
+use crate::models::market::{SlippageConfig, TokenCount};
 use crate::wallet::balance::get_wallet_token_balance;
-use crate::wallet::swap::{get_usdc_mint, JupiterClient};
+use crate::wallet::swap::JupiterClient;
+
+/// Token rebalancing swaps through unless REBALANCE_BASE_TOKEN says otherwise
+pub const DEFAULT_BASE_TOKEN: &str = "USDC";
+
+/// Token every rebalancing swap buys with or sells into
+#[derive(Debug, Clone, PartialEq)]
+pub struct BaseToken {
+    pub symbol: String,
+    pub mint: Pubkey,
+}
 
 /// Calculate which tokens need rebalancing
+/// The base token itself is never rebalanced, it funds the others
 pub fn calculate_rebalance_needed(
     current_balances: &HashMap<String, f64>,
     targets: &[TokenCount],
     padding: f64,
+    base_symbol: &str,
 ) -> Vec<(String, f64, bool)> {
     let mut rebalance_actions = Vec::new();
     
     for target in targets {
         let current = current_balances.get(&target.symbol).copied().unwrap_or(0.0);
         let target_amount = target.target;
+        let (lower_bound, upper_bound) = target.bounds(padding);
         
-        // Calculate bounds with padding
-        let lower_bound = target_amount * (1.0 - padding);
-        let upper_bound = target_amount * (1.0 + padding);
-        
-        // Skip USDC as it's the base token
-        if target.symbol == "USDC" {
+        if target.symbol == base_symbol {
             continue;
         }
         
         // Check if rebalancing is needed
         if current < lower_bound {
-            // Need to buy (swap USDC -> token)
+            // Need to buy (swap base -> token)
             let amount_needed = target_amount - current;
             rebalance_actions.push((target.symbol.clone(), amount_needed, true));
         } else if current > upper_bound {
-            // Need to sell (swap token -> USDC)
+            // Need to sell (swap token -> base)
             let amount_to_sell = current - target_amount;
             rebalance_actions.push((target.symbol.clone(), amount_to_sell, false));
         }
@@ -45,14 +53,28 @@ pub fn calculate_rebalance_needed(
     rebalance_actions
 }
 
+/// Base token input needed to buy a token amount, given what selling that
+/// amount would return (`reverse_out_amount`)
+/// Padded by the slippage tolerance to cover the buy/sell spread
+pub fn base_input_for_buy(reverse_out_amount: u64, slippage_bps: u16) -> u64 {
+    let padded = reverse_out_amount as u128 * (10_000 + slippage_bps as u128) / 10_000;
+    padded.min(u64::MAX as u128) as u64
+}
+
 /// Rebalance wallet to maintain target distribution
+/// Tokens are bought with and sold into `base`
+#[allow(clippy::too_many_arguments)]
 pub async fn rebalance_wallet(
     rpc_client: &RpcClient,
     payer: &Keypair,
-    env: &str,
+    base: &BaseToken,
     targets: &[TokenCount],
     padding: f64,
     token_mints: &HashMap<String, (Pubkey, u8)>, // symbol -> (mint, decimals)
+    jupiter: &JupiterClient,
+    slippage: &SlippageConfig,
+    priority_fee_lamports: Option<u64>,
+    max_price_impact_pct: f64,
 ) -> Result<()> {
     if targets.is_empty() {
         return Ok(());
@@ -60,21 +82,25 @@ pub async fn rebalance_wallet(
     
     info!("Starting wallet rebalancing...");
     
-    // Get current balances
+    // Get current balances; a token whose balance can't be read is left alone this round
     let mut current_balances = HashMap::new();
+    let mut unreadable = HashSet::new();
     for (symbol, (mint, decimals)) in token_mints {
-        let (_, balance_decimal) = get_wallet_token_balance(
-            rpc_client,
-            mint,
-            &payer.pubkey(),
-            *decimals,
-        )?;
+        let balance_decimal = match get_wallet_token_balance(rpc_client, mint, &payer.pubkey(), *decimals) {
+            Ok((_, balance)) => balance,
+            Err(e) => {
+                warn!("  Skipping {}: failed to read wallet balance: {}", symbol, e);
+                unreadable.insert(symbol.as_str());
+                continue;
+            }
+        };
         let balance_f64 = balance_decimal.to_f64().unwrap_or(0.0);
         current_balances.insert(symbol.clone(), balance_f64);
     }
     
     // Calculate rebalancing actions
-    let actions = calculate_rebalance_needed(&current_balances, targets, padding);
+    let mut actions = calculate_rebalance_needed(&current_balances, targets, padding, &base.symbol);
+    actions.retain(|(symbol, _, _)| !unreadable.contains(symbol.as_str()));
     
     if actions.is_empty() {
         info!("✓ Wallet is balanced, no rebalancing needed");
@@ -83,46 +109,55 @@ pub async fn rebalance_wallet(
     
     info!("Rebalancing {} tokens", actions.len());
     
-    let jupiter = JupiterClient::new();
-    let usdc_mint = get_usdc_mint(env)?;
-    
     // Execute rebalancing swaps
     for (symbol, amount, is_buy) in actions {
         let (token_mint, decimals) = token_mints
             .get(&symbol)
             .ok_or_else(|| anyhow!("Token {} not found in mint map", symbol))?;
+        let slippage_bps = slippage.bps_for(&symbol);
+        let token_amount = (amount * 10f64.powi(*decimals as i32)) as u64;
         
         if is_buy {
-            info!("  Buying {:.4} {} (swapping USDC)", amount, symbol);
+            info!("  Buying {:.4} {} (swapping {})", amount, symbol, base.symbol);
             
-            // Calculate USDC amount needed (approximate)
-            // In production, you'd get a quote first to determine exact amount
-            let usdc_decimals = 6;
-            let usdc_amount = (amount * 100.0 * 10f64.powi(usdc_decimals as i32)) as u64; // Rough estimate
+            // Price the deficit by quoting the reverse direction (token -> base)
+            let base_amount = match jupiter
+                .get_quote(token_mint, &base.mint, token_amount, slippage_bps)
+                .await
+                .and_then(|quote| Ok(quote.out_amount.parse::<u64>()?))
+            {
+                Ok(reverse_out_amount) => base_input_for_buy(reverse_out_amount, slippage_bps),
+                Err(e) => {
+                    warn!("    ✗ Skipping {}: no price quote available: {}", symbol, e);
+                    continue;
+                }
+            };
             
             match jupiter.swap(
                 rpc_client,
                 payer,
-                &usdc_mint,
+                &base.mint,
                 token_mint,
-                usdc_amount,
-                100, // 1% slippage
+                base_amount,
+                slippage_bps,
+                priority_fee_lamports,
+                max_price_impact_pct,
             ).await {
                 Ok(sig) => info!("    ✓ Bought {} (sig: {})", symbol, sig),
                 Err(e) => warn!("    ✗ Failed to buy {}: {}", symbol, e),
             }
         } else {
-            info!("  Selling {:.4} {} (swapping to USDC)", amount, symbol);
-            
-            let token_amount = (amount * 10f64.powi(*decimals as i32)) as u64;
+            info!("  Selling {:.4} {} (swapping to {})", amount, symbol, base.symbol);
             
             match jupiter.swap(
                 rpc_client,
                 payer,
                 token_mint,
-                &usdc_mint,
+                &base.mint,
                 token_amount,
-                100, // 1% slippage
+                slippage_bps,
+                priority_fee_lamports,
+                max_price_impact_pct,
             ).await {
                 Ok(sig) => info!("    ✓ Sold {} (sig: {})", symbol, sig),
                 Err(e) => warn!("    ✗ Failed to sell {}: {}", symbol, e),
@@ -145,12 +180,12 @@ mod tests {
         current.insert("USDT".to_string(), 60.0);
         
         let targets = vec![
-            TokenCount { symbol: "USDC".to_string(), target: 100.0 },
-            TokenCount { symbol: "SOL".to_string(), target: 1.0 },
-            TokenCount { symbol: "USDT".to_string(), target: 50.0 },
+            TokenCount { symbol: "USDC".to_string(), target: 100.0, min: None, max: None },
+            TokenCount { symbol: "SOL".to_string(), target: 1.0, min: None, max: None },
+            TokenCount { symbol: "USDT".to_string(), target: 50.0, min: None, max: None },
         ];
         
-        let actions = calculate_rebalance_needed(&current, &targets, 0.2);
+        let actions = calculate_rebalance_needed(&current, &targets, 0.2, DEFAULT_BASE_TOKEN);
         
         // SOL: current 0.3, target 1.0, lower bound 0.8 -> need to buy
         // USDT: current 60.0, target 50.0, upper bound 60.0 -> at boundary, no action
@@ -159,18 +194,108 @@ mod tests {
         assert!(actions[0].2); // is_buy
     }
     
+    #[test]
+    fn test_non_usdc_base_is_skipped_instead_of_usdc() {
+        let mut current = HashMap::new();
+        current.insert("USDC".to_string(), 20.0);
+        current.insert("USDT".to_string(), 5.0);
+        
+        let targets = vec![
+            TokenCount { symbol: "USDC".to_string(), target: 100.0, min: None, max: None },
+            TokenCount { symbol: "USDT".to_string(), target: 500.0, min: None, max: None },
+        ];
+        
+        // With USDT as the base, its shortfall funds the rest and USDC gets topped up
+        let actions = calculate_rebalance_needed(&current, &targets, 0.2, "USDT");
+        assert_eq!(actions, vec![("USDC".to_string(), 80.0, true)]);
+        
+        let actions = calculate_rebalance_needed(&current, &targets, 0.2, DEFAULT_BASE_TOKEN);
+        assert_eq!(actions, vec![("USDT".to_string(), 495.0, true)]);
+    }
+    
+    #[test]
+    fn test_base_input_for_buy() {
+        // Selling the deficit returns 150 of the base token; 1% slippage pads the buy
+        assert_eq!(base_input_for_buy(150_000_000, 100), 151_500_000);
+        assert_eq!(base_input_for_buy(150_000_000, 0), 150_000_000);
+        assert_eq!(base_input_for_buy(u64::MAX, 100), u64::MAX);
+    }
+    
     #[test]
     fn test_no_rebalance_within_threshold() {
         let mut current = HashMap::new();
         current.insert("SOL".to_string(), 0.9);
         
         let targets = vec![
-            TokenCount { symbol: "SOL".to_string(), target: 1.0 },
+            TokenCount { symbol: "SOL".to_string(), target: 1.0, min: None, max: None },
         ];
         
-        let actions = calculate_rebalance_needed(&current, &targets, 0.2);
+        let actions = calculate_rebalance_needed(&current, &targets, 0.2, DEFAULT_BASE_TOKEN);
         
         // 0.9 is within [0.8, 1.2], no rebalancing needed
         assert_eq!(actions.len(), 0);
     }
+    
+    #[test]
+    fn test_absolute_bounds_override_padding() {
+        let mut current = HashMap::new();
+        current.insert("SOL".to_string(), 0.85);
+        current.insert("USDT".to_string(), 130.0);
+        current.insert("ETH".to_string(), 0.7);
+        
+        let targets = vec![
+            // Keep SOL between 0.9 and 1.5, although 20% padding would accept 0.85
+            TokenCount { symbol: "SOL".to_string(), target: 1.0, min: Some(0.9), max: Some(1.5) },
+            // 30% over target, but under the absolute maximum
+            TokenCount { symbol: "USDT".to_string(), target: 100.0, min: Some(50.0), max: Some(150.0) },
+            // Only the upper bound is absolute; the lower one still comes from the padding
+            TokenCount { symbol: "ETH".to_string(), target: 1.0, min: None, max: Some(2.0) },
+        ];
+        
+        let actions = calculate_rebalance_needed(&current, &targets, 0.2, DEFAULT_BASE_TOKEN);
+        
+        assert_eq!(actions.len(), 2);
+        assert_eq!(actions[0].0, "SOL");
+        assert!((actions[0].1 - 0.15).abs() < 1e-9);
+        assert!(actions[0].2);
+        assert_eq!(actions[1].0, "ETH");
+        assert!((actions[1].1 - 0.3).abs() < 1e-9);
+        assert!(actions[1].2);
+    }
+    
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_unreadable_balance_skips_the_token() {
+        use solana_client::rpc_request::RpcRequest;
+        
+        // Every balance read returns an amount that doesn't parse
+        let mut mocks = HashMap::new();
+        mocks.insert(
+            RpcRequest::GetTokenAccountBalance,
+            serde_json::json!({
+                "context": { "slot": 1 },
+                "value": { "amount": "garbage", "decimals": 9, "uiAmount": null, "uiAmountString": "" },
+            }),
+        );
+        let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+        let token_mints = HashMap::from([("SOL".to_string(), (Pubkey::new_unique(), 9))]);
+        let base = BaseToken { symbol: DEFAULT_BASE_TOKEN.to_string(), mint: Pubkey::new_unique() };
+        let targets = vec![TokenCount { symbol: "SOL".to_string(), target: 1.0, min: None, max: None }];
+        let slippage = SlippageConfig { default_bps: 100, per_token: HashMap::new() };
+        
+        // SOL is left alone rather than read as empty and bought, and the rebalance goes on
+        rebalance_wallet(
+            &client,
+            &Keypair::new(),
+            &base,
+            &targets,
+            0.2,
+            &token_mints,
+            &JupiterClient::with_config("http://127.0.0.1:1", None),
+            &slippage,
+            None,
+            1.0,
+        )
+        .await
+        .unwrap();
+    }
 }
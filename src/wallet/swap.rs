@@ -1,16 +1,37 @@
 use anyhow::{anyhow, Result};
 use reqwest;
 use serde::{Deserialize, Serialize};
+use solana_client::client_error::ClientError;
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::{
+    hash::Hash,
+    instruction::InstructionError,
     pubkey::Pubkey,
     signature::{Keypair, Signature, Signer},
-    transaction::Transaction,
+    transaction::{TransactionError, VersionedTransaction},
 };
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
-/// Jupiter API v6 base URL
-const JUPITER_API_URL: &str = "https://quote-api.jup.ag/v6";
+use crate::rpc::blockhash::{BlockhashExpired, BlockhashManager};
+use crate::utils::{http_client_builder, HttpClientConfig};
+
+/// Public Jupiter API v6 base URL
+pub const JUPITER_API_URL: &str = "https://quote-api.jup.ag/v6";
+
+/// Header carrying the key for the paid Jupiter API
+const API_KEY_HEADER: &str = "x-api-key";
+
+/// Default attempts for `swap`, each with a fresh quote
+pub const DEFAULT_MAX_SWAP_ATTEMPTS: u32 = 3;
+const SWAP_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Jupiter program error when the output falls below the quote's minimum
+const SLIPPAGE_TOLERANCE_EXCEEDED: u32 = 0x1771;
+
+/// Default `max_price_impact_pct`, in percent
+pub const DEFAULT_MAX_PRICE_IMPACT_PCT: f64 = 1.0;
 
 /// Jupiter quote response
 #[derive(Debug, Deserialize, Serialize)]
@@ -40,6 +61,10 @@ struct SwapRequest {
     user_public_key: String,
     #[serde(rename = "wrapAndUnwrapSol")]
     wrap_and_unwrap_sol: bool,
+    #[serde(rename = "asLegacyTransaction")]
+    as_legacy_transaction: bool,
+    #[serde(rename = "prioritizationFeeLamports", skip_serializing_if = "Option::is_none")]
+    prioritization_fee_lamports: Option<u64>,
 }
 
 /// Jupiter swap response
@@ -53,14 +78,52 @@ struct SwapResponse {
 pub struct JupiterClient {
     client: reqwest::Client,
     api_url: String,
+    api_key: Option<String>,
+    max_swap_attempts: u32,
+    blockhashes: Option<Arc<BlockhashManager>>,
 }
 
 impl JupiterClient {
-    /// Create a new Jupiter client
+    /// Create a new Jupiter client for the public endpoint
     pub fn new() -> Self {
+        Self::with_config(JUPITER_API_URL, None)
+    }
+    
+    /// Client for a self-hosted or paid Jupiter API
+    /// `api_key` is sent as `x-api-key` on every request when set
+    pub fn with_config(base_url: &str, api_key: Option<String>) -> Self {
         Self {
-            client: reqwest::Client::new(),
-            api_url: JUPITER_API_URL.to_string(),
+            client: http_client(&HttpClientConfig::default()),
+            api_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            max_swap_attempts: DEFAULT_MAX_SWAP_ATTEMPTS,
+            blockhashes: None,
+        }
+    }
+    
+    /// Override the default HTTP timeouts and connection pool
+    pub fn with_http_config(mut self, http: &HttpClientConfig) -> Self {
+        self.client = http_client(http);
+        self
+    }
+    
+    /// Attempts `swap` makes when a route expires before landing (at least 1)
+    pub fn with_max_swap_attempts(mut self, max_swap_attempts: u32) -> Self {
+        self.max_swap_attempts = max_swap_attempts.max(1);
+        self
+    }
+    
+    /// Sign swaps with the shared blockhash instead of the one Jupiter set
+    pub fn with_blockhashes(mut self, blockhashes: Arc<BlockhashManager>) -> Self {
+        self.blockhashes = Some(blockhashes);
+        self
+    }
+    
+    /// Attach the API key, if any
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.api_key {
+            Some(key) => request.header(API_KEY_HEADER, key),
+            None => request,
         }
     }
     
@@ -83,8 +146,7 @@ impl JupiterClient {
         
         log::debug!("Fetching Jupiter quote: {}", url);
         
-        let response = self.client
-            .get(&url)
+        let response = self.authorize(self.client.get(&url))
             .send()
             .await
             .map_err(|e| anyhow!("Failed to fetch Jupiter quote: {}", e))?;
@@ -103,36 +165,45 @@ impl JupiterClient {
             .map_err(|e| anyhow!("Failed to parse Jupiter quote: {}", e))?;
         
         log::info!(
-            "Jupiter quote: {} {} -> {} {} (impact: {}%)",
+            "Jupiter quote: {} {} -> {} {} (impact: {:.4}%)",
             quote.in_amount,
             input_mint,
             quote.out_amount,
             output_mint,
-            quote.price_impact_pct
+            quote.price_impact_pct.parse::<f64>().unwrap_or(0.0) * 100.0
         );
         
         Ok(quote)
     }
     
     /// Execute a swap transaction
+    /// `priority_fee_lamports` is passed to Jupiter; `None` keeps its default
+    /// Quotes with more than `max_price_impact_pct` impact, or a minimum out below
+    /// what `slippage_bps` allows, are refused
     pub async fn execute_swap(
         &self,
         rpc_client: &RpcClient,
         payer: &Keypair,
         quote: QuoteResponse,
+        slippage_bps: u16,
+        priority_fee_lamports: Option<u64>,
+        max_price_impact_pct: f64,
     ) -> Result<Signature> {
+        check_quote(&quote, slippage_bps, max_price_impact_pct)?;
+        
         let swap_request = SwapRequest {
             quote_response: quote,
             user_public_key: payer.pubkey().to_string(),
             wrap_and_unwrap_sol: true,
+            as_legacy_transaction: false,
+            prioritization_fee_lamports: priority_fee_lamports,
         };
         
         let url = format!("{}/swap", self.api_url);
         
         log::debug!("Requesting Jupiter swap transaction");
         
-        let response = self.client
-            .post(&url)
+        let response = self.authorize(self.client.post(&url))
             .json(&swap_request)
             .send()
             .await
@@ -157,22 +228,30 @@ impl JupiterClient {
             .decode(&swap_response.swap_transaction)
             .map_err(|e| anyhow!("Failed to decode swap transaction: {}", e))?;
         
-        let mut transaction: Transaction = bincode::deserialize(&transaction_bytes)
-            .map_err(|e| anyhow!("Failed to deserialize swap transaction: {}", e))?;
-        
-        // Sign the transaction
-        let recent_blockhash = rpc_client.get_latest_blockhash()?;
-        transaction.partial_sign(&[payer], recent_blockhash);
+        let blockhash = self.blockhashes
+            .as_ref()
+            .map(|blockhashes| blockhashes.get_fresh_blockhash(rpc_client))
+            .transpose()?;
+        let transaction = sign_swap_transaction(&transaction_bytes, payer, blockhash)?;
         
         // Send and confirm
-        let signature = rpc_client.send_and_confirm_transaction(&transaction)?;
+        let signature = rpc_client.send_and_confirm_transaction(&transaction).map_err(|e| {
+            if !is_blockhash_expired(&e) {
+                return anyhow::Error::from(e);
+            }
+            if let Some(blockhashes) = &self.blockhashes {
+                blockhashes.invalidate();
+            }
+            BlockhashExpired(*transaction.message.recent_blockhash()).into()
+        })?;
         
         log::info!("Jupiter swap successful! Signature: {}", signature);
         
         Ok(signature)
     }
     
-    /// Convenience method to quote and execute a swap
+    /// Quote and execute a swap, re-quoting when the route expires before landing
+    #[allow(clippy::too_many_arguments)]
     pub async fn swap(
         &self,
         rpc_client: &RpcClient,
@@ -181,9 +260,33 @@ impl JupiterClient {
         output_mint: &Pubkey,
         amount: u64,
         slippage_bps: u16,
+        priority_fee_lamports: Option<u64>,
+        max_price_impact_pct: f64,
     ) -> Result<Signature> {
-        let quote = self.get_quote(input_mint, output_mint, amount, slippage_bps).await?;
-        self.execute_swap(rpc_client, payer, quote).await
+        let mut attempt = 1;
+        
+        loop {
+            // Quote failures (e.g. no route) are permanent, so they're never retried
+            let quote = self.get_quote(input_mint, output_mint, amount, slippage_bps).await?;
+            
+            match self
+                .execute_swap(rpc_client, payer, quote, slippage_bps, priority_fee_lamports, max_price_impact_pct)
+                .await
+            {
+                Ok(signature) => return Ok(signature),
+                Err(e) if attempt < self.max_swap_attempts && is_route_expired(&e) => {
+                    log::warn!(
+                        "Jupiter swap attempt {}/{} failed, re-quoting: {}",
+                        attempt,
+                        self.max_swap_attempts,
+                        e
+                    );
+                    attempt += 1;
+                    tokio::time::sleep(SWAP_RETRY_DELAY).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
     }
 }
 
@@ -193,11 +296,222 @@ impl Default for JupiterClient {
     }
 }
 
+/// Falls back to reqwest's defaults if the TLS backend can't be configured
+fn http_client(http: &HttpClientConfig) -> reqwest::Client {
+    http_client_builder(http).build().unwrap_or_else(|_| reqwest::Client::new())
+}
+
+/// Refuse quotes that would swap at a large price impact or accept more slippage than requested
+/// Jupiter reports `priceImpactPct` as a fraction, so "0.015" is a 1.5% impact
+fn check_quote(quote: &QuoteResponse, slippage_bps: u16, max_price_impact_pct: f64) -> Result<()> {
+    let price_impact: f64 = quote
+        .price_impact_pct
+        .parse()
+        .map_err(|_| anyhow!("Invalid Jupiter price impact: {}", quote.price_impact_pct))?;
+    let price_impact_pct = price_impact * 100.0;
+    if price_impact_pct > max_price_impact_pct {
+        return Err(anyhow!(
+            "Jupiter quote price impact {}% exceeds max {}%",
+            price_impact_pct,
+            max_price_impact_pct
+        ));
+    }
+    
+    // For ExactOut the threshold is the maximum input instead
+    if quote.swap_mode == "ExactIn" {
+        let out_amount: u64 = quote.out_amount.parse()?;
+        let min_out: u64 = quote.other_amount_threshold.parse()?;
+        let floor = out_amount as u128 * (10_000 - slippage_bps.min(10_000) as u128) / 10_000;
+        if out_amount == 0 || (min_out as u128) < floor {
+            return Err(anyhow!(
+                "Jupiter quote minimum out {} is below {} ({} bps under quoted out {})",
+                min_out,
+                floor,
+                slippage_bps,
+                out_amount
+            ));
+        }
+    }
+    
+    Ok(())
+}
+
+/// Whether a failed swap went stale (blockhash expired or price moved past the slippage)
+/// rather than failing for a reason a fresh quote can't fix
+fn is_route_expired(err: &anyhow::Error) -> bool {
+    if err.is::<BlockhashExpired>() {
+        return true;
+    }
+    let Some(client_error) = err.downcast_ref::<ClientError>() else {
+        return false;
+    };
+    match client_error.get_transaction_error() {
+        Some(TransactionError::InstructionError(_, InstructionError::Custom(code))) => {
+            code == SLIPPAGE_TOLERANCE_EXCEEDED
+        }
+        _ => is_blockhash_expired(client_error),
+    }
+}
+
+/// Whether a send failed because the transaction's blockhash expired
+fn is_blockhash_expired(err: &ClientError) -> bool {
+    match err.get_transaction_error() {
+        Some(err) => err == TransactionError::BlockhashNotFound,
+        // Expiry during confirmation surfaces as a plain RPC error
+        None => {
+            let message = err.to_string();
+            message.contains("block height exceeded") || message.contains("unable to confirm transaction")
+        }
+    }
+}
+
+/// Deserialize a Jupiter swap transaction (v0 or legacy) and sign it as the payer
+/// Jupiter already set a blockhash; `blockhash` replaces it when given
+fn sign_swap_transaction(
+    transaction_bytes: &[u8],
+    payer: &Keypair,
+    blockhash: Option<Hash>,
+) -> Result<VersionedTransaction> {
+    let mut transaction: VersionedTransaction = bincode::deserialize(transaction_bytes)
+        .map_err(|e| anyhow!("Failed to deserialize swap transaction: {}", e))?;
+    if let Some(blockhash) = blockhash {
+        transaction.message.set_recent_blockhash(blockhash);
+    }
+    
+    VersionedTransaction::try_new(transaction.message, &[payer])
+        .map_err(|e| anyhow!("Failed to sign swap transaction: {}", e))
+}
+
 /// Get USDC mint address for the given environment
+/// The beta and staging programs are deployed on mainnet, so they share its USDC
 pub fn get_usdc_mint(env: &str) -> Result<Pubkey> {
     match env {
-        "production" => Ok(Pubkey::from_str("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v")?),
+        "production" | "beta" | "staging" => Ok(Pubkey::from_str("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v")?),
         "devnet" => Ok(Pubkey::from_str("4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU")?),
         _ => Err(anyhow!("Unknown environment: {}", env)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{message::{v0, VersionedMessage}, system_instruction};
+    
+    #[test]
+    fn test_sign_swap_transaction_v0() {
+        let payer = Keypair::new();
+        let ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+        let message = v0::Message::try_compile(&payer.pubkey(), &[ix], &[], Hash::new_unique()).unwrap();
+        
+        // Jupiter returns the transaction with placeholder signatures
+        let unsigned = VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: VersionedMessage::V0(message),
+        };
+        let bytes = bincode::serialize(&unsigned).unwrap();
+        
+        let signed = sign_swap_transaction(&bytes, &payer, None).unwrap();
+        assert_ne!(signed.signatures[0], Signature::default());
+        assert!(signed.verify_with_results().iter().all(|ok| *ok));
+        
+        // A fresher blockhash replaces Jupiter's before signing
+        let blockhash = Hash::new_unique();
+        let signed = sign_swap_transaction(&bytes, &payer, Some(blockhash)).unwrap();
+        assert_eq!(*signed.message.recent_blockhash(), blockhash);
+        assert!(signed.verify_with_results().iter().all(|ok| *ok));
+    }
+    
+    #[test]
+    fn test_swap_request_serialization() {
+        let request = SwapRequest {
+            quote_response: QuoteResponse {
+                input_mint: String::new(),
+                output_mint: String::new(),
+                in_amount: "1".to_string(),
+                out_amount: "1".to_string(),
+                other_amount_threshold: "1".to_string(),
+                swap_mode: "ExactIn".to_string(),
+                price_impact_pct: "0".to_string(),
+            },
+            user_public_key: String::new(),
+            wrap_and_unwrap_sol: true,
+            as_legacy_transaction: false,
+            prioritization_fee_lamports: Some(5_000),
+        };
+        
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["asLegacyTransaction"], false);
+        assert_eq!(json["prioritizationFeeLamports"], 5_000);
+    }
+    
+    #[test]
+    fn test_with_config_attaches_api_key() {
+        let jupiter = JupiterClient::with_config("https://jupiter.example.com/v6/", Some("secret".to_string()));
+        assert_eq!(jupiter.api_url, "https://jupiter.example.com/v6");
+        
+        let request = jupiter.authorize(jupiter.client.get(&jupiter.api_url)).build().unwrap();
+        assert_eq!(request.headers()[API_KEY_HEADER], "secret");
+        
+        let public = JupiterClient::new();
+        let request = public.authorize(public.client.get(&public.api_url)).build().unwrap();
+        assert!(request.headers().get(API_KEY_HEADER).is_none());
+    }
+    
+    #[test]
+    fn test_is_route_expired() {
+        let tx_error = |e: TransactionError| anyhow::Error::from(ClientError::from(e));
+        
+        assert!(is_route_expired(&tx_error(TransactionError::BlockhashNotFound)));
+        assert!(is_route_expired(&tx_error(TransactionError::InstructionError(
+            3,
+            InstructionError::Custom(SLIPPAGE_TOLERANCE_EXCEEDED),
+        ))));
+        assert!(!is_route_expired(&tx_error(TransactionError::InstructionError(
+            3,
+            InstructionError::Custom(1),
+        ))));
+        assert!(!is_route_expired(&tx_error(TransactionError::InsufficientFundsForFee)));
+        assert!(!is_route_expired(&anyhow!("Jupiter API error (400): Could not find any route")));
+        assert!(is_route_expired(&BlockhashExpired(Hash::new_unique()).into()));
+        
+        assert_eq!(JupiterClient::new().with_max_swap_attempts(0).max_swap_attempts, 1);
+    }
+    
+    #[test]
+    fn test_get_usdc_mint_for_every_app() {
+        let mainnet = get_usdc_mint("production").unwrap();
+        assert_eq!(get_usdc_mint("beta").unwrap(), mainnet);
+        assert_eq!(get_usdc_mint("staging").unwrap(), mainnet);
+        assert_ne!(get_usdc_mint("devnet").unwrap(), mainnet);
+        assert!(get_usdc_mint("localnet").is_err());
+    }
+    
+    #[test]
+    fn test_check_quote() {
+        let quote = |out: &str, min_out: &str, impact: &str| QuoteResponse {
+            input_mint: String::new(),
+            output_mint: String::new(),
+            in_amount: "1000".to_string(),
+            out_amount: out.to_string(),
+            other_amount_threshold: min_out.to_string(),
+            swap_mode: "ExactIn".to_string(),
+            price_impact_pct: impact.to_string(),
+        };
+        
+        // Impact is a fraction: 0.2% passes a 1% cap, 1.5% doesn't
+        assert!(check_quote(&quote("1000000", "990000", "0.002"), 100, 1.0).is_ok());
+        assert!(check_quote(&quote("1000000", "990000", "0.015"), 100, 1.0)
+            .unwrap_err()
+            .to_string()
+            .contains("exceeds max"));
+        
+        // 100 bps of slippage floors the minimum out at 990000
+        assert!(check_quote(&quote("1000000", "989999", "0.002"), 100, 1.0)
+            .unwrap_err()
+            .to_string()
+            .contains("minimum out"));
+        assert!(check_quote(&quote("1000000", "989999", "0.002"), 200, 1.0).is_ok());
+        assert!(check_quote(&quote("0", "0", "0"), 100, 1.0).is_err());
+        assert!(check_quote(&quote("1000000", "990000", "n/a"), 100, 1.0).is_err());
+    }
+}
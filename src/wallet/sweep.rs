@@ -0,0 +1,117 @@
+use anyhow::Result;
+use log::{info, warn};
+use solana_sdk::{pubkey::Pubkey, signature::{Keypair, Signer}};
+use std::collections::{HashMap, HashSet};
+
+use crate::models::market::SlippageConfig;
+use crate::rpc::SolendRpcClient;
+use crate::wallet::balance::MintDecimalsCache;
+use crate::wallet::rebalance::BaseToken;
+use crate::wallet::swap::JupiterClient;
+
+/// Token balances worth sweeping: non-zero, not the base token, not kept, not wrapped SOL
+/// Jupiter wraps swaps from the native mint out of the SOL fee balance, so wSOL is never swept
+fn sweep_candidates(
+    balances: &[(Pubkey, u64)],
+    base_mint: &Pubkey,
+    keep_mints: &HashSet<Pubkey>,
+) -> Vec<(Pubkey, u64)> {
+    balances
+        .iter()
+        .filter(|(mint, amount)| {
+            *amount > 0
+                && mint != base_mint
+                && *mint != spl_token::native_mint::ID
+                && !keep_mints.contains(mint)
+        })
+        .copied()
+        .collect()
+}
+
+/// Swap every token balance worth at least `min_sweep_usd` into the rebalancing base token
+/// Balances are valued in the base token, so it should be a dollar stablecoin
+/// Mints in `keep_mints` (e.g. rebalancing targets) are left alone
+/// Returns the number of tokens swept; individual swap failures are logged and skipped
+#[allow(clippy::too_many_arguments)]
+pub async fn sweep_dust(
+    client: &SolendRpcClient,
+    payer: &Keypair,
+    base: &BaseToken,
+    mint_decimals: &MintDecimalsCache,
+    jupiter: &JupiterClient,
+    symbols: &HashMap<Pubkey, String>, // mint -> symbol
+    keep_mints: &HashSet<Pubkey>,
+    min_sweep_usd: f64,
+    slippage: &SlippageConfig,
+    priority_fee_lamports: Option<u64>,
+    max_price_impact_pct: f64,
+) -> Result<usize> {
+    let base_unit = 10f64.powi(mint_decimals.get(client, &base.mint).await? as i32);
+
+    // Several accounts may hold the same mint
+    let mut totals: HashMap<Pubkey, u64> = HashMap::new();
+    for (_, account) in client.get_token_accounts_by_owner(&payer.pubkey())? {
+        *totals.entry(account.mint).or_default() += account.amount;
+    }
+    let balances: Vec<(Pubkey, u64)> = totals.into_iter().collect();
+
+    let mut swept = 0;
+    for (mint, amount) in sweep_candidates(&balances, &base.mint, keep_mints) {
+        let symbol = symbols.get(&mint).cloned().unwrap_or_else(|| mint.to_string());
+        let slippage_bps = slippage.bps_for(&symbol);
+
+        // The quote doubles as the price check
+        let value_usd = match jupiter.get_quote(&mint, &base.mint, amount, slippage_bps).await {
+            Ok(quote) => quote.out_amount.parse::<u64>().unwrap_or(0) as f64 / base_unit,
+            Err(e) => {
+                warn!("    ✗ Not sweeping {}: no price quote available: {}", symbol, e);
+                continue;
+            }
+        };
+
+        if value_usd < min_sweep_usd {
+            continue;
+        }
+
+        info!("  Sweeping {} {} (~${:.2}) to {}", amount, symbol, value_usd, base.symbol);
+        match jupiter.swap(
+            client.client(),
+            payer,
+            &mint,
+            &base.mint,
+            amount,
+            slippage_bps,
+            priority_fee_lamports,
+            max_price_impact_pct,
+        ).await {
+            Ok(sig) => {
+                info!("    ✓ Swept {} (sig: {})", symbol, sig);
+                swept += 1;
+            }
+            Err(e) => warn!("    ✗ Failed to sweep {}: {}", symbol, e),
+        }
+    }
+
+    Ok(swept)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sweep_candidates_skip_base_wsol_and_kept_mints() {
+        let base = Pubkey::new_unique();
+        let (kept, dust, empty) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let balances = vec![
+            (base, 5_000_000),
+            (spl_token::native_mint::ID, 1_000_000_000),
+            (kept, 10),
+            (dust, 42),
+            (empty, 0),
+        ];
+
+        let candidates = sweep_candidates(&balances, &base, &HashSet::from([kept]));
+        assert_eq!(candidates, vec![(dust, 42)]);
+    }
+}
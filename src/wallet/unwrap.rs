@@ -2,9 +2,32 @@ use anyhow::{anyhow, Result};
 use log::{info, warn};
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
-    signature::{Keypair, Signature},
+    signature::{Keypair, Signature, Signer},
+    transaction::Transaction,
 };
+use std::str::FromStr;
+
+use crate::models::market::KTokenReserve;
+use crate::wallet::balance::find_associated_token_address;
+
+/// Kamino Lend program ID
+pub const KAMINO_LEND_PROGRAM_ID: &str = "KLend2g3cP87fffoy8q1mQqGKjrxjC8boSyAYavgmjD";
+
+/// Kamino Lend reserve offsets (after the 8-byte Anchor discriminator)
+const KAMINO_RESERVE_LENDING_MARKET_OFFSET: usize = 32;
+const KAMINO_RESERVE_LIQUIDITY_MINT_OFFSET: usize = 128;
+const KAMINO_RESERVE_LIQUIDITY_SUPPLY_OFFSET: usize = 160;
+
+/// Oracle accounts in the reserve's `config.token_info`, as `refresh_reserve` expects them
+const KAMINO_RESERVE_SCOPE_PRICES_OFFSET: usize = 5112;
+const KAMINO_RESERVE_SWITCHBOARD_PRICE_OFFSET: usize = 5160;
+const KAMINO_RESERVE_SWITCHBOARD_TWAP_OFFSET: usize = 5192;
+const KAMINO_RESERVE_PYTH_PRICE_OFFSET: usize = 5224;
+
+/// SPL token account size
+const TOKEN_ACCOUNT_SIZE: u64 = 165;
 
 /// Types of wrapped tokens we support
 #[derive(Debug, Clone, Copy)]
@@ -15,48 +38,341 @@ pub enum WrappedTokenType {
 }
 
 /// Unwrap a specific token
+/// `reserve` is the protocol account that redeems the wrapped mint
 pub async fn unwrap_token(
-    _client: &RpcClient,
-    _payer: &Keypair,
+    client: &RpcClient,
+    payer: &Keypair,
     token_mint: &Pubkey,
     token_type: WrappedTokenType,
+    amount: u64,
+    reserve: &Pubkey,
 ) -> Result<Signature> {
-    // TODO: Implement actual unwrapping logic for each token type
-    // This would require the specific program IDs and instruction formats
-    warn!("Token unwrapping not yet fully implemented for {:?} token: {}", token_type, token_mint);
-    Err(anyhow!("Token unwrapping not yet fully implemented"))
-}
-
-/// Unwrap all wrapped tokens in wallet
-/// Note: This is a placeholder implementation
-/// Full implementation would require:
-/// 1. Querying wallet for all token accounts
-/// 2. Identifying which are wrapped tokens
-/// 3. Calling the appropriate unwrap program for each
+    match token_type {
+        WrappedTokenType::Kamino => unwrap_ktoken(client, payer, token_mint, amount, reserve),
+        WrappedTokenType::Basis | WrappedTokenType::Nazare => {
+            warn!("Token unwrapping not yet implemented for {:?} token: {}", token_type, token_mint);
+            Err(anyhow!("Token unwrapping not yet implemented for {:?}", token_type))
+        }
+    }
+}
+
+/// Redeem kTokens for the underlying liquidity through Kamino Lend
+fn unwrap_ktoken(
+    client: &RpcClient,
+    payer: &Keypair,
+    ktoken_mint: &Pubkey,
+    amount: u64,
+    reserve: &Pubkey,
+) -> Result<Signature> {
+    let program_id = Pubkey::from_str(KAMINO_LEND_PROGRAM_ID)?;
+
+    let reserve_account = client
+        .get_account(reserve)
+        .map_err(|e| anyhow!("Failed to get Kamino reserve {}: {}", reserve, e))?;
+
+    if reserve_account.owner != program_id {
+        return Err(anyhow!("Reserve {} is not owned by Kamino Lend", reserve));
+    }
+
+    // Liquidity may live under Token-2022, so use the mint's owning program
+    let liquidity_mint = read_pubkey(&reserve_account.data, KAMINO_RESERVE_LIQUIDITY_MINT_OFFSET)?;
+    let liquidity_token_program = client
+        .get_account(&liquidity_mint)
+        .map_err(|e| anyhow!("Failed to get liquidity mint {}: {}", liquidity_mint, e))?
+        .owner;
+
+    let instructions = unwrap_ktoken_instructions(
+        &program_id,
+        &payer.pubkey(),
+        ktoken_mint,
+        amount,
+        reserve,
+        &reserve_account.data,
+        &liquidity_token_program,
+    )?;
+
+    let recent_blockhash = client.get_latest_blockhash()?;
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &[payer],
+        recent_blockhash,
+    );
+
+    let signature = client.send_and_confirm_transaction(&transaction)?;
+    info!("Unwrapped {} kTokens ({}), signature: {}", amount, ktoken_mint, signature);
+
+    Ok(signature)
+}
+
+/// Pubkey stored at `offset` of a Kamino reserve
+fn read_pubkey(reserve_data: &[u8], offset: usize) -> Result<Pubkey> {
+    let bytes = reserve_data
+        .get(offset..offset + 32)
+        .ok_or_else(|| anyhow!("Kamino reserve data too small"))?;
+    Ok(Pubkey::new_from_array(bytes.try_into()?))
+}
+
+/// Create the liquidity ATA, refresh the reserve, then redeem `amount` kTokens
+/// Kamino Lend rejects redeeming from a reserve that wasn't refreshed in the same slot
+fn unwrap_ktoken_instructions(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    ktoken_mint: &Pubkey,
+    amount: u64,
+    reserve: &Pubkey,
+    reserve_data: &[u8],
+    liquidity_token_program: &Pubkey,
+) -> Result<Vec<Instruction>> {
+    let lending_market = read_pubkey(reserve_data, KAMINO_RESERVE_LENDING_MARKET_OFFSET)?;
+    let liquidity_mint = read_pubkey(reserve_data, KAMINO_RESERVE_LIQUIDITY_MINT_OFFSET)?;
+    let liquidity_supply = read_pubkey(reserve_data, KAMINO_RESERVE_LIQUIDITY_SUPPLY_OFFSET)?;
+
+    // Unconfigured oracles are passed as the program ID, Anchor's "none" for optional accounts
+    let oracle = |offset: usize| -> Result<Pubkey> {
+        let oracle = read_pubkey(reserve_data, offset)?;
+        Ok(if oracle == Pubkey::default() { *program_id } else { oracle })
+    };
+    let refresh_ix = refresh_reserve_instruction(
+        program_id,
+        reserve,
+        &lending_market,
+        &oracle(KAMINO_RESERVE_PYTH_PRICE_OFFSET)?,
+        &oracle(KAMINO_RESERVE_SWITCHBOARD_PRICE_OFFSET)?,
+        &oracle(KAMINO_RESERVE_SWITCHBOARD_TWAP_OFFSET)?,
+        &oracle(KAMINO_RESERVE_SCOPE_PRICES_OFFSET)?,
+    );
+
+    let (lending_market_authority, _) =
+        Pubkey::find_program_address(&[b"lma", lending_market.as_ref()], program_id);
+
+    let user_source_collateral = find_associated_token_address(owner, ktoken_mint, &spl_token::ID);
+    let user_destination_liquidity = find_associated_token_address(owner, &liquidity_mint, liquidity_token_program);
+
+    let create_ata_ix = spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+        owner,
+        owner,
+        &liquidity_mint,
+        liquidity_token_program,
+    );
+
+    let redeem_ix = redeem_reserve_collateral_instruction(
+        program_id,
+        amount,
+        owner,
+        &lending_market,
+        reserve,
+        &lending_market_authority,
+        &liquidity_mint,
+        ktoken_mint,
+        &liquidity_supply,
+        &user_source_collateral,
+        &user_destination_liquidity,
+        liquidity_token_program,
+    );
+
+    Ok(vec![create_ata_ix, refresh_ix, redeem_ix])
+}
+
+/// Create Kamino Lend refresh_reserve instruction
+fn refresh_reserve_instruction(
+    program_id: &Pubkey,
+    reserve: &Pubkey,
+    lending_market: &Pubkey,
+    pyth_oracle: &Pubkey,
+    switchboard_price_oracle: &Pubkey,
+    switchboard_twap_oracle: &Pubkey,
+    scope_prices: &Pubkey,
+) -> Instruction {
+    // Anchor discriminator: first 8 bytes of sha256("global:refresh_reserve")
+    let data = solana_sdk::hash::hash(b"global:refresh_reserve").to_bytes()[..8].to_vec();
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*reserve, false),
+            AccountMeta::new_readonly(*lending_market, false),
+            AccountMeta::new_readonly(*pyth_oracle, false),
+            AccountMeta::new_readonly(*switchboard_price_oracle, false),
+            AccountMeta::new_readonly(*switchboard_twap_oracle, false),
+            AccountMeta::new_readonly(*scope_prices, false),
+        ],
+        data,
+    }
+}
+
+/// Create Kamino Lend redeem_reserve_collateral instruction
+#[allow(clippy::too_many_arguments)]
+fn redeem_reserve_collateral_instruction(
+    program_id: &Pubkey,
+    collateral_amount: u64,
+    owner: &Pubkey,
+    lending_market: &Pubkey,
+    reserve: &Pubkey,
+    lending_market_authority: &Pubkey,
+    reserve_liquidity_mint: &Pubkey,
+    reserve_collateral_mint: &Pubkey,
+    reserve_liquidity_supply: &Pubkey,
+    user_source_collateral: &Pubkey,
+    user_destination_liquidity: &Pubkey,
+    liquidity_token_program: &Pubkey,
+) -> Instruction {
+    // Anchor discriminator: first 8 bytes of sha256("global:redeem_reserve_collateral")
+    let mut data = solana_sdk::hash::hash(b"global:redeem_reserve_collateral").to_bytes()[..8].to_vec();
+    data.extend_from_slice(&collateral_amount.to_le_bytes());
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new_readonly(*lending_market, false),
+            AccountMeta::new(*reserve, false),
+            AccountMeta::new_readonly(*lending_market_authority, false),
+            AccountMeta::new_readonly(*reserve_liquidity_mint, false),
+            AccountMeta::new(*reserve_collateral_mint, false),
+            AccountMeta::new(*reserve_liquidity_supply, false),
+            AccountMeta::new(*user_source_collateral, false),
+            AccountMeta::new(*user_destination_liquidity, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(*liquidity_token_program, false),
+            AccountMeta::new_readonly(solana_sdk::sysvar::instructions::id(), false),
+        ],
+        data,
+    }
+}
+
+/// Unwrap all known wrapped tokens in wallet
+/// Enumerates the wallet's SPL token accounts and redeems every known
+/// kToken with a non-zero balance
 pub async fn unwrap_all_wrapped_tokens(
-    _client: &RpcClient,
-    _payer: &Keypair,
+    client: &RpcClient,
+    payer: &Keypair,
+    ktokens: &[KTokenReserve],
 ) -> Result<()> {
-    // Placeholder - in production, this would:
-    // 1. Get all token accounts for the wallet
-    // 2. Check each mint against known wrapped token mints
-    // 3. Call unwrap_token for each wrapped token found
-    
-    info!("Token unwrapping feature is available but not yet fully implemented");
-    info!("  To enable: implement detection and unwrapping logic for specific wrapped tokens");
-    
+    use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+    use solana_sdk::program_pack::Pack;
+    use spl_token::state::Account as TokenAccount;
+
+    if ktokens.is_empty() {
+        return Ok(());
+    }
+
+    // SPL token accounts store the owner at offset 32
+    let filters = vec![
+        RpcFilterType::DataSize(TOKEN_ACCOUNT_SIZE),
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(32, payer.pubkey().as_ref())),
+    ];
+
+    let accounts = client.get_program_accounts_with_config(
+        &spl_token::id(),
+        solana_client::rpc_config::RpcProgramAccountsConfig {
+            filters: Some(filters),
+            account_config: solana_client::rpc_config::RpcAccountInfoConfig {
+                encoding: Some(solana_account_decoder::UiAccountEncoding::Base64),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )?;
+
+    for (_, account) in accounts {
+        let token_account = match TokenAccount::unpack(&account.data) {
+            Ok(token_account) => token_account,
+            Err(_) => continue,
+        };
+
+        if token_account.amount == 0 {
+            continue;
+        }
+
+        let mint = token_account.mint.to_string();
+        let Some(ktoken) = ktokens.iter().find(|k| k.mint == mint) else {
+            continue;
+        };
+
+        let reserve = match Pubkey::from_str(&ktoken.reserve) {
+            Ok(reserve) => reserve,
+            Err(e) => {
+                warn!("Invalid Kamino reserve {} for {}: {}", ktoken.reserve, mint, e);
+                continue;
+            }
+        };
+
+        info!("Unwrapping {} kTokens ({})", token_account.amount, mint);
+
+        if let Err(e) = unwrap_token(
+            client,
+            payer,
+            &token_account.mint,
+            WrappedTokenType::Kamino,
+            token_account.amount,
+            &reserve,
+        ).await {
+            warn!("Failed to unwrap {}: {}", mint, e);
+        }
+    }
+
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+
+    #[test]
+    fn test_unwrap_refreshes_the_reserve_before_redeeming() {
+        let program_id = Pubkey::from_str(KAMINO_LEND_PROGRAM_ID).unwrap();
+        let (owner, ktoken_mint, reserve) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (lending_market, liquidity_mint, liquidity_supply) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (scope_prices, pyth_price) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        // Switchboard is left unconfigured
+        let mut reserve_data = vec![0u8; KAMINO_RESERVE_PYTH_PRICE_OFFSET + 32];
+        for (offset, pubkey) in [
+            (KAMINO_RESERVE_LENDING_MARKET_OFFSET, lending_market),
+            (KAMINO_RESERVE_LIQUIDITY_MINT_OFFSET, liquidity_mint),
+            (KAMINO_RESERVE_LIQUIDITY_SUPPLY_OFFSET, liquidity_supply),
+            (KAMINO_RESERVE_SCOPE_PRICES_OFFSET, scope_prices),
+            (KAMINO_RESERVE_PYTH_PRICE_OFFSET, pyth_price),
+        ] {
+            reserve_data[offset..offset + 32].copy_from_slice(pubkey.as_ref());
+        }
+
+        let instructions = unwrap_ktoken_instructions(
+            &program_id, &owner, &ktoken_mint, 42, &reserve, &reserve_data, &spl_token::id(),
+        )
+        .unwrap();
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(instructions[0].program_id, spl_associated_token_account::id());
+
+        let refresh = &instructions[1];
+        assert_eq!(refresh.program_id, program_id);
+        assert_eq!(refresh.data, solana_sdk::hash::hash(b"global:refresh_reserve").to_bytes()[..8]);
+        let accounts: Vec<Pubkey> = refresh.accounts.iter().map(|meta| meta.pubkey).collect();
+        assert_eq!(accounts, vec![reserve, lending_market, pyth_price, program_id, program_id, scope_prices]);
+        assert!(refresh.accounts[0].is_writable);
+
+        let redeem = &instructions[2];
+        assert_eq!(redeem.program_id, program_id);
+        assert_eq!(redeem.accounts[2].pubkey, reserve);
+        assert_eq!(redeem.accounts[6].pubkey, liquidity_supply);
+        assert_eq!(redeem.accounts[8].pubkey, find_associated_token_address(&owner, &liquidity_mint, &spl_token::id()));
+
+        assert!(unwrap_ktoken_instructions(&program_id, &owner, &ktoken_mint, 42, &reserve, &[0; 64], &spl_token::id())
+            .is_err());
+    }
+
     #[test]
-    fn test_wrapped_token_types() {
-        // Just verify the enum exists and can be used
-        let _basis = WrappedTokenType::Basis;
-        let _kamino = WrappedTokenType::Kamino;
-        let _nazare = WrappedTokenType::Nazare;
+    fn test_redeem_reserve_collateral_instruction() {
+        let program_id = Pubkey::from_str(KAMINO_LEND_PROGRAM_ID).unwrap();
+        let key = Pubkey::new_unique();
+        let ix = redeem_reserve_collateral_instruction(
+            &program_id, 42, &key, &key, &key, &key, &key, &key, &key, &key, &key, &spl_token::id(),
+        );
+
+        assert_eq!(ix.accounts.len(), 12);
+        assert!(ix.accounts[0].is_signer);
+        assert_eq!(&ix.data[8..], &42u64.to_le_bytes());
     }
 }
@@ -0,0 +1,132 @@
+use anyhow::{anyhow, Result};
+use log::info;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+use spl_token::native_mint;
+
+use crate::wallet::balance::find_associated_token_address;
+
+/// SPL token account size
+const TOKEN_ACCOUNT_SIZE: usize = 165;
+
+/// The wallet's wSOL associated token account
+pub fn wsol_account(owner: &Pubkey) -> Pubkey {
+    find_associated_token_address(owner, &native_mint::ID, &spl_token::ID)
+}
+
+/// Create the wSOL ATA if needed, fund it with `lamports` and sync its token balance
+pub fn wrap_sol_instructions(owner: &Pubkey, lamports: u64) -> Result<Vec<Instruction>> {
+    let ata = wsol_account(owner);
+
+    Ok(vec![
+        spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+            owner,
+            owner,
+            &native_mint::ID,
+            &spl_token::ID,
+        ),
+        system_instruction::transfer(owner, &ata, lamports),
+        spl_token::instruction::sync_native(&spl_token::ID, &ata)?,
+    ])
+}
+
+/// Close the wSOL ATA, returning its balance and rent to the owner as native SOL
+pub fn close_wsol_instruction(owner: &Pubkey) -> Result<Instruction> {
+    Ok(spl_token::instruction::close_account(
+        &spl_token::ID,
+        &wsol_account(owner),
+        owner,
+        owner,
+        &[],
+    )?)
+}
+
+/// Lamports that can be wrapped without dipping into the reserve kept for fees
+/// or the rent of a wSOL account that doesn't exist yet
+fn wrappable_lamports(native_lamports: u64, keep_lamports: u64, account_rent: u64) -> u64 {
+    native_lamports.saturating_sub(keep_lamports).saturating_sub(account_rent)
+}
+
+/// Wrap up to `shortfall` lamports of native SOL so a SOL repay can be covered
+/// `keep_lamports` stay native for fees; returns the amount wrapped, 0 if nothing could be
+pub fn wrap_sol_for_repay(
+    client: &RpcClient,
+    payer: &Keypair,
+    shortfall: u64,
+    keep_lamports: u64,
+) -> Result<u64> {
+    let owner = payer.pubkey();
+    let native_lamports = client.get_balance(&owner)?;
+    let account_rent = if client.get_account(&wsol_account(&owner)).is_ok() {
+        0
+    } else {
+        client.get_minimum_balance_for_rent_exemption(TOKEN_ACCOUNT_SIZE)?
+    };
+
+    let lamports = shortfall.min(wrappable_lamports(native_lamports, keep_lamports, account_rent));
+    if lamports == 0 {
+        return Ok(0);
+    }
+
+    let signature = send(client, payer, &wrap_sol_instructions(&owner, lamports)?)?;
+    info!("  Wrapped {} lamports of SOL (sig: {})", lamports, signature);
+
+    Ok(lamports)
+}
+
+/// Unwrap everything in the wSOL ATA back to native SOL and recover its rent
+pub fn close_wsol_account(client: &RpcClient, payer: &Keypair) -> Result<Signature> {
+    let signature = send(client, payer, &[close_wsol_instruction(&payer.pubkey())?])?;
+    info!("  Closed wSOL account (sig: {})", signature);
+
+    Ok(signature)
+}
+
+fn send(client: &RpcClient, payer: &Keypair, instructions: &[Instruction]) -> Result<Signature> {
+    let recent_blockhash = client.get_latest_blockhash()?;
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&payer.pubkey()),
+        &[payer],
+        recent_blockhash,
+    );
+
+    client
+        .send_and_confirm_transaction(&transaction)
+        .map_err(|e| anyhow!("wSOL transaction failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_sol_instructions_fund_and_sync_the_ata() {
+        let owner = Pubkey::new_unique();
+        let ata = wsol_account(&owner);
+        let ixs = wrap_sol_instructions(&owner, 1_000).unwrap();
+
+        assert_eq!(ixs.len(), 3);
+        assert_eq!(ixs[0].program_id, spl_associated_token_account::ID);
+        assert_eq!(ixs[1].program_id, solana_sdk::system_program::id());
+        assert_eq!(ixs[1].accounts[1].pubkey, ata);
+        assert_eq!(ixs[2].program_id, spl_token::ID);
+        assert_eq!(ixs[2].accounts[0].pubkey, ata);
+
+        let close = close_wsol_instruction(&owner).unwrap();
+        assert_eq!(close.accounts[0].pubkey, ata);
+        assert_eq!(close.accounts[1].pubkey, owner);
+    }
+
+    #[test]
+    fn test_wrappable_lamports_keeps_fee_reserve_and_rent() {
+        assert_eq!(wrappable_lamports(1_000_000, 100_000, 2_000), 898_000);
+        assert_eq!(wrappable_lamports(50_000, 100_000, 0), 0);
+    }
+}
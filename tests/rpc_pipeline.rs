@@ -0,0 +1,259 @@
+//! Fetch/parse pipeline against a mock JSON-RPC server
+//! Each test stands up its own server with canned responses and points `SolendRpcClient` at it
+
+use base64::Engine;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+use solend_liquidator::models::obligation::{LENDING_MARKET_OFFSET, OBLIGATION_SIZE};
+use solend_liquidator::models::{LiquidityToken, MarketConfig, MarketConfigReserve};
+use solend_liquidator::oracle::{self, NULL_ORACLE};
+use solend_liquidator::rpc::SolendRpcClient;
+use solend_liquidator::utils::get_program_id;
+
+/// Pyth V2 price account: magic/version/type header, expo at 20, EMA price at 48,
+/// aggregate price/conf/status/slot from 208
+fn pyth_account(price: i64, conf: u64, expo: i32) -> Vec<u8> {
+    let mut data = vec![0u8; 3312];
+    data[0..4].copy_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+    data[4..8].copy_from_slice(&2u32.to_le_bytes());
+    data[8..12].copy_from_slice(&3u32.to_le_bytes()); // Price account
+    data[20..24].copy_from_slice(&expo.to_le_bytes());
+    data[48..56].copy_from_slice(&price.to_le_bytes());
+    data[208..216].copy_from_slice(&price.to_le_bytes());
+    data[216..224].copy_from_slice(&conf.to_le_bytes());
+    data[224..228].copy_from_slice(&1u32.to_le_bytes()); // Trading
+    data[232..240].copy_from_slice(&1234u64.to_le_bytes());
+    data
+}
+
+/// Pyth pull `PriceUpdateV2` account, fully verified and posted at slot 1250
+fn pyth_pull_account(price: i64, conf: u64, exponent: i32) -> Vec<u8> {
+    let mut data = oracle::pyth::PRICE_UPDATE_V2_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(Pubkey::new_unique().as_ref());
+    data.push(1); // VerificationLevel::Full
+    data.extend_from_slice(&[0u8; 32]);
+    data.extend_from_slice(&price.to_le_bytes());
+    data.extend_from_slice(&conf.to_le_bytes());
+    data.extend_from_slice(&exponent.to_le_bytes());
+    data.extend_from_slice(&[0u8; 16]); // publish times
+    data.extend_from_slice(&price.to_le_bytes());
+    data.extend_from_slice(&conf.to_le_bytes());
+    data.extend_from_slice(&1250u64.to_le_bytes());
+    data.resize(134, 0);
+    data
+}
+
+/// Obligation with no positions, last refreshed at `slot` (0 = never initialized)
+fn obligation_account(lending_market: &Pubkey, slot: u64) -> Vec<u8> {
+    let mut data = vec![0u8; OBLIGATION_SIZE];
+    data[0] = 1;
+    data[1..9].copy_from_slice(&slot.to_le_bytes());
+    data[LENDING_MARKET_OFFSET..LENDING_MARKET_OFFSET + 32].copy_from_slice(lending_market.as_ref());
+    data
+}
+
+fn ui_account(data: &[u8]) -> Value {
+    json!({
+        "data": [base64::engine::general_purpose::STANDARD.encode(data), "base64"],
+        "executable": false,
+        "lamports": 1_000_000,
+        "owner": get_program_id("production").unwrap().to_string(),
+        "rentEpoch": 0,
+        "space": data.len(),
+    })
+}
+
+fn rpc_result(result: Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({ "jsonrpc": "2.0", "result": result, "id": 1 }))
+}
+
+fn with_context(value: Value) -> Value {
+    json!({ "context": { "slot": 1 }, "value": value })
+}
+
+/// Answers `getMultipleAccounts` from a fixed set of accounts; unknown keys are null
+struct AccountsResponder(HashMap<String, Vec<u8>>);
+
+impl Respond for AccountsResponder {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let body: Value = serde_json::from_slice(&request.body).unwrap();
+        let accounts: Vec<Value> = body["params"][0]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|key| match self.0.get(key.as_str().unwrap()) {
+                Some(data) => ui_account(data),
+                None => Value::Null,
+            })
+            .collect();
+        rpc_result(with_context(Value::Array(accounts)))
+    }
+}
+
+fn mock_rpc(rpc_method: &str, response: impl Respond + 'static) -> Mock {
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": rpc_method })))
+        .respond_with(response)
+}
+
+/// Server answering the `getVersion` probe the client makes before filtered or batched reads
+async fn mock_server() -> MockServer {
+    let server = MockServer::start().await;
+    mock_rpc("getVersion", rpc_result(json!({ "solana-core": "1.18.26" }))).mount(&server).await;
+    server
+}
+
+fn client(server: &MockServer) -> SolendRpcClient {
+    SolendRpcClient::new(&server.uri(), "production")
+        .unwrap()
+        .with_retry_policy(1, 0)
+}
+
+/// Requests the server received for one RPC method
+async fn received(server: &MockServer, rpc_method: &str) -> Vec<Value> {
+    server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| serde_json::from_slice::<Value>(&request.body).unwrap())
+        .filter(|body| body["method"] == rpc_method)
+        .collect()
+}
+
+fn market_with_oracle(pyth_oracle: &Pubkey) -> MarketConfig {
+    MarketConfig {
+        name: "main".to_string(),
+        is_primary: true,
+        description: String::new(),
+        creator: String::new(),
+        address: Pubkey::new_unique().to_string(),
+        authority_address: String::new(),
+        owner: String::new(),
+        reserves: vec![MarketConfigReserve {
+            liquidity_token: LiquidityToken {
+                coingecko_id: String::new(),
+                decimals: 9,
+                logo: String::new(),
+                mint: Pubkey::new_unique().to_string(),
+                name: "Solana".to_string(),
+                symbol: "SOL".to_string(),
+                volume24h: String::new(),
+            },
+            pyth_oracle: pyth_oracle.to_string(),
+            switchboard_oracle: NULL_ORACLE.to_string(),
+            address: Pubkey::new_unique().to_string(),
+            collateral_mint_address: String::new(),
+            collateral_supply_address: String::new(),
+            liquidity_address: String::new(),
+            liquidity_fee_receiver_address: String::new(),
+            user_supply_cap: 0,
+        }],
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_obligations_filters_by_market_and_skips_unusable_accounts() {
+    let server = mock_server().await;
+    let market = Pubkey::new_unique();
+    let (live, uninitialized, truncated) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+
+    let accounts = json!([
+        { "pubkey": live.to_string(), "account": ui_account(&obligation_account(&market, 250_000_000)) },
+        { "pubkey": uninitialized.to_string(), "account": ui_account(&obligation_account(&market, 0)) },
+        { "pubkey": truncated.to_string(), "account": ui_account(&[1u8; 64]) },
+    ]);
+    mock_rpc("getProgramAccounts", rpc_result(accounts)).mount(&server).await;
+
+    let obligations = client(&server).get_obligations(&market.to_string()).unwrap();
+    assert_eq!(obligations.len(), 1);
+    assert_eq!(obligations[0].0, live);
+    assert_eq!(obligations[0].1.lending_market, market);
+
+    // Only this market's full-size obligations are requested
+    let requests = received(&server, "getProgramAccounts").await;
+    assert_eq!(requests.len(), 1);
+    let filters = requests[0]["params"][1]["filters"].to_string();
+    assert!(filters.contains(&format!("\"dataSize\":{}", OBLIGATION_SIZE)));
+    assert!(filters.contains(&market.to_string()));
+    assert!(filters.contains(&format!("\"offset\":{}", LENDING_MARKET_OFFSET)));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_multiple_accounts_batched_splits_requests_in_order() {
+    let server = mock_server().await;
+    let pubkeys: Vec<Pubkey> = (0..150).map(|_| Pubkey::new_unique()).collect();
+    let known: HashMap<String, Vec<u8>> = pubkeys
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| i % 3 == 0)
+        .map(|(i, pubkey)| (pubkey.to_string(), vec![i as u8; 8]))
+        .collect();
+    mock_rpc("getMultipleAccounts", AccountsResponder(known)).mount(&server).await;
+
+    let accounts = client(&server).get_multiple_accounts_batched(&pubkeys, 100).await.unwrap();
+
+    let requests = received(&server, "getMultipleAccounts").await;
+    let batch_sizes: Vec<usize> = requests.iter().map(|r| r["params"][0].as_array().unwrap().len()).collect();
+    assert_eq!(batch_sizes, vec![100, 50]);
+
+    assert_eq!(accounts.len(), pubkeys.len());
+    for (i, account) in accounts.iter().enumerate() {
+        match account {
+            Some(account) => assert_eq!(account.data, vec![i as u8; 8]),
+            None => assert_ne!(i % 3, 0),
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_oracle_prices_parse_from_fetched_pyth_accounts() {
+    let server = mock_server().await;
+    let feed = Pubkey::new_unique();
+    let data = pyth_account(15_000_000_000, 10_000_000, -8);
+
+    mock_rpc("getMultipleAccounts", AccountsResponder(HashMap::from([(feed.to_string(), data.clone())])))
+        .mount(&server)
+        .await;
+    mock_rpc("getAccountInfo", rpc_result(with_context(ui_account(&data)))).mount(&server).await;
+    // Published at slot 1234, well within the staleness limit
+    mock_rpc("getSlot", rpc_result(json!(1_300))).mount(&server).await;
+
+    let client = client(&server);
+    let market = market_with_oracle(&feed);
+
+    // Batched market-wide fetch
+    let prices = oracle::get_tokens_oracle_data(&client, &market, 0.02, 5.0, &Default::default()).await.unwrap();
+    let sol = &prices["SOL"];
+    assert_eq!(sol.price, Decimal::from(150));
+    assert_eq!(sol.confidence, Decimal::new(1, 1));
+    assert!(sol.trusted);
+
+    // Single-reserve fetch through getAccountInfo
+    let single = oracle::pyth::get_token_oracle_data(&client, &market.reserves[0]).await.unwrap();
+    assert_eq!(single.price, Decimal::from(150));
+    assert_eq!(received(&server, "getAccountInfo").await.len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_oracle_prices_parse_from_pyth_pull_accounts() {
+    let server = mock_server().await;
+    let feed = Pubkey::new_unique();
+    let data = pyth_pull_account(2_500_000, 1_000, -6);
+    
+    mock_rpc("getMultipleAccounts", AccountsResponder(HashMap::from([(feed.to_string(), data)])))
+        .mount(&server)
+        .await;
+    mock_rpc("getSlot", rpc_result(json!(1_300))).mount(&server).await;
+    
+    let prices = oracle::get_tokens_oracle_data(&client(&server), &market_with_oracle(&feed), 0.02, 5.0, &Default::default())
+        .await
+        .unwrap();
+    assert_eq!(prices["SOL"].price, Decimal::new(25, 1));
+    assert_eq!(prices["SOL"].confidence, Decimal::new(1, 3));
+}